@@ -1,18 +1,11 @@
-use archive_cracker::{Args, CrackError, cli::Charset, crack_archive};
+use archive_cracker::{CrackConfig, CrackError, cli::Charset, crack_archive};
+use std::time::Duration;
 
-fn main() {
+fn main() -> Result<(), CrackError> {
     // 示例 1: 只使用字典攻击 + 默认 1-5 位暴力破解
-    let args = Args {
-        archive_path: "test.zip".to_string(),
-        dictionary: None,
-        charset: vec![Charset::Lower, Charset::Upper, Charset::Digit],
-        length: None,
-        max_length: None,
-        min_length: 1,
-        skip_dictionary: false,
-    };
+    let config = CrackConfig::builder("test.zip").build()?;
 
-    match crack_archive(&args) {
+    match crack_archive(&config) {
         Ok(success) => {
             println!("✅ 密码找到: {}", success.password);
             println!("   耗时: {:.2}秒", success.elapsed_secs);
@@ -28,33 +21,27 @@ fn main() {
     }
 
     // 示例 2: 指定固定长度
-    let args2 = Args {
-        archive_path: "test.zip".to_string(),
-        dictionary: None,
-        charset: vec![Charset::Digit],
-        length: Some(4), // 只破解 4 位数字
-        max_length: None,
-        min_length: 1,
-        skip_dictionary: false,
-    };
+    let config2 = CrackConfig::builder("test.zip")
+        .charsets([Charset::Digit])
+        .length(4) // 只破解 4 位数字
+        .build()?;
 
-    if let Ok(success) = crack_archive(&args2) {
+    if let Ok(success) = crack_archive(&config2) {
         println!("第二次尝试成功: {}", success.password);
     }
 
-    // 示例 3: 指定范围
-    let args3 = Args {
-        archive_path: "test.zip".to_string(),
-        dictionary: Some("/path/to/custom.txt".to_string()),
-        charset: vec![Charset::Lower, Charset::Digit],
-        length: None,
-        max_length: Some(6), // 破解 1-6 位
-        min_length: 1,
-        skip_dictionary: false,
-    };
+    // 示例 3: 指定范围，最多运行 10 分钟
+    let config3 = CrackConfig::builder("test.zip")
+        .dictionary("/path/to/custom.txt")
+        .dictionary("/path/to/wordlists")
+        .charsets([Charset::Lower, Charset::Digit])
+        .length_range(1, 6) // 破解 1-6 位
+        .max_time(Duration::from_mins(10))
+        .build()?;
 
-    match crack_archive(&args3) {
+    match crack_archive(&config3) {
         Ok(success) => println!("第三次尝试成功: {}", success.password),
         Err(e) => println!("第三次尝试失败: {e}"),
     }
+    Ok(())
 }
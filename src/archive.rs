@@ -1,11 +1,18 @@
 pub mod common;
-mod zip;
 mod sevenz;
+mod zip;
 
-pub use self::zip::ZipHandler;
+pub(crate) use self::sevenz::SEVENZ_KEY_CYCLES_POWER;
 pub use self::sevenz::SevenZHandler;
+pub use self::zip::ZipHandler;
 
+use serde::{Deserialize, Serialize};
+use std::io;
 use std::path::Path;
+use std::sync::Arc;
+
+use crate::extract::Extractor;
+use crate::rewrap::Rewrapper;
 
 /// 压缩包格式
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -16,9 +23,8 @@ pub enum ArchiveFormat {
 
 impl ArchiveFormat {
     /// 从文件路径自动检测格式
-    #[must_use] 
-    pub fn detect(path: &str) -> Option<Self> {
-        let path = Path::new(path);
+    #[must_use]
+    pub fn detect(path: &Path) -> Option<Self> {
         let ext = path.extension()?.to_str()?.to_lowercase();
 
         match ext.as_str() {
@@ -29,31 +35,368 @@ impl ArchiveFormat {
     }
 }
 
+/// 压缩包无法读取或解析
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum ArchiveError {
+    #[error("无法读取压缩包: {0}")]
+    Io(#[source] Arc<io::Error>),
+
+    #[error("无法解析 {format} 压缩包: {message}")]
+    Corrupt {
+        format: &'static str,
+        message: String,
+    },
+
+    #[error("不支持的压缩包: {0}")]
+    Unsupported(String),
+}
+
+impl From<io::Error> for ArchiveError {
+    fn from(e: io::Error) -> Self {
+        Self::Io(Arc::new(e))
+    }
+}
+
+/// 尝试一个密码的结果
+#[derive(Debug, Clone)]
+pub enum TryOutcome {
+    /// 密码错误
+    Rejected,
+    /// 通过了快速检查（如校验字节、解压成功）但内容检查未通过，多为校验字节碰撞的错误密码；
+    /// 也包括内容检查通过但压缩包没有 CRC 可供完整校验的候选（7z 可以不保存 CRC）
+    ///
+    /// `confidence` 为 0 到 1 之间的估计值，越大越可能是正确密码（如目标文件本身已损坏）
+    Candidate { confidence: f32 },
+    /// 内容检查通过，但完整解压后 CRC32 或 AES 认证码（HMAC-SHA1）校验失败，附带失败原因
+    ///
+    /// 文件头恰好被识别的错误密码，或目标文件本身已损坏
+    Unconfirmed(String),
+    /// 内容检查和完整校验都通过，即正确密码
+    Verified,
+    /// 读取压缩包出错（如网络存储断开），之后的候选也无法测试
+    IoError(Arc<io::Error>),
+    /// 使用了不支持的加密或压缩方式，或压缩包已损坏
+    Unsupported(String),
+}
+
+impl TryOutcome {
+    /// 是否为正确密码
+    #[must_use]
+    pub const fn is_verified(&self) -> bool {
+        matches!(self, Self::Verified)
+    }
+
+    /// 需要中止攻击的错误：继续测试其他候选没有意义
+    #[must_use]
+    pub fn fatal_error(&self) -> Option<ArchiveError> {
+        match self {
+            Self::IoError(e) => Some(ArchiveError::Io(Arc::clone(e))),
+            Self::Unsupported(message) => Some(ArchiveError::Unsupported(message.clone())),
+            Self::Rejected | Self::Candidate { .. } | Self::Unconfirmed(_) | Self::Verified => None,
+        }
+    }
+}
+
+impl From<io::Error> for TryOutcome {
+    fn from(e: io::Error) -> Self {
+        Self::IoError(Arc::new(e))
+    }
+}
+
+/// 目标文件的验证方式，按条目扩展名选择（排在前面的更快更可靠，检测目标时优先）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Verification {
+    /// 与已知内容比较（[`Verifier`]），由用户指定而非按扩展名选择
+    Known,
+    /// infer 库检测文件魔数是否匹配扩展名
+    Magic,
+    /// 文本：能按 UTF-8 或 GBK 解码、可打印字符比例高，并符合格式的基本结构（如 JSON 以 `{` 或 `[` 开头）
+    Text,
+    /// 其他类型：完整解压后校验 CRC32（错误密码通常在解压时就失败）
+    Checksum,
+}
+
+impl Verification {
+    /// 扩展名（小写）对应的验证方式
+    #[must_use]
+    pub fn for_extension(ext: Option<&str>) -> Self {
+        match ext {
+            Some(ext) if common::is_infer_supported(ext) => Self::Magic,
+            Some(ext) if common::is_verifiable_text(ext) => Self::Text,
+            _ => Self::Checksum,
+        }
+    }
+
+    /// 简短名称（用于表格）
+    #[must_use]
+    pub const fn label(self) -> &'static str {
+        match self {
+            Self::Known => "已知内容",
+            Self::Magic => "文件头",
+            Self::Text => "文本",
+            Self::Checksum => "仅 CRC",
+        }
+    }
+}
+
+impl std::fmt::Display for Verification {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let description = match self {
+            Self::Known => "与已知内容比较，通过后完整解压校验 CRC",
+            Self::Magic => "infer 库检测解密后内容是否匹配扩展名，通过后完整解压校验 CRC",
+            Self::Text => "检查解密后内容是否为可读文本，通过后完整解压校验 CRC",
+            Self::Checksum => "完整解压并校验 CRC（较慢）",
+        };
+        f.write_str(description)
+    }
+}
+
+/// 自定义验证函数：参数为解密后的条目开头
+pub type VerifyFn = dyn Fn(&[u8]) -> bool + Send + Sync;
+
+/// 用已知内容验证解密后的条目开头（如固定的文件头、公司信笺模板）
+#[derive(Clone)]
+pub enum Verifier {
+    /// 条目开头必须与这些字节完全一致
+    Prefix(Vec<u8>),
+    /// 自定义验证函数：收到条目开头最多 `len` 字节（条目较短时为整个条目），返回是否匹配
+    Custom { len: usize, check: Arc<VerifyFn> },
+}
+
+impl Verifier {
+    /// 自定义验证函数，`len` 为需要的条目开头字节数
+    pub fn custom(len: usize, check: impl Fn(&[u8]) -> bool + Send + Sync + 'static) -> Self {
+        Self::Custom {
+            len,
+            check: Arc::new(check),
+        }
+    }
+
+    /// 需要读取的条目开头字节数
+    #[must_use]
+    pub const fn head_len(&self) -> usize {
+        match self {
+            Self::Prefix(prefix) => prefix.len(),
+            Self::Custom { len, .. } => *len,
+        }
+    }
+
+    /// 检查条目开头 `head` 是否匹配
+    #[must_use]
+    pub fn matches(&self, head: &[u8]) -> bool {
+        match self {
+            Self::Prefix(prefix) => head == prefix.as_slice(),
+            Self::Custom { check, .. } => check(head),
+        }
+    }
+}
+
+impl std::fmt::Debug for Verifier {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Prefix(prefix) => f
+                .debug_tuple("Prefix")
+                .field(&common::to_hex(prefix))
+                .finish(),
+            Self::Custom { len, .. } => f
+                .debug_struct("Custom")
+                .field("len", len)
+                .finish_non_exhaustive(),
+        }
+    }
+}
+
+impl PartialEq for Verifier {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Self::Prefix(a), Self::Prefix(b)) => a == b,
+            (Self::Custom { len: a, check: f }, Self::Custom { len: b, check: g }) => {
+                a == b && Arc::ptr_eq(f, g)
+            }
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Verifier {}
+
+/// 序列化为十六进制字符串，自定义验证函数无法序列化
+impl Serialize for Verifier {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Self::Prefix(prefix) => serializer.serialize_str(&common::to_hex(prefix)),
+            Self::Custom { .. } => Err(serde::ser::Error::custom("自定义验证函数无法序列化")),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Verifier {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let hex = String::deserialize(deserializer)?;
+        common::from_hex(&hex)
+            .map(Self::Prefix)
+            .ok_or_else(|| serde::de::Error::custom(format!("无效的十六进制内容: {hex}")))
+    }
+}
+
 /// 目标文件信息
 #[derive(Debug, Clone)]
 pub struct TargetFile {
     pub index: usize,
     pub name: String,
+    /// 扩展名（小写），没有扩展名时为空
     pub extension: String,
+    pub verification: Verification,
+    /// 用户提供的已知内容（`verification` 为 [`Verification::Known`] 时）
+    pub verifier: Option<Verifier>,
+}
+
+impl TargetFile {
+    /// 根据条目名称选择验证方式
+    #[must_use]
+    pub fn new(index: usize, name: String) -> Self {
+        let extension = common::get_extension(&name);
+        Self {
+            index,
+            verification: Verification::for_extension(extension.as_deref()),
+            extension: extension.unwrap_or_default(),
+            name,
+            verifier: None,
+        }
+    }
+
+    /// 改用已知内容验证
+    #[must_use]
+    pub fn with_verifier(self, verifier: Verifier) -> Self {
+        Self {
+            verification: Verification::Known,
+            verifier: Some(verifier),
+            ..self
+        }
+    }
+
+    /// 攻击信息中的目标文件说明（多行）
+    #[must_use]
+    pub fn describe(&self) -> String {
+        format!(
+            "检测到目标文件: {} (索引 {})\n文件扩展名: .{}\n验证方式: {}",
+            self.name, self.index, self.extension, self.verification
+        )
+    }
+}
+
+/// 压缩包中的文件条目（用于选择验证条目）
+#[derive(Debug, Clone)]
+pub struct EntryInfo {
+    pub index: usize,
+    pub name: String,
+    /// 解压后大小
+    pub size: u64,
+    /// 加密方式（如 `ZipCrypto`、`AES-256`，7z 附带数据块序号），未加密时为 `None`
+    ///
+    /// 加密方式不同的条目一定使用不同的密钥，按条目破解时据此分组
+    pub encryption: Option<String>,
+}
+
+impl EntryInfo {
+    #[must_use]
+    pub const fn is_encrypted(&self) -> bool {
+        self.encryption.is_some()
+    }
+}
+
+/// 从 `entries` 中选择验证条目：只选加密的非空文件，优先验证较快的条目，同类中选最小的
+///
+/// 未加密的条目任何密码都能解压，不能用于验证
+#[must_use]
+pub fn pick_target(entries: impl IntoIterator<Item = EntryInfo>) -> Option<TargetFile> {
+    entries
+        .into_iter()
+        .filter(|entry| entry.size > 0 && entry.is_encrypted())
+        .map(|entry| {
+            let target = TargetFile::new(entry.index, entry.name);
+            ((target.verification, entry.size), target)
+        })
+        .min_by(|(a, _), (b, _)| a.cmp(b))
+        .map(|(_, target)| target)
+}
+
+/// 压缩包元数据（用于收集候选密码）
+#[derive(Debug, Clone, Default)]
+pub struct ArchiveMetadata {
+    /// 压缩包注释
+    pub comment: String,
+    /// 所有条目名称（含目录）
+    pub entry_names: Vec<String>,
+    /// 未加密文本条目 (名称, 内容)
+    pub text_entries: Vec<(String, String)>,
 }
 
 /// 压缩包处理器 trait
 pub trait ArchiveHandler: Send + Sync {
-    /// 检测压缩包中的目标文件
-    fn detect_target(&self, path: &str) -> Option<TargetFile>;
+    /// 列出所有文件条目（不含目录）
+    fn entries(&self, path: &Path) -> Result<Vec<EntryInfo>, ArchiveError>;
+
+    /// 检测压缩包中的目标文件，没有可验证的条目时返回 `Ok(None)`
+    ///
+    /// 只选加密条目，按验证方式优先魔数、文本、CRC，同类中选最小的
+    /// （确认阶段需要完整解压；7z 固实压缩时前面的数据也要解压），跳过空文件
+    fn detect_target(&self, path: &Path) -> Result<Option<TargetFile>, ArchiveError> {
+        Ok(pick_target(self.entries(path)?))
+    }
+
+    /// 用 `password` 解压条目，通过 `extractor` 写入目标目录（未加密的条目忽略密码）
+    ///
+    /// 单个条目解压失败时记录原因后继续，压缩包无法打开时返回错误
+    fn extract(
+        &self,
+        path: &Path,
+        password: &str,
+        extractor: &mut Extractor,
+    ) -> Result<(), ArchiveError>;
+
+    /// 用各条目的原密码解密，写入 `rewrapper` 指定的新压缩包（同一格式，必须不存在）
+    ///
+    /// 任何条目失败都返回错误，调用方负责删除不完整的新压缩包
+    fn rewrap(&self, path: &Path, rewrapper: &mut Rewrapper) -> Result<(), ArchiveError>;
 
     /// 获取文件数量
-    fn file_count(&self, path: &str) -> Result<usize, String>;
+    fn file_count(&self, path: &Path) -> Result<usize, ArchiveError>;
 
-    /// 尝试密码
-    fn try_password(&self, path: &str, password: &str, target: &TargetFile) -> bool;
+    /// 读取压缩包元数据（注释、条目名、未加密文本条目内容）
+    fn metadata(&self, path: &Path) -> Option<ArchiveMetadata>;
+
+    /// 计算压缩包指纹：对加密参数（盐、校验字节、首个加密块等）做 SHA-256
+    ///
+    /// 同一压缩包的副本（即使改名）指纹相同，用于在 potfile 中查找已破解的密码
+    fn fingerprint(&self, path: &Path) -> Option<String>;
+
+    /// 尝试密码并验证目标文件内容
+    fn try_password(&self, path: &Path, password: &str, target: &TargetFile) -> TryOutcome;
+
+    /// 依次验证所有目标条目，候选必须全部通过
+    ///
+    /// 通过了前面的条目却未通过后面的条目时返回 [`TryOutcome::Unconfirmed`]
+    fn try_targets(&self, path: &Path, password: &str, targets: &[TargetFile]) -> TryOutcome {
+        for (i, target) in targets.iter().enumerate() {
+            match self.try_password(path, password, target) {
+                TryOutcome::Verified => {}
+                TryOutcome::Rejected | TryOutcome::Candidate { .. } if i > 0 => {
+                    return TryOutcome::Unconfirmed(format!("未通过条目 {} 的验证", target.name));
+                }
+                outcome => return outcome,
+            }
+        }
+        TryOutcome::Verified
+    }
 
     /// 格式名称
     fn format_name(&self) -> &'static str;
 }
 
 /// 获取对应格式的处理器
-#[must_use] 
+#[must_use]
 pub fn get_handler(format: ArchiveFormat) -> Box<dyn ArchiveHandler> {
     match format {
         ArchiveFormat::Zip => Box::new(ZipHandler),
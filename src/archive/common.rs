@@ -1,3 +1,13 @@
+use std::io::{self, Read};
+
+use super::{TargetFile, TryOutcome, Verification, Verifier};
+
+/// 内容检查读取的条目开头字节数
+const HEAD_SIZE: usize = 8192;
+
+/// 文本验证要求的最低可打印字符比例
+const MIN_PRINTABLE_RATIO: f64 = 0.95;
+
 /// 从文件名提取扩展名（小写）
 #[must_use]
 pub fn get_extension(filename: &str) -> Option<String> {
@@ -35,3 +45,269 @@ pub fn verify_content(data: &[u8], expected_ext: &str) -> bool {
     }
     infer::get(data).is_some_and(|kind| kind.extension() == expected_ext)
 }
+
+/// 按目标文件的验证方式检查解密后的条目数据 `reader`
+///
+/// 返回 `Err` 表示读取或解压失败（包括 CRC 校验失败），由调用方按格式区分读取错误和错误密码
+pub fn verify_entry(reader: &mut dyn Read, target: &TargetFile) -> io::Result<TryOutcome> {
+    if target.verifier.is_none() && target.verification == Verification::Checksum {
+        // 没有可检查的内容，完整解压后由解压库校验 CRC
+        io::copy(reader, &mut io::sink())?;
+        return Ok(TryOutcome::Verified);
+    }
+
+    let len = target
+        .verifier
+        .as_ref()
+        .map_or(HEAD_SIZE, Verifier::head_len);
+    let mut head = Vec::with_capacity(len);
+    reader.take(len as u64).read_to_end(&mut head)?;
+    let matched = match (&target.verifier, target.verification) {
+        (Some(verifier), _) => verifier.matches(&head),
+        (None, Verification::Magic) => verify_content(&head, &target.extension),
+        (None, _) => verify_text(&head, &target.extension),
+    };
+    if !matched {
+        return Ok(TryOutcome::Candidate { confidence: 0.5 });
+    }
+    Ok(confirm(reader))
+}
+
+/// 检查条目开头是否为可读文本：能按 UTF-8 或 GBK 解码（允许末尾截断的字符）、可打印字符比例足够高，
+/// 且符合扩展名对应格式的基本结构
+#[must_use]
+#[allow(clippy::cast_precision_loss)]
+pub fn verify_text(data: &[u8], ext: &str) -> bool {
+    let data = data.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(data);
+    let Some(text) = decode_strict(data) else {
+        return false;
+    };
+    let total = text.chars().count();
+    if total == 0 {
+        return false;
+    }
+    let printable = text
+        .chars()
+        .filter(|c| !c.is_control() || matches!(c, '\n' | '\r' | '\t'))
+        .count();
+    if (printable as f64) < total as f64 * MIN_PRINTABLE_RATIO {
+        return false;
+    }
+
+    let text = text.trim_start();
+    match ext {
+        "json" => text.starts_with(['{', '[']),
+        "xml" | "htm" | "html" | "svg" => text.starts_with('<'),
+        // 字幕序号
+        "srt" => text.starts_with(|c: char| c.is_ascii_digit()),
+        _ => true,
+    }
+}
+
+/// 严格解码：UTF-8 或 GBK 中的任一种完全合法时返回文本，末尾被截断的多字节字符忽略
+fn decode_strict(data: &[u8]) -> Option<std::borrow::Cow<'_, str>> {
+    match std::str::from_utf8(data) {
+        Ok(text) => return Some(text.into()),
+        // 截断在多字节字符中间
+        Err(e) if e.error_len().is_none() => {
+            return std::str::from_utf8(&data[..e.valid_up_to()])
+                .ok()
+                .map(Into::into);
+        }
+        Err(_) => {}
+    }
+    let gbk = encoding_rs::GBK;
+    gbk.decode_without_bom_handling_and_without_replacement(data)
+        .or_else(|| {
+            let (_, rest) = data.split_last()?;
+            gbk.decode_without_bom_handling_and_without_replacement(rest)
+        })
+}
+
+/// 确认阶段：读完条目剩余的数据，由解压库在结尾校验 CRC32（ZIP AE-2 为 HMAC-SHA1 认证码）
+///
+/// 只在内容检查通过后调用，用于排除文件头恰好被识别的错误密码
+#[must_use]
+pub fn confirm(reader: &mut dyn Read) -> TryOutcome {
+    match io::copy(reader, &mut io::sink()) {
+        Ok(_) => TryOutcome::Verified,
+        Err(e) if e.raw_os_error().is_some() => e.into(),
+        Err(e) => TryOutcome::Unconfirmed(e.to_string()),
+    }
+}
+
+/// 可按文本验证的扩展名：说明文件之外还包括常见的数据、字幕和源代码文件
+#[must_use]
+pub fn is_verifiable_text(ext: &str) -> bool {
+    const TEXT: &[&str] = &[
+        "csv", "tsv", "json", "xml", "svg", "srt", "ass", "vtt", "yaml", "yml", "toml", "py", "js",
+        "ts", "c", "h", "cpp", "rs", "go", "java", "sh", "bat", "sql", "css", "tex",
+    ];
+    is_text_extension(ext) || TEXT.contains(&ext)
+}
+
+/// 可作为文本读取的扩展名（用于收集未加密说明文件中的密码线索）
+#[must_use]
+pub fn is_text_extension(ext: &str) -> bool {
+    const TEXT: &[&str] = &[
+        "txt", "nfo", "md", "url", "htm", "html", "ini", "diz", "log",
+    ];
+    TEXT.contains(&ext)
+}
+
+/// 可作为文本读取的文件名：扩展名为文本类型，或是没有扩展名的 README、LICENSE 等说明文件
+///
+/// 只检查路径最后一部分，不区分大小写
+#[must_use]
+pub fn is_text_file_name(name: &str) -> bool {
+    const NAMES: &[&str] = &[
+        "readme",
+        "license",
+        "licence",
+        "copying",
+        "notes",
+        "password",
+        "passwords",
+    ];
+    let file_name = name.rsplit(['/', '\\']).next().unwrap_or(name);
+    if file_name.contains('.') {
+        get_extension(file_name).is_some_and(|ext| is_text_extension(&ext))
+    } else {
+        NAMES
+            .iter()
+            .any(|text| file_name.eq_ignore_ascii_case(text))
+    }
+}
+
+/// 读取说明类文本条目的最大字节数
+pub const MAX_TEXT_ENTRY_SIZE: u64 = 64 * 1024;
+
+/// 解码文本：优先 UTF-8（去除 BOM），否则按 GBK 解码
+#[must_use]
+pub fn decode_text(data: &[u8]) -> String {
+    let data = data.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(data);
+    std::str::from_utf8(data).map_or_else(
+        |_| encoding_rs::GBK.decode(data).0.into_owned(),
+        ToString::to_string,
+    )
+}
+
+/// 通配符匹配：`*` 匹配任意多个字符（包括 `/`），`?` 匹配一个字符
+#[must_use]
+pub fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // 最近一个 `*` 的位置及其当前匹配到的名称位置，失配时回溯
+    let mut star = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((star_p, star_n)) => {
+                    p = star_p + 1;
+                    n = star_n + 1;
+                    star = Some((star_p, star_n + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// 指纹中每个加密条目读取的原始数据字节数
+pub const FINGERPRINT_HEAD_SIZE: u64 = 64;
+
+/// 指纹最多包含的加密条目数
+pub const MAX_FINGERPRINT_ENTRIES: usize = 16;
+
+/// 解析十六进制字符串（忽略空白和 `0x` 前缀），格式无效时返回 `None`
+#[must_use]
+pub fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    let hex = hex.trim();
+    let digits: Vec<u8> = hex
+        .strip_prefix("0x")
+        .unwrap_or(hex)
+        .bytes()
+        .filter(|b| !b.is_ascii_whitespace())
+        .collect();
+    // from_str_radix 接受 `+` 号，需要先逐个检查
+    if !digits.len().is_multiple_of(2) || !digits.iter().all(u8::is_ascii_hexdigit) {
+        return None;
+    }
+    digits
+        .chunks(2)
+        .map(|pair| u8::from_str_radix(std::str::from_utf8(pair).ok()?, 16).ok())
+        .collect()
+}
+
+/// 字节转小写十六进制字符串
+#[must_use]
+pub fn to_hex(bytes: &[u8]) -> String {
+    use std::fmt::Write;
+    bytes
+        .iter()
+        .fold(String::with_capacity(bytes.len() * 2), |mut s, b| {
+            let _ = write!(s, "{b:02x}");
+            s
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wildcard_matches_literal_names() {
+        assert!(wildcard_match("a.txt", "a.txt"));
+        assert!(!wildcard_match("a.txt", "a.txt2"));
+        assert!(!wildcard_match("a.txt", "b.txt"));
+        assert!(wildcard_match("", ""));
+        assert!(!wildcard_match("", "a"));
+    }
+
+    #[test]
+    fn wildcard_star_and_question_mark() {
+        assert!(wildcard_match("*.txt", "a.txt"));
+        assert!(wildcard_match("*.txt", "dir/sub/a.txt"));
+        assert!(wildcard_match("*", ""));
+        assert!(wildcard_match("a*b*c", "aXXbYYbZc"));
+        assert!(!wildcard_match("a*b*c", "aXXbYY"));
+        assert!(wildcard_match("?.jpg", "图.jpg"));
+        assert!(!wildcard_match("?.jpg", "ab.jpg"));
+        assert!(wildcard_match("**a", "bba"));
+        assert!(!wildcard_match("*.TXT", "a.txt"));
+    }
+
+    #[test]
+    fn from_hex_accepts_prefix_and_whitespace() {
+        assert_eq!(from_hex("0a1B"), Some(vec![0x0a, 0x1b]));
+        assert_eq!(
+            from_hex(" 0xde ad\nbe ef "),
+            Some(vec![0xde, 0xad, 0xbe, 0xef])
+        );
+        assert_eq!(from_hex(""), Some(Vec::new()));
+    }
+
+    #[test]
+    fn from_hex_rejects_invalid_input() {
+        assert_eq!(from_hex("abc"), None);
+        assert_eq!(from_hex("zz"), None);
+        assert_eq!(from_hex("+1"), None);
+        assert_eq!(from_hex("é1"), None);
+    }
+
+    #[test]
+    fn hex_round_trip() {
+        let bytes: Vec<u8> = (0..=255).collect();
+        assert_eq!(from_hex(&to_hex(&bytes)), Some(bytes));
+    }
+}
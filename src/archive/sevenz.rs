@@ -1,106 +1,424 @@
+use sevenz_rust::{
+    AesEncoderOptions, BlockDecoder, SevenZArchiveEntry, SevenZMethod, SevenZWriter,
+};
+use sha2::{Digest, Sha256};
 use std::fs::File;
-use std::sync::Arc;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::io::{self, BufWriter, Read, Seek, SeekFrom};
+use std::path::Path;
 
-use super::common::{get_extension, is_infer_supported, verify_content};
-use super::{ArchiveHandler, TargetFile};
+use super::common::{
+    FINGERPRINT_HEAD_SIZE, MAX_TEXT_ENTRY_SIZE, decode_text, is_text_file_name, to_hex,
+    verify_entry,
+};
+use super::{ArchiveError, ArchiveHandler, ArchiveMetadata, EntryInfo, TargetFile, TryOutcome};
+use crate::extract::Extractor;
+use crate::rewrap::{NewEncryption, Rewrapper};
 
 pub struct SevenZHandler;
 
+/// 收集元数据时，固实块中说明文件之前最多解压的数据量
+const MAX_TEXT_PREFIX_SIZE: u64 = 16 * 1024 * 1024;
+
+/// 新建 7z 加密数据时的密钥派生强度（2^19 次 SHA-256，与 7-Zip 默认相同；sevenz-rust 默认只有 2^8 次）
+pub const SEVENZ_KEY_CYCLES_POWER: u8 = 19;
+
+/// 读取文件头（不带密码，文件头加密时失败）
+fn read_header(path: &Path) -> Result<sevenz_rust::Archive, ArchiveError> {
+    let mut file = File::open(path)?;
+    let len = file.metadata()?.len();
+    sevenz_rust::Archive::read(&mut file, len, &[]).map_err(|e| ArchiveError::Corrupt {
+        format: "7z",
+        message: match e {
+            sevenz_rust::Error::PasswordRequired | sevenz_rust::Error::MaybeBadPassword(_) => {
+                "文件头已加密".to_string()
+            }
+            sevenz_rust::Error::Io(e, _) => e.to_string(),
+            e => format!("{e:?}"),
+        },
+    })
+}
+
+/// 用密码读取文件头（文件头加密时需要正确的密码）
+fn read_with_password(
+    file: &mut File,
+    password: &sevenz_rust::Password,
+) -> Result<sevenz_rust::Archive, ArchiveError> {
+    let len = file.metadata()?.len();
+    sevenz_rust::Archive::read(file, len, password.as_slice()).map_err(corrupt)
+}
+
+fn corrupt(e: impl std::fmt::Debug) -> ArchiveError {
+    ArchiveError::Corrupt {
+        format: "7z",
+        message: format!("{e:?}"),
+    }
+}
+
+/// 复制条目的名称、类型、时间和属性，大小和校验和由写入时重新计算
+fn copy_entry(entry: &SevenZArchiveEntry) -> SevenZArchiveEntry {
+    let mut copy = SevenZArchiveEntry::new();
+    copy.name.clone_from(&entry.name);
+    copy.is_directory = entry.is_directory;
+    copy.is_anti_item = entry.is_anti_item;
+    copy.has_creation_date = entry.has_creation_date;
+    copy.has_last_modified_date = entry.has_last_modified_date;
+    copy.has_access_date = entry.has_access_date;
+    copy.creation_date = entry.creation_date;
+    copy.last_modified_date = entry.last_modified_date;
+    copy.access_date = entry.access_date;
+    copy.has_windows_attributes = entry.has_windows_attributes;
+    copy.windows_attributes = entry.windows_attributes;
+    copy
+}
+
 impl ArchiveHandler for SevenZHandler {
-    fn detect_target(&self, path: &str) -> Option<TargetFile> {
-        let mut file = File::open(path).ok()?;
-        let len = file.metadata().ok()?.len();
-        let archive = sevenz_rust::Archive::read(&mut file, len, &[]).ok()?;
+    fn entries(&self, path: &Path) -> Result<Vec<EntryInfo>, ArchiveError> {
+        let archive = read_header(path)?;
+        Ok(archive
+            .files
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| !entry.is_directory())
+            .map(|(i, entry)| EntryInfo {
+                index: i,
+                name: entry.name().to_string(),
+                // 没有数据流的条目为空文件
+                size: if entry.has_stream() { entry.size() } else { 0 },
+                encryption: archive.stream_map.file_folder_index[i]
+                    .filter(|&folder| is_encrypted(&archive, folder))
+                    .map(|folder| format!("AES-256 (数据块 {folder})")),
+            })
+            .collect())
+    }
 
-        // 收集所有可识别魔数的文件，选择最小的一个
-        // 7z 解压时即使跳过写入，固实压缩仍需处理前面的数据
-        // 选择最小文件可以减少验证时读取的数据量
-        let mut candidates: Vec<(usize, String, String, u64)> = Vec::new();
+    fn extract(
+        &self,
+        path: &Path,
+        password: &str,
+        extractor: &mut Extractor,
+    ) -> Result<(), ArchiveError> {
+        let mut file = File::open(path)?;
+        // 7z 的密码按 UTF-16LE 编码
+        let password = sevenz_rust::Password::from(password);
+        let archive = read_with_password(&mut file, &password)?;
 
+        // 目录和没有数据流的空文件不在任何数据块中
         for (i, entry) in archive.files.iter().enumerate() {
             if entry.is_directory() {
-                continue;
+                extractor.create_dir(i, entry.name());
+            } else if archive.stream_map.file_folder_index[i].is_none()
+                && let Some(dest) = extractor.begin(i, entry.name(), 0, None)
+            {
+                extractor.write(entry.name(), &dest, &mut io::empty());
             }
+        }
 
-            let name = entry.name();
-            if let Some(ext) = get_extension(name)
-                && is_infer_supported(&ext)
+        for folder_index in 0..archive.folders.len() {
+            let first = archive.stream_map.folder_first_file_index[folder_index];
+            let count = archive.folders[folder_index].num_unpack_sub_streams;
+            let indexes = first..first + count;
+            // 跳过没有需要解压的条目的数据块
+            if extractor.full()
+                || !indexes
+                    .clone()
+                    .any(|i| extractor.wants(i, archive.files[i].name()))
             {
-                candidates.push((i, name.to_string(), ext, entry.size()));
+                continue;
+            }
+
+            // 数据块中的条目按顺序依次传入，固实块中跳过的条目也要读完
+            let mut index = first;
+            let decoder = sevenz_rust::BlockDecoder::new(
+                folder_index,
+                &archive,
+                password.as_slice(),
+                &mut file,
+            );
+            let result = decoder.for_each_entries(&mut |entry, reader| {
+                let i = index;
+                index += 1;
+                if !entry.is_directory()
+                    && let Some(dest) = extractor.begin(i, entry.name(), entry.size(), None)
+                {
+                    extractor.write(entry.name(), &dest, reader);
+                }
+                io::copy(reader, &mut io::sink()).map_err(sevenz_rust::Error::io)?;
+                Ok(true)
+            });
+            // 数据块解压失败（如密码错误）时，其中尚未解压的条目都记为跳过
+            if let Err(e) = result {
+                for i in index..indexes.end {
+                    let name = archive.files[i].name();
+                    if extractor.wants(i, name) {
+                        extractor.skip(name, format!("{e:?}"));
+                    }
+                }
             }
         }
+        Ok(())
+    }
 
-        // 按文件大小排序，选择最小的
-        candidates.sort_by_key(|(_, _, _, size)| *size);
+    fn rewrap(&self, path: &Path, rewrapper: &mut Rewrapper) -> Result<(), ArchiveError> {
+        let options = rewrapper.options();
+        if let Some(encryption) = options.encryption
+            && encryption != NewEncryption::Aes256
+        {
+            return Err(ArchiveError::Unsupported(format!(
+                "7z 不支持 {} 加密，只能使用 AES-256",
+                encryption.name()
+            )));
+        }
+        let mut file = File::open(path)?;
+        let archive = read_with_password(
+            &mut file,
+            &sevenz_rust::Password::from(rewrapper.password(0)),
+        )?;
 
-        candidates
-            .into_iter()
-            .next()
-            .map(|(index, name, extension, _)| TargetFile {
-                index,
-                name,
-                extension,
-            })
+        let output = File::create_new(rewrapper.output())?;
+        let mut writer = SevenZWriter::new(BufWriter::new(output)).map_err(corrupt)?;
+        // 设置了新密码时数据和文件头都用 AES-256 加密，每个条目单独成块（不使用固实压缩）
+        if let Some((password, _)) = options.new_password() {
+            let mut aes = AesEncoderOptions::new(password.into());
+            aes.num_cycles_power = SEVENZ_KEY_CYCLES_POWER;
+            writer.set_content_methods(vec![aes.into(), SevenZMethod::LZMA2.into()]);
+        }
+
+        // 目录和没有数据流的空文件
+        for (i, entry) in archive.files.iter().enumerate() {
+            if archive.stream_map.file_folder_index[i].is_none() {
+                writer
+                    .push_archive_entry::<&[u8]>(copy_entry(entry), None)
+                    .map_err(corrupt)?;
+                rewrapper.record(0);
+            }
+        }
+        // 数据块中的条目按顺序解密，边读边写入新压缩包
+        for folder_index in 0..archive.folders.len() {
+            let first = archive.stream_map.folder_first_file_index[folder_index];
+            let password = sevenz_rust::Password::from(rewrapper.password(first));
+            let decoder = sevenz_rust::BlockDecoder::new(
+                folder_index,
+                &archive,
+                password.as_slice(),
+                &mut file,
+            );
+            decoder
+                .for_each_entries(&mut |entry, reader| {
+                    writer.push_archive_entry(copy_entry(entry), Some(reader))?;
+                    rewrapper.record(entry.size());
+                    Ok(true)
+                })
+                .map_err(corrupt)?;
+        }
+        writer.finish()?;
+        Ok(())
     }
 
-    fn file_count(&self, path: &str) -> Result<usize, String> {
-        let mut file = File::open(path).map_err(|_| "无法打开 7z 文件")?;
-        let len = file.metadata().map_err(|_| "无法获取文件大小")?.len();
-        let archive = sevenz_rust::Archive::read(&mut file, len, &[])
-            .map_err(|_| "无法解析 7z 文件（可能已加密）")?;
-        Ok(archive.files.len())
+    fn file_count(&self, path: &Path) -> Result<usize, ArchiveError> {
+        Ok(read_header(path)?.files.len())
     }
 
-    fn try_password(&self, path: &str, password: &str, target: &TargetFile) -> bool {
-        let Ok(file) = File::open(path) else {
-            return false;
+    fn metadata(&self, path: &Path) -> Option<ArchiveMetadata> {
+        let mut file = File::open(path).ok()?;
+        let len = file.metadata().ok()?.len();
+        // 头部加密时无法读取任何元数据
+        let archive = sevenz_rust::Archive::read(&mut file, len, &[]).ok()?;
+
+        // sevenz-rust 不解析 7z 注释属性，这里只收集条目名和文本内容
+        let mut metadata = ArchiveMetadata {
+            entry_names: archive.files.iter().map(|f| f.name().to_string()).collect(),
+            ..ArchiveMetadata::default()
         };
 
-        let target_name = target.name.clone();
-        let target_ext = target.extension.clone();
-        let found = Arc::new(AtomicBool::new(false));
-        let found_clone = Arc::clone(&found);
-
-        // 使用自定义提取函数，只验证目标文件，不写入磁盘
-        let result = sevenz_rust::decompress_with_extract_fn_and_password(
-            file,
-            std::path::Path::new("."), // 不会实际写入
-            password.into(),
-            move |entry, reader, _dest_path| {
-                // 跳过目录
-                if entry.is_directory() {
-                    return Ok(true);
-                }
+        for folder_index in 0..archive.folders.len() {
+            // 跳过包含 AES 编码器的数据块，以及不含说明文件或说明文件前数据过多的数据块
+            if is_encrypted(&archive, folder_index) {
+                continue;
+            }
+            let Some(last) = last_text_entry(&archive, folder_index) else {
+                continue;
+            };
 
-                // 只处理目标文件
-                if entry.name() == target_name {
-                    // 读取文件内容到内存验证
-                    let mut buffer = vec![0u8; 8192];
-                    if let Ok(bytes_read) = reader.read(&mut buffer)
-                        && bytes_read > 0
-                        && verify_content(&buffer[..bytes_read], &target_ext)
-                    {
-                        found_clone.store(true, Ordering::Relaxed);
-                    }
-                    // 找到目标后可以停止（返回 false 停止遍历）
-                    return Ok(false);
+            let texts = &mut metadata.text_entries;
+            let mut position = 0;
+            let decoder = sevenz_rust::BlockDecoder::new(folder_index, &archive, &[], &mut file);
+            let _ = decoder.for_each_entries(&mut |entry, reader| {
+                let name = entry.name();
+                if is_readable_text(entry) {
+                    let mut data = Vec::new();
+                    reader
+                        .take(MAX_TEXT_ENTRY_SIZE)
+                        .read_to_end(&mut data)
+                        .map_err(sevenz_rust::Error::io)?;
+                    texts.push((name.to_string(), decode_text(&data)));
+                } else if position < last {
+                    // 固实块中后续条目依赖前面的数据，必须读完
+                    std::io::copy(reader, &mut std::io::sink()).map_err(sevenz_rust::Error::io)?;
                 }
+                position += 1;
+                // 最后一个说明文件之后的条目不再解压
+                Ok(position <= last)
+            });
+        }
 
-                // 跳过其他文件（不写入磁盘）
-                Ok(true)
-            },
-        );
+        Some(metadata)
+    }
+
+    fn fingerprint(&self, path: &Path) -> Option<String> {
+        /// 7z 签名头大小
+        const SIGNATURE_HEADER_SIZE: u64 = 32;
+
+        let mut file = File::open(path).ok()?;
+        let len = file.metadata().ok()?.len();
+
+        let mut hasher = Sha256::new();
+        hasher.update(b"7z");
 
-        // 解压错误（密码错误）返回 false
-        if result.is_err() {
-            return false;
+        let mut head = Vec::new();
+        if let Ok(archive) = sevenz_rust::Archive::read(&mut file, len, &[]) {
+            // AES 编码器属性包含盐和 IV
+            for coder in archive.folders.iter().flat_map(|folder| &folder.coders) {
+                if coder.decompression_method_id() == sevenz_rust::SevenZMethod::ID_AES256SHA256 {
+                    hasher.update(&coder.properties);
+                }
+            }
+            // 首个加密数据块
+            file.seek(SeekFrom::Start(SIGNATURE_HEADER_SIZE + archive.pack_pos))
+                .ok()?;
+            (&mut file)
+                .take(FINGERPRINT_HEAD_SIZE)
+                .read_to_end(&mut head)
+                .ok()?;
+        } else {
+            // 头部加密：签名头中包含加密头的偏移、大小和 CRC
+            file.seek(SeekFrom::Start(0)).ok()?;
+            (&mut file)
+                .take(SIGNATURE_HEADER_SIZE)
+                .read_to_end(&mut head)
+                .ok()?;
         }
+        hasher.update(&head);
 
-        found.load(Ordering::Relaxed)
+        Some(to_hex(&hasher.finalize()))
+    }
+
+    fn try_password(&self, path: &Path, password: &str, target: &TargetFile) -> TryOutcome {
+        let mut file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) => return e.into(),
+        };
+        let len = match file.metadata() {
+            Ok(metadata) => metadata.len(),
+            Err(e) => return e.into(),
+        };
+        let password = sevenz_rust::Password::from(password);
+        // 文件头加密时错误密码在这里就会失败
+        let archive = match sevenz_rust::Archive::read(&mut file, len, password.as_slice()) {
+            Ok(archive) => archive,
+            Err(e) => return sevenz_error_outcome(e),
+        };
+        let Some(folder_index) = archive
+            .stream_map
+            .file_folder_index
+            .get(target.index)
+            .copied()
+            .flatten()
+        else {
+            return TryOutcome::Rejected;
+        };
+        let folder_crc = archive.folders[folder_index].has_crc;
+        let entry_crc = archive.files[target.index].has_crc;
+
+        // 目标文件的验证结果，未解压到目标文件时为空
+        let mut outcome = None;
+        let mut index = archive.stream_map.folder_first_file_index[folder_index];
+        let result = BlockDecoder::new(folder_index, &archive, password.as_slice(), &mut file)
+            .for_each_entries(&mut |_entry, reader| {
+                let current = index;
+                index += 1;
+                if current != target.index {
+                    // 固实块只能按顺序解压：目标之前的条目读出后丢弃，之后的条目用于数据块 CRC 校验
+                    io::copy(reader, &mut io::sink()).map_err(sevenz_rust::Error::io)?;
+                    return Ok(true);
+                }
+
+                // 7z 没有校验字节，解压失败多为错误密码
+                let verified = verify_entry(reader, target).unwrap_or_else(read_error_outcome);
+                // 条目没有自己的 CRC 时继续读完数据块，由解压库校验数据块的 CRC
+                let more = verified.is_verified() && !entry_crc && folder_crc;
+                outcome = Some(verified);
+                Ok(more)
+            });
+
+        match (outcome, result) {
+            (Some(TryOutcome::Verified), Err(e)) => match sevenz_error_outcome(e) {
+                TryOutcome::IoError(e) => TryOutcome::IoError(e),
+                _ => TryOutcome::Unconfirmed("数据块 CRC 校验失败".to_string()),
+            },
+            // 条目和数据块都没有 CRC，无法完整校验
+            (Some(TryOutcome::Verified), Ok(_)) if !entry_crc && !folder_crc => {
+                TryOutcome::Candidate { confidence: 0.9 }
+            }
+            (Some(outcome), _) => outcome,
+            (None, Ok(_)) => TryOutcome::Rejected,
+            (None, Err(e)) => sevenz_error_outcome(e),
+        }
     }
 
     fn format_name(&self) -> &'static str {
         "7z"
     }
 }
+
+/// 是否为收集元数据时要读取内容的说明文件：不是目录、不超过大小上限、文件名为文本类型
+fn is_readable_text(entry: &SevenZArchiveEntry) -> bool {
+    !entry.is_directory() && entry.size() <= MAX_TEXT_ENTRY_SIZE && is_text_file_name(entry.name())
+}
+
+/// 数据块中最后一个说明文件在块内的位置
+///
+/// 固实块需要从头解压，说明文件前的数据超过 [`MAX_TEXT_PREFIX_SIZE`] 时返回 `None`
+fn last_text_entry(archive: &sevenz_rust::Archive, folder_index: usize) -> Option<usize> {
+    let start = archive.stream_map.folder_first_file_index[folder_index];
+    let count = archive.folders[folder_index].num_unpack_sub_streams;
+    let files = archive.files.get(start..start + count)?;
+    let last = files.iter().rposition(is_readable_text)?;
+    let prefix: u64 = files[..last].iter().map(SevenZArchiveEntry::size).sum();
+    (prefix <= MAX_TEXT_PREFIX_SIZE).then_some(last)
+}
+
+/// 数据块是否包含 AES 编码器
+fn is_encrypted(archive: &sevenz_rust::Archive, folder_index: usize) -> bool {
+    archive.folders[folder_index]
+        .coders
+        .iter()
+        .any(|coder| coder.decompression_method_id() == sevenz_rust::SevenZMethod::ID_AES256SHA256)
+}
+
+/// 读取失败的原因：有系统错误码时是读取压缩包失败，否则是用错误密钥解密后的数据无法解压
+fn read_error_outcome(e: std::io::Error) -> TryOutcome {
+    if e.raw_os_error().is_some() {
+        e.into()
+    } else {
+        TryOutcome::Rejected
+    }
+}
+
+/// 解压失败的原因：7z 没有密码校验值，错误密码表现为解压或文件头解析失败
+fn sevenz_error_outcome(e: sevenz_rust::Error) -> TryOutcome {
+    use sevenz_rust::Error;
+
+    match e {
+        Error::Io(e, _) | Error::FileOpen(e, _) | Error::MaybeBadPassword(e) => {
+            read_error_outcome(e)
+        }
+        Error::UnsupportedCompressionMethod(method) => {
+            TryOutcome::Unsupported(format!("压缩方式 {method}"))
+        }
+        Error::ExternalUnsupported | Error::Unsupported(_) | Error::UnsupportedVersion { .. } => {
+            TryOutcome::Unsupported(format!("{e:?}"))
+        }
+        Error::BadSignature(_) => TryOutcome::Unsupported("不是 7z 文件".to_string()),
+        _ => TryOutcome::Rejected,
+    }
+}
@@ -1,69 +1,293 @@
+use sha2::{Digest, Sha256};
 use std::fs::File;
-use std::io::Read;
-use zip::ZipArchive;
+use std::io::{self, BufWriter, Read};
+use std::path::Path;
+use zip::read::ZipFile;
+use zip::result::ZipError;
+use zip::write::FullFileOptions;
+use zip::{AesMode, CompressionMethod, ZipArchive, ZipWriter};
 
-use super::{ArchiveHandler, TargetFile};
-use crate::archive::common::{get_extension, is_infer_supported};
+use super::{ArchiveError, ArchiveHandler, ArchiveMetadata, EntryInfo, TargetFile, TryOutcome};
+use crate::archive::common::{
+    FINGERPRINT_HEAD_SIZE, MAX_FINGERPRINT_ENTRIES, MAX_TEXT_ENTRY_SIZE, decode_text,
+    is_text_file_name, to_hex, verify_entry,
+};
+use crate::extract::Extractor;
+use crate::rewrap::{NewEncryption, Rewrapper};
 
 pub struct ZipHandler;
 
+/// zip 库按 UTF-8 解析的条目名（设置了 UTF-8 标志或纯 ASCII）直接使用，其余用 `decode` 解码
+fn entry_name(raw: &ZipFile<'_, File>, decode: impl FnOnce(&[u8]) -> String) -> String {
+    if std::str::from_utf8(raw.name_raw()).is_ok_and(|name| name == raw.name()) {
+        raw.name().to_string()
+    } else {
+        decode(raw.name_raw())
+    }
+}
+
+/// 重新打包时的错误，附带出错的条目名
+fn rewrap_error(name: &str, e: impl std::fmt::Display) -> ArchiveError {
+    ArchiveError::Corrupt {
+        format: "ZIP",
+        message: if name.is_empty() {
+            e.to_string()
+        } else {
+            format!("条目 {name}: {e}")
+        },
+    }
+}
+
+/// 新压缩包的 AES 密钥长度
+const fn aes_mode(encryption: NewEncryption) -> AesMode {
+    match encryption {
+        NewEncryption::Aes128 => AesMode::Aes128,
+        NewEncryption::Aes192 => AesMode::Aes192,
+        NewEncryption::Aes256 => AesMode::Aes256,
+    }
+}
+
+/// 重新打包时可以原样写入的压缩方法，其他方法（LZMA、Deflate64 等只能读取）返回 `None`
+const fn rewrap_method(method: CompressionMethod) -> Option<CompressionMethod> {
+    match method {
+        CompressionMethod::Stored
+        | CompressionMethod::Deflated
+        | CompressionMethod::Bzip2
+        | CompressionMethod::Zstd
+        | CompressionMethod::Xz
+        | CompressionMethod::Ppmd => Some(method),
+        _ => None,
+    }
+}
+
+/// 打开并解析中央目录
+fn open(path: &Path) -> Result<ZipArchive<File>, ArchiveError> {
+    ZipArchive::new(File::open(path)?).map_err(|e| ArchiveError::Corrupt {
+        format: "ZIP",
+        message: e.to_string(),
+    })
+}
+
 impl ArchiveHandler for ZipHandler {
-    fn detect_target(&self, path: &str) -> Option<TargetFile> {
-        let file = File::open(path).ok()?;
-        let archive = ZipArchive::new(file).ok()?;
+    fn entries(&self, path: &Path) -> Result<Vec<EntryInfo>, ArchiveError> {
+        let mut archive = open(path)?;
+        Ok((0..archive.len())
+            .filter_map(|i| {
+                let raw = archive.by_index_raw(i).ok()?;
+                let (name, size, encrypted) = (raw.name().to_string(), raw.size(), raw.encrypted());
+                if raw.is_dir() {
+                    return None;
+                }
+                drop(raw);
+                let encryption = encrypted.then(|| encryption_name(&mut archive, i));
+                Some(EntryInfo {
+                    index: i,
+                    name,
+                    size,
+                    encryption,
+                })
+            })
+            .collect())
+    }
 
+    fn extract(
+        &self,
+        path: &Path,
+        password: &str,
+        extractor: &mut Extractor,
+    ) -> Result<(), ArchiveError> {
+        let mut archive = open(path)?;
         for i in 0..archive.len() {
-            let name = archive.name_for_index(i)?;
+            if extractor.full() {
+                break;
+            }
+            let Ok(raw) = archive.by_index_raw(i) else {
+                continue;
+            };
+            let name = entry_name(&raw, |raw| extractor.decode_name(raw));
+            let (size, compressed, encrypted) =
+                (raw.size(), raw.compressed_size(), raw.encrypted());
+            if raw.is_dir() {
+                drop(raw);
+                extractor.create_dir(i, &name);
+                continue;
+            }
+            drop(raw);
 
-            // 跳过目录
-            if name.ends_with('/') {
+            let Some(dest) = extractor.begin(i, &name, size, Some(compressed)) else {
                 continue;
+            };
+            let entry = if encrypted {
+                archive.by_index_decrypt(i, password.as_bytes())
+            } else {
+                archive.by_index(i)
+            };
+            match entry {
+                Ok(mut entry) => extractor.write(&name, &dest, &mut entry),
+                Err(e) => extractor.skip(&name, e.to_string()),
+            }
+        }
+        Ok(())
+    }
+
+    fn rewrap(&self, path: &Path, rewrapper: &mut Rewrapper) -> Result<(), ArchiveError> {
+        let mut archive = open(path)?;
+        let options = rewrapper.options();
+        let output = File::create_new(rewrapper.output())?;
+        let mut writer = ZipWriter::new(BufWriter::new(output));
+        writer.set_raw_comment(archive.comment().into());
+
+        for i in 0..archive.len() {
+            let raw = archive.by_index_raw(i).map_err(|e| rewrap_error("", e))?;
+            let name = entry_name(&raw, |raw| rewrapper.decode_name(raw));
+            let encrypted = raw.encrypted();
+            drop(raw);
+            let mut entry = if encrypted {
+                archive.by_index_decrypt(i, rewrapper.password(i).as_bytes())
+            } else {
+                archive.by_index(i)
             }
+            .map_err(|e| rewrap_error(&name, e))?;
 
-            let name_owned = name.to_string();
-            if let Some(ext) = get_extension(&name_owned)
-                && is_infer_supported(&ext)
-            {
-                return Some(TargetFile {
-                    index: i,
-                    name: name_owned,
-                    extension: ext,
-                });
+            // 保留原压缩方法，写入端不支持时改用 Deflate 并记录
+            let original = entry.compression();
+            let method = rewrap_method(original).unwrap_or_else(|| {
+                rewrapper.record_recompressed(&name, original.to_string());
+                CompressionMethod::Deflated
+            });
+            let mut file_options = FullFileOptions::default()
+                .compression_method(method)
+                .large_file(entry.size() >= u64::from(u32::MAX))
+                .with_file_comment(entry.comment());
+            if let Some(time) = entry.last_modified() {
+                file_options = file_options.last_modified_time(time);
+            }
+            if let Some(mode) = entry.unix_mode() {
+                file_options = file_options.unix_permissions(mode);
             }
+            if let Some((password, encryption)) = options.new_password() {
+                file_options = file_options.with_aes_encryption(aes_mode(encryption), password);
+            }
+
+            let result = if entry.is_dir() {
+                writer.add_directory(name.as_str(), file_options)
+            } else if entry.is_symlink() {
+                let mut target = String::new();
+                entry
+                    .read_to_string(&mut target)
+                    .map_err(|e| rewrap_error(&name, e))?;
+                writer.add_symlink(name.as_str(), target, file_options)
+            } else {
+                writer
+                    .start_file(name.as_str(), file_options)
+                    .and_then(|()| io::copy(&mut entry, &mut writer).map_err(ZipError::Io))
+                    .map(drop)
+            };
+            result.map_err(|e| rewrap_error(&name, e))?;
+            rewrapper.record(entry.size());
         }
-        None
+        writer.finish().map_err(|e| rewrap_error("", e))?;
+        Ok(())
     }
 
-    fn file_count(&self, path: &str) -> Result<usize, String> {
-        let file = File::open(path).map_err(|_| "无法打开 ZIP 文件")?;
-        let archive = ZipArchive::new(file).map_err(|_| "无法解析 ZIP 文件")?;
-        Ok(archive.len())
+    fn file_count(&self, path: &Path) -> Result<usize, ArchiveError> {
+        Ok(open(path)?.len())
     }
 
-    fn try_password(&self, path: &str, password: &str, target: &TargetFile) -> bool {
-        let Ok(file) = File::open(path) else {
-            return false;
-        };
+    fn metadata(&self, path: &Path) -> Option<ArchiveMetadata> {
+        let file = File::open(path).ok()?;
+        let mut archive = ZipArchive::new(file).ok()?;
 
-        let Ok(mut archive) = ZipArchive::new(file) else {
-            return false;
+        let mut metadata = ArchiveMetadata {
+            comment: decode_text(archive.comment()),
+            ..ArchiveMetadata::default()
         };
 
-        let result = archive.by_index_decrypt(target.index, password.as_bytes());
-        match result {
-            Ok(mut file) => {
-                let mut buffer = vec![0u8; 8192];
-                let Ok(bytes_read) = file.read(&mut buffer) else {
-                    return false;
-                };
+        for i in 0..archive.len() {
+            let Ok(raw) = archive.by_index_raw(i) else {
+                continue;
+            };
+            let name = decode_text(raw.name_raw());
+            let readable = !raw.encrypted()
+                && !raw.is_dir()
+                && raw.size() <= MAX_TEXT_ENTRY_SIZE
+                && is_text_file_name(&name);
+            drop(raw);
 
-                if bytes_read == 0 {
-                    return false;
+            // 读取未加密的说明文件（如 密码.txt、README）
+            if readable && let Ok(entry) = archive.by_index(i) {
+                // 条目头中的大小可能不可信，读取时再限制一次
+                let mut data = Vec::new();
+                if entry
+                    .take(MAX_TEXT_ENTRY_SIZE)
+                    .read_to_end(&mut data)
+                    .is_ok()
+                {
+                    metadata
+                        .text_entries
+                        .push((name.clone(), decode_text(&data)));
                 }
-                infer::get(&buffer[..bytes_read])
-                    .is_some_and(|kind| kind.extension() == target.extension)
             }
-            Err(_) => false,
+            metadata.entry_names.push(name);
+        }
+
+        Some(metadata)
+    }
+
+    fn fingerprint(&self, path: &Path) -> Option<String> {
+        let file = File::open(path).ok()?;
+        let mut archive = ZipArchive::new(file).ok()?;
+
+        let mut hasher = Sha256::new();
+        hasher.update(b"zip");
+        let mut encrypted = 0;
+
+        for i in 0..archive.len() {
+            if encrypted >= MAX_FINGERPRINT_ENTRIES {
+                break;
+            }
+            let Ok(mut raw) = archive.by_index_raw(i) else {
+                continue;
+            };
+            if !raw.encrypted() {
+                continue;
+            }
+            encrypted += 1;
+
+            // 原始数据开头即 ZipCrypto 头 / AES 盐和校验值，后接首个加密块
+            let mut head = Vec::new();
+            (&mut raw)
+                .take(FINGERPRINT_HEAD_SIZE)
+                .read_to_end(&mut head)
+                .ok()?;
+            hasher.update(raw.crc32().to_le_bytes());
+            hasher.update(raw.compressed_size().to_le_bytes());
+            hasher.update(&head);
+        }
+
+        (encrypted > 0).then(|| to_hex(&hasher.finalize()))
+    }
+
+    fn try_password(&self, path: &Path, password: &str, target: &TargetFile) -> TryOutcome {
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) => return e.into(),
+        };
+        let mut archive = match ZipArchive::new(file) {
+            Ok(archive) => archive,
+            Err(e) => return zip_error_outcome(e),
+        };
+
+        let mut file = match archive.by_index_decrypt(target.index, password.as_bytes()) {
+            Ok(file) => file,
+            Err(e) => return zip_error_outcome(e),
+        };
+        // 通过了校验字节（ZipCrypto 1 字节、AES 2 字节），错误密码也有可能通过
+        match verify_entry(&mut file, target) {
+            Ok(outcome) => outcome,
+            // 系统错误码说明是读取失败，否则是用错误密钥解密后的数据无法解压或 CRC 不符
+            Err(e) if e.raw_os_error().is_some() => e.into(),
+            Err(_) => TryOutcome::Candidate { confidence: 0.25 },
         }
     }
 
@@ -71,3 +295,25 @@ impl ArchiveHandler for ZipHandler {
         "ZIP"
     }
 }
+
+/// 条目的加密方式：WinZip AES 的密钥长度或传统 `ZipCrypto`
+fn encryption_name(archive: &mut ZipArchive<File>, index: usize) -> String {
+    match archive.get_aes_verification_key_and_salt(index) {
+        Ok(Some(aes)) => match aes.aes_mode {
+            AesMode::Aes128 => "AES-128",
+            AesMode::Aes192 => "AES-192",
+            AesMode::Aes256 => "AES-256",
+        }
+        .to_string(),
+        _ => "ZipCrypto".to_string(),
+    }
+}
+
+/// 打开条目失败的原因：密码错误、读取失败或压缩包不受支持
+fn zip_error_outcome(e: ZipError) -> TryOutcome {
+    match e {
+        ZipError::InvalidPassword => TryOutcome::Rejected,
+        ZipError::Io(e) => e.into(),
+        e => TryOutcome::Unsupported(e.to_string()),
+    }
+}
@@ -1,7 +1,11 @@
 pub mod bruteforce;
+pub mod budget;
 pub mod dictionary;
+pub mod wordlist;
 
 pub use bruteforce::bruteforce_attack;
+pub use budget::Budget;
 pub use dictionary::{
     append_to_dictionary, dictionary_attack, ensure_dictionary_exists, get_default_dictionary_path,
+    resolve_dictionaries, wordlist_attack,
 };
@@ -1,118 +1,162 @@
 use rayon::prelude::*;
+use std::ops::Range;
+use std::path::Path;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Instant;
 
+use super::Budget;
 use crate::archive::{ArchiveHandler, TargetFile};
 use crate::charset::{get_combined_charset, index_to_password};
 use crate::cli::Charset;
+use crate::keyspace::{bruteforce_keyspace, length_keyspace};
+use crate::observer::Monitor;
+use crate::session::{ChunkSizer, SessionTracker};
 
 /// 暴力破解结果
 pub struct BruteforceResult {
     pub password: Option<String>,
     pub total_tested: u64,
     pub elapsed_secs: f64,
+    /// 是否因 Ctrl-C 中断
+    pub interrupted: bool,
+    /// 是否因预算用尽提前结束
+    pub budget_exhausted: bool,
 }
 
 /// 暴力破解参数
 pub struct BruteforceParams<'a> {
-    pub archive_path: &'a str,
+    pub archive_path: &'a Path,
     pub charsets: &'a [Charset],
     pub min_len: u32,
     pub max_len: u32,
-    pub target: &'a TargetFile,
+    /// 验证条目，候选必须全部通过
+    pub targets: &'a [TargetFile],
     pub file_count: usize,
     pub handler: &'a dyn ArchiveHandler,
+    /// 断点续传：从该长度的该索引开始（之前的长度视为已完成）
+    pub resume: (u32, u128),
+    /// 会话记录器（用于保存进度）
+    pub session: Option<&'a SessionTracker>,
+    /// 键空间分片：只测试该范围内的全局索引（各长度首尾相接），`None` 表示全部
+    pub range: Option<Range<u128>>,
+    /// 本阶段的时间和候选数量预算
+    pub budget: Budget,
+    /// 报告过程、取消和暂停
+    pub monitor: &'a Monitor<'a>,
 }
 
 /// 执行暴力破解攻击
 #[must_use]
 pub fn bruteforce_attack(params: &BruteforceParams) -> BruteforceResult {
-    let num_cpus = num_cpus::get();
     let (charset_name, chars) = get_combined_charset(params.charsets);
-
-    println!(
-        "=== {} 密码暴力破解器 (Rust 多线程版) ===",
-        params.handler.format_name()
-    );
-    println!("目标文件: {}", params.archive_path);
-    println!("CPU 核心数: {num_cpus}");
-    println!("字符集: {} ({}字符)", charset_name, chars.len());
-
-    if params.min_len == params.max_len {
-        println!("密码长度: {}", params.min_len);
-        let total_combinations = (chars.len() as u64).pow(params.min_len);
-        println!(
-            "密码空间: {}^{} = {} 组合",
-            chars.len(),
-            params.min_len,
-            total_combinations
-        );
-    } else {
-        println!(
-            "密码长度: {} ~ {} (递增模式)",
-            params.min_len, params.max_len
-        );
-        let total_combinations: u64 = (params.min_len..=params.max_len)
-            .map(|len| (chars.len() as u64).pow(len))
-            .sum();
-        println!(
-            "密码空间: {} 组合 (长度{}到{}的总和)",
-            total_combinations, params.min_len, params.max_len
-        );
-    }
-    println!();
-
-    println!(
-        "检测到目标文件: {} (索引 {})",
-        params.target.name, params.target.index
-    );
-    println!("文件扩展名: .{}", params.target.extension);
-    println!("验证方式: 使用 infer 库检测解密后内容是否匹配扩展名");
-    println!();
-
-    println!("压缩包包含 {} 个文件", params.file_count);
-    println!();
-    println!("开始破解...");
+    let monitor = params.monitor;
+    monitor.message(&banner(params, &charset_name, chars.len()));
 
     let found = Arc::new(AtomicBool::new(false));
     let start = Instant::now();
     let mut total_tested: u64 = 0;
     let mut result_password: Option<String> = None;
+    let mut interrupted = false;
+    let mut budget_exhausted = false;
+    let mut chunks = ChunkSizer::new();
+    let (resume_len, resume_index) = params.resume;
+    let mut offset: u128 = 0;
+    report_keyspace(params, chars.len());
 
     // 从最小长度到最大长度逐一尝试
     for current_len in params.min_len..=params.max_len {
-        if found.load(Ordering::Relaxed) {
+        if found.load(Ordering::Relaxed) || interrupted || budget_exhausted {
             break;
         }
 
-        if params.min_len != params.max_len {
-            println!("尝试长度 {current_len} ...");
+        // 长度范围已在入口校验过不超出 u128
+        let total_combinations = length_keyspace(chars.len(), current_len).unwrap_or(u128::MAX);
+        let length_offset = offset;
+        offset = offset.saturating_add(total_combinations);
+
+        // 键空间分片：换算为该长度内的索引范围
+        let (range_start, range_end) = params.range.as_ref().map_or((0, total_combinations), |r| {
+            (
+                r.start.clamp(length_offset, offset) - length_offset,
+                r.end.clamp(length_offset, offset) - length_offset,
+            )
+        });
+        // 断点续传：跳过已完成的长度
+        if current_len < resume_len || range_start >= range_end {
+            continue;
         }
 
-        let total_combinations = (chars.len() as u64).pow(current_len);
-        total_tested += total_combinations;
+        if params.min_len != params.max_len {
+            monitor.message(&format!("尝试长度 {current_len} ..."));
+        }
+        monitor.progress().set_detail(format!("长度 {current_len}"));
+
+        let mut chunk_start = if current_len == resume_len {
+            resume_index.clamp(range_start, range_end)
+        } else {
+            range_start
+        };
+        if chunk_start > 0 {
+            monitor.message(&format!("从索引 {chunk_start} / {total_combinations} 开始"));
+        }
 
-        // 使用索引并行搜索，零内存预分配
-        let result = (0..total_combinations).into_par_iter().find_any(|&index| {
-            if found.load(Ordering::Relaxed) {
-                return false;
+        // 分块并行搜索：每块完成后记录进度，零内存预分配
+        while chunk_start < range_end {
+            // 块大小不超过 u64，块内用 u64 偏移并行迭代
+            let size = (chunks.size() as u64).min(params.budget.remaining(total_tested));
+            let chunk_len = u64::try_from(range_end - chunk_start)
+                .map_or(size, |remaining| remaining.min(size));
+            if chunk_len == 0 {
+                budget_exhausted = true;
+                break;
             }
-
-            let pwd = index_to_password(index, &chars, current_len as usize);
-            if params
-                .handler
-                .try_password(params.archive_path, &pwd, params.target)
-            {
-                found.store(true, Ordering::Relaxed);
-                return true;
+            let chunk_end = chunk_start + u128::from(chunk_len);
+
+            let chunk_timer = Instant::now();
+            let result = (0..chunk_len).into_par_iter().find_map_any(|offset| {
+                if found.load(Ordering::Relaxed) || monitor.cancelled() {
+                    return None;
+                }
+
+                let pwd = index_to_password(
+                    chunk_start + u128::from(offset),
+                    &chars,
+                    current_len as usize,
+                );
+                monitor.progress().record();
+                let outcome = params
+                    .handler
+                    .try_targets(params.archive_path, &pwd, params.targets);
+                if monitor.record(&pwd, &outcome) {
+                    found.store(true, Ordering::Relaxed);
+                    return Some(pwd);
+                }
+                None
+            });
+            total_tested += chunk_len;
+
+            if result.is_some() {
+                result_password = result;
+                break;
+            }
+            // 被中断的块不计入已完成，恢复时重新测试
+            if monitor.cancelled() {
+                interrupted = true;
+                break;
             }
-            false
-        });
 
-        if let Some(index) = result {
-            result_password = Some(index_to_password(index, &chars, current_len as usize));
-            break;
+            chunk_start = chunk_end;
+            chunks.adjust(chunk_timer.elapsed());
+            save_progress(params, current_len, chunk_start);
+            if params.budget.expired() {
+                budget_exhausted = true;
+                break;
+            }
+            if monitor.checkpoint() {
+                interrupted = true;
+                break;
+            }
         }
     }
 
@@ -122,5 +166,81 @@ pub fn bruteforce_attack(params: &BruteforceParams) -> BruteforceResult {
         password: result_password,
         total_tested,
         elapsed_secs: elapsed.as_secs_f64(),
+        interrupted,
+        budget_exhausted,
+    }
+}
+
+/// 记录断点，自动保存失败时报告警告
+fn save_progress(params: &BruteforceParams, len: u32, index: u128) {
+    if let Some(tracker) = params.session
+        && let Err(e) = tracker.update_bruteforce(len, index)
+    {
+        params.monitor.warning(&format!("无法保存会话: {e}"));
+    }
+}
+
+/// 暴力破解的配置和密码空间
+fn banner(params: &BruteforceParams, charset_name: &str, charset_size: usize) -> String {
+    let mut lines = vec![
+        format!(
+            "=== {} 密码暴力破解器 (Rust 多线程版) ===",
+            params.handler.format_name()
+        ),
+        format!("目标文件: {}", params.archive_path.display()),
+        format!("CPU 核心数: {}", num_cpus::get()),
+        format!("字符集: {charset_name} ({charset_size}字符)"),
+    ];
+
+    if params.min_len == params.max_len {
+        lines.push(format!("密码长度: {}", params.min_len));
+        let total_combinations = length_keyspace(charset_size, params.min_len).unwrap_or(u128::MAX);
+        lines.push(format!(
+            "密码空间: {}^{} = {} 组合",
+            charset_size, params.min_len, total_combinations
+        ));
+    } else {
+        lines.push(format!(
+            "密码长度: {} ~ {} (递增模式)",
+            params.min_len, params.max_len
+        ));
+        let total_combinations =
+            bruteforce_keyspace(charset_size, params.min_len, params.max_len).unwrap_or(u128::MAX);
+        lines.push(format!(
+            "密码空间: {} 组合 (长度{}到{}的总和)",
+            total_combinations, params.min_len, params.max_len
+        ));
+    }
+
+    lines.extend([String::new()]);
+    lines.extend(params.targets.iter().map(TargetFile::describe));
+    lines.extend([
+        String::new(),
+        format!("压缩包包含 {} 个文件", params.file_count),
+        String::new(),
+        "开始破解...".to_string(),
+    ]);
+    lines.join("\n")
+}
+
+/// 报告本次搜索的键空间大小和断点续传前已完成的数量
+fn report_keyspace(params: &BruteforceParams, charset_size: usize) {
+    let total =
+        bruteforce_keyspace(charset_size, params.min_len, params.max_len).unwrap_or(u128::MAX);
+    // 断点之前的长度已全部完成
+    let (resume_len, resume_index) = params.resume;
+    let resumed = if resume_len > params.min_len {
+        bruteforce_keyspace(charset_size, params.min_len, resume_len - 1)
+            .unwrap_or(u128::MAX)
+            .saturating_add(resume_index)
+    } else {
+        resume_index
+    };
+    match &params.range {
+        Some(range) => params.monitor.progress().set_keyspace(
+            range.end - range.start,
+            resumed.clamp(range.start, range.end) - range.start,
+        ),
+        None => params.monitor.progress().set_keyspace(total, resumed),
     }
 }
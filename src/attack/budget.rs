@@ -0,0 +1,190 @@
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// 攻击阶段的预算：截止时间和最多测试的候选数量
+///
+/// 攻击循环在每个断点分块之间检查截止时间，预算用尽时正常结束该阶段（不算中断）
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Budget {
+    /// 截止时间，`None` 表示不限时
+    pub deadline: Option<Instant>,
+    /// 最多测试的候选数量，`None` 表示不限
+    pub max_candidates: Option<u64>,
+}
+
+impl Budget {
+    /// 不限时间和数量
+    #[must_use]
+    pub const fn unlimited() -> Self {
+        Self {
+            deadline: None,
+            max_candidates: None,
+        }
+    }
+
+    /// 从现在起最多运行 `max_duration`，最多测试 `max_candidates` 个候选
+    #[must_use]
+    pub fn new(max_duration: Option<Duration>, max_candidates: Option<u64>) -> Self {
+        Self {
+            deadline: max_duration.map(|d| Instant::now() + d),
+            max_candidates,
+        }
+    }
+
+    /// 附加截止时间（系统时间），与已有的截止时间取较早者
+    #[must_use]
+    pub fn with_deadline(self, deadline: Option<SystemTime>) -> Self {
+        let Some(deadline) = deadline else {
+            return self;
+        };
+        // 已过去的截止时间视为立即到期
+        let remaining = deadline
+            .duration_since(SystemTime::now())
+            .unwrap_or_default();
+        self.intersect(Self {
+            deadline: Some(Instant::now() + remaining),
+            max_candidates: None,
+        })
+    }
+
+    /// 同时受两个预算约束：取较早的截止时间和较小的候选数量
+    #[must_use]
+    pub fn intersect(self, other: Self) -> Self {
+        Self {
+            deadline: earlier(self.deadline, other.deadline),
+            max_candidates: earlier(self.max_candidates, other.max_candidates),
+        }
+    }
+
+    /// 是否已过截止时间
+    #[must_use]
+    pub fn expired(&self) -> bool {
+        self.deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
+    /// 已测试 `tested` 个候选后还能测试的数量
+    #[must_use]
+    pub fn remaining(&self, tested: u64) -> u64 {
+        self.max_candidates
+            .map_or(u64::MAX, |max| max.saturating_sub(tested))
+    }
+
+    /// 已测试 `tested` 个候选后剩余的预算（截止时间不变）
+    #[must_use]
+    pub fn after(self, tested: u64) -> Self {
+        Self {
+            max_candidates: self.max_candidates.map(|max| max.saturating_sub(tested)),
+            ..self
+        }
+    }
+
+    /// 已测试 `tested` 个候选后预算是否用尽
+    #[must_use]
+    pub fn exhausted(&self, tested: u64) -> bool {
+        self.remaining(tested) == 0 || self.expired()
+    }
+}
+
+/// 两个可选上限中较小的一个（`None` 表示不限）
+fn earlier<T: Ord>(a: Option<T>, b: Option<T>) -> Option<T> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+/// 解析时长：纯数字为秒数，或带单位 `s`/`m`/`h`/`d` 的组合，如 `90`、`10m`、`1h30m`
+pub fn parse_duration(s: &str) -> Result<Duration, String> {
+    let invalid = || format!("无效的时长（如 90、10m、2h、1h30m）: {s}");
+    let s = s.trim();
+    if s.is_empty() {
+        return Err(invalid());
+    }
+
+    let mut total: u64 = 0;
+    let mut number = String::new();
+    for c in s.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let unit = match c {
+            's' => 1,
+            'm' => 60,
+            'h' => 3600,
+            'd' => 86400,
+            _ => return Err(invalid()),
+        };
+        let value: u64 = number.parse().map_err(|_| invalid())?;
+        total = value
+            .checked_mul(unit)
+            .and_then(|secs| total.checked_add(secs))
+            .ok_or_else(invalid)?;
+        number.clear();
+    }
+    // 末尾不带单位的数字按秒计
+    if !number.is_empty() {
+        let value: u64 = number.parse().map_err(|_| invalid())?;
+        total = total.checked_add(value).ok_or_else(invalid)?;
+    }
+    Ok(Duration::from_secs(total))
+}
+
+/// 解析 RFC 3339 格式的截止时间，如 `2026-10-18T23:00:00+08:00` 或 `2026-10-18T15:00Z`（必须带时区）
+pub fn parse_deadline(s: &str) -> Result<SystemTime, String> {
+    let invalid = || format!("无效的截止时间（如 2026-10-18T23:00:00+08:00）: {s}");
+    let (date, rest) = s.trim().split_once(['T', ' ']).ok_or_else(invalid)?;
+
+    // 时区偏移（秒）
+    let (time, offset) = if let Some(time) = rest.strip_suffix(['Z', 'z']) {
+        (time, 0)
+    } else {
+        let pos = rest.rfind(['+', '-']).ok_or_else(invalid)?;
+        let (time, offset) = rest.split_at(pos);
+        let (hours, minutes) = offset[1..].split_once(':').ok_or_else(invalid)?;
+        let secs = parse_part(hours, 23).ok_or_else(invalid)? * 3600
+            + parse_part(minutes, 59).ok_or_else(invalid)? * 60;
+        (time, if offset.starts_with('-') { -secs } else { secs })
+    };
+
+    let date: Vec<&str> = date.split('-').collect();
+    let time: Vec<&str> = time.split(':').collect();
+    let [year, month, day] = date.as_slice() else {
+        return Err(invalid());
+    };
+    let (hour, minute, second) = match time.as_slice() {
+        [hour, minute] => (hour, minute, &"0"),
+        [hour, minute, second] => (hour, minute, second),
+        _ => return Err(invalid()),
+    };
+
+    let year: i64 = year.parse().map_err(|_| invalid())?;
+    let month = parse_part(month, 12)
+        .filter(|&m| m > 0)
+        .ok_or_else(invalid)?;
+    let day = parse_part(day, 31).filter(|&d| d > 0).ok_or_else(invalid)?;
+    // 秒允许带小数部分，截断到整秒
+    let second = second.split('.').next().unwrap_or_default();
+    let secs = days_from_civil(year, month, day) * 86400
+        + parse_part(hour, 23).ok_or_else(invalid)? * 3600
+        + parse_part(minute, 59).ok_or_else(invalid)? * 60
+        + parse_part(second, 60).ok_or_else(invalid)?
+        - offset;
+    let secs = u64::try_from(secs).map_err(|_| invalid())?;
+    Ok(UNIX_EPOCH + Duration::from_secs(secs))
+}
+
+/// 解析不超过 `max` 的非负整数字段
+fn parse_part(value: &str, max: i64) -> Option<i64> {
+    value.parse().ok().filter(|v| (0..=max).contains(v))
+}
+
+/// 公历日期距 1970-01-01 的天数
+const fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
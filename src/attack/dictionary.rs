@@ -1,31 +1,43 @@
 use rayon::prelude::*;
 use std::collections::HashSet;
 use std::fs::{self, File, OpenOptions};
-use std::io::{BufRead, BufReader, Write};
-use std::path::PathBuf;
+use std::io::{Read, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::time::Instant;
 
+use super::Budget;
+use super::wordlist::{
+    BATCH_SIZE, SeenFilter, WordlistReader, decode_word, encode_word, format_bytes,
+};
 use crate::archive::{ArchiveHandler, TargetFile};
+use crate::observer::Monitor;
 use crate::passwords::TOP_1000_PASSWORDS;
+use crate::paths::data_dir;
+use crate::session::{ChunkSizer, SessionTracker};
 
 /// 字典攻击结果
 pub struct DictionaryResult {
     pub password: Option<String>,
     pub total_tested: u64,
     pub elapsed_secs: f64,
+    /// 各字典来源的统计（内存候选列表为空）
+    pub sources: Vec<SourceStats>,
+    /// 是否因 Ctrl-C 中断
+    pub interrupted: bool,
+    /// 是否因预算用尽提前结束
+    pub budget_exhausted: bool,
 }
 
 /// 获取默认字典路径 (~/.`archive_cracker/dictionary.txt`)
-#[must_use]
-pub fn get_default_dictionary_path() -> PathBuf {
-    let home = dirs::home_dir().expect("无法获取用户主目录");
-    home.join(".archive_cracker").join("dictionary.txt")
+pub fn get_default_dictionary_path() -> std::io::Result<PathBuf> {
+    Ok(data_dir()?.join("dictionary.txt"))
 }
 
 /// 确保字典目录和文件存在，首次创建时写入内置 Top1000 密码
-pub fn ensure_dictionary_exists(path: &PathBuf) -> std::io::Result<()> {
+pub fn ensure_dictionary_exists(path: &Path) -> std::io::Result<()> {
     if let Some(parent) = path.parent() {
         fs::create_dir_all(parent)?;
     }
@@ -47,108 +59,596 @@ pub fn ensure_dictionary_exists(path: &PathBuf) -> std::io::Result<()> {
     Ok(())
 }
 
-/// 追加密码到字典（去重）
-pub fn append_to_dictionary(dict_path: &PathBuf, password: &str) -> std::io::Result<bool> {
-    // 先检查是否已存在
-    if dict_path.exists() {
-        let existing = load_dictionary(&dict_path.to_string_lossy())?;
-        if existing.contains(&password.to_string()) {
-            return Ok(false); // 密码已存在
-        }
-    }
-
-    // 追加密码
+/// 追加密码到字典（去重），特殊密码写成 `$HEX[...]`（见 [`encode_word`]）
+///
+/// 写入期间持有排他文件锁，持锁后再检查是否已存在，多个进程同时运行也不会重复写入
+pub fn append_to_dictionary(dict_path: &Path, password: &str) -> std::io::Result<bool> {
     let mut file = OpenOptions::new()
-        .create(true)
+        .read(true)
         .append(true)
+        .create(true)
         .open(dict_path)?;
-    writeln!(file, "{password}")?;
+    file.lock()?;
+
+    // 字典可能被手动编辑过，含有非 UTF-8 内容
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes)?;
+    let content = String::from_utf8_lossy(&bytes);
+    let exists = content
+        .lines()
+        .map(|line| line.strip_suffix('\r').unwrap_or(line))
+        .any(|line| !line.starts_with('#') && decode_word(line) == password);
+    if exists {
+        return Ok(false); // 密码已存在
+    }
+
+    // 上一行可能未以换行结尾（例如被手动编辑过）
+    if !content.is_empty() && !content.ends_with('\n') {
+        writeln!(file)?;
+    }
+    writeln!(file, "{}", encode_word(password))?;
     Ok(true)
 }
 
-/// 从字典文件加载密码列表
-pub fn load_dictionary(path: &str) -> Result<Vec<String>, std::io::Error> {
-    let file = File::open(path)?;
-    let reader = BufReader::new(file);
+/// 单个字典来源的统计
+#[derive(Debug, Clone)]
+pub struct SourceStats {
+    /// 字典文件路径
+    pub source: String,
+    /// 从该字典实际测试的候选数（已去除重复）
+    pub tested: u64,
+    /// 因与更早的字典重复而跳过的条目数
+    pub duplicates: u64,
+}
 
-    let passwords: Vec<String> = reader
-        .lines()
-        .map_while(std::result::Result::ok)
-        .filter(|line| !line.is_empty() && !line.starts_with('#'))
-        .collect();
+/// 展开字典参数：内置字典总是第一个，目录按文件名顺序递归展开，重复路径只保留首个
+#[must_use]
+pub fn resolve_dictionaries<P: AsRef<Path>>(builtin: &Path, paths: &[P]) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    if builtin.exists() {
+        files.push(builtin.to_path_buf());
+    }
+    for path in paths {
+        collect_dictionary_files(path.as_ref(), &mut files);
+    }
 
-    Ok(passwords)
+    let mut seen = HashSet::new();
+    files.retain(|path| seen.insert(fs::canonicalize(path).unwrap_or_else(|_| path.clone())));
+    files
 }
 
-/// 从字典文件加载密码列表（去重）
-pub fn load_dictionary_unique(path: &str) -> Result<Vec<String>, std::io::Error> {
-    let file = File::open(path)?;
-    let reader = BufReader::new(file);
+/// 递归收集目录中的字典文件（跳过隐藏文件）
+fn collect_dictionary_files(path: &Path, files: &mut Vec<PathBuf>) {
+    if !path.is_dir() {
+        files.push(path.to_path_buf());
+        return;
+    }
 
-    let mut seen = HashSet::new();
-    let passwords: Vec<String> = reader
-        .lines()
-        .map_while(std::result::Result::ok)
-        .filter(|line| !line.is_empty() && !line.starts_with('#'))
-        .filter(|line| seen.insert(line.clone()))
-        .collect();
+    let Ok(entries) = fs::read_dir(path) else {
+        return;
+    };
+    let mut children: Vec<PathBuf> = entries.map_while(Result::ok).map(|e| e.path()).collect();
+    children.sort();
+
+    for child in children {
+        let hidden = child
+            .file_name()
+            .is_some_and(|name| name.to_string_lossy().starts_with('.'));
+        if !hidden {
+            collect_dictionary_files(&child, files);
+        }
+    }
+}
 
-    Ok(passwords)
+/// 字典攻击参数
+pub struct DictionaryParams<'a> {
+    pub archive_path: &'a Path,
+    pub dict_paths: &'a [PathBuf],
+    /// 验证条目，候选必须全部通过
+    pub targets: &'a [TargetFile],
+    pub file_count: usize,
+    pub handler: &'a dyn ArchiveHandler,
+    /// 断点续传：从第几个字典、该字典的第几条开始
+    pub resume: (usize, u64),
+    /// 会话记录器（用于保存进度）
+    pub session: Option<&'a SessionTracker>,
+    /// 键空间分片：每个字典只测试该范围内的条目，`None` 表示全部
+    pub ranges: Option<&'a [Range<u64>]>,
+    /// 本阶段的时间和候选数量预算
+    pub budget: Budget,
+    /// 报告过程、取消和暂停
+    pub monitor: &'a Monitor<'a>,
 }
 
 /// 执行字典攻击
-pub fn dictionary_attack(
-    archive_path: &str,
-    dict_path: &str,
-    target: &TargetFile,
-    file_count: usize,
-    handler: &dyn ArchiveHandler,
-) -> DictionaryResult {
-    let num_cpus = num_cpus::get();
+///
+/// 按顺序依次读取多个字典，跨字典去重。字典按批流式读取（支持 `.gz`/`.xz`/`.zst`），
+/// 测试当前批次的同时读取下一批，内存占用与字典大小无关。
+/// 找到密码时，`sources` 的最后一项即为提供密码的字典
+#[must_use]
+pub fn dictionary_attack(params: &DictionaryParams) -> DictionaryResult {
+    let monitor = params.monitor;
+    monitor.message(&dictionary_banner(params));
+
+    let start = Instant::now();
+    let mut seen = SeenFilter::default();
+    let mut result = DictionaryResult {
+        password: None,
+        total_tested: 0,
+        elapsed_secs: 0.0,
+        sources: Vec::new(),
+        interrupted: false,
+        budget_exhausted: false,
+    };
+    let (resume_source, resume_line) = params.resume;
+    // 按文件大小估算整个阶段的完成比例
+    let sizes: Vec<u64> = params
+        .dict_paths
+        .iter()
+        .map(|path| fs::metadata(path).map_or(0, |m| m.len()))
+        .collect();
+    let total_bytes = sizes.iter().sum();
+
+    for (index, path) in params.dict_paths.iter().enumerate() {
+        if params.budget.exhausted(result.total_tested) {
+            break;
+        }
+        let range = params
+            .ranges
+            .map_or(0..u64::MAX, |ranges| ranges[index].clone());
+        if range.is_empty() {
+            continue;
+        }
+        let mut reader = match WordlistReader::open(path) {
+            Ok(reader) => reader,
+            Err(e) => {
+                monitor.warning(&format!("无法打开字典 {}，已跳过: {e}", path.display()));
+                continue;
+            }
+        };
+        if index < resume_source {
+            // 恢复会话：之前的字典已测试完，只重建去重记录，避免再次测试其中出现过的候选
+            monitor.message(&format!("重建去重记录: {}", path.display()));
+            remember_range(&mut reader, &mut seen, range);
+            continue;
+        }
+        monitor.message(&format!(
+            "读取字典: {} ({}, {})",
+            path.display(),
+            format_bytes(reader.total_bytes()),
+            reader.compression().name()
+        ));
+        monitor.progress().set_detail(format!(
+            "{}/{} {}",
+            index + 1,
+            params.dict_paths.len(),
+            path.display()
+        ));
+
+        let resume = if index == resume_source {
+            resume_line.max(range.start)
+        } else {
+            range.start
+        };
+        // 候选数量预算按原始条目数截断（去重后实际测试的不会更多）
+        let end = range
+            .end
+            .min(resume.saturating_add(params.budget.remaining(result.total_tested)));
+        let bytes = ByteProgress {
+            before: sizes[..index].iter().sum(),
+            total: total_bytes,
+            batch: 0..0,
+        };
+        let outcome = stream_source(
+            params,
+            index,
+            &mut reader,
+            &mut seen,
+            range.start..end,
+            resume,
+            bytes,
+        );
+        result.total_tested += outcome.tested;
+        result.sources.push(SourceStats {
+            source: path.display().to_string(),
+            tested: outcome.tested,
+            duplicates: outcome.duplicates,
+        });
+
+        if outcome.password.is_some() || outcome.interrupted || params.budget.expired() {
+            result.password = outcome.password;
+            result.interrupted = outcome.interrupted;
+            break;
+        }
+    }
+
+    result.budget_exhausted = result.password.is_none()
+        && !result.interrupted
+        && params.budget.exhausted(result.total_tested);
+
+    result.elapsed_secs = start.elapsed().as_secs_f64();
+    result
+}
+
+/// 字典阶段开始时显示的信息（列出所有字典）
+fn dictionary_banner(params: &DictionaryParams) -> String {
+    banner(
+        params.handler,
+        params.archive_path,
+        &format!("{} 个字典", params.dict_paths.len()),
+        &params
+            .dict_paths
+            .iter()
+            .enumerate()
+            .map(|(i, path)| format!("  {}. {}", i + 1, path.display()))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        params.targets,
+        params.file_count,
+    )
+}
+
+/// 单个字典的测试结果
+struct StreamOutcome {
+    password: Option<String>,
+    tested: u64,
+    duplicates: u64,
+    interrupted: bool,
+}
+
+/// 按读取的字节数估算字典阶段的完成比例
+struct ByteProgress {
+    /// 之前各字典的大小之和
+    before: u64,
+    /// 所有字典的总大小
+    total: u64,
+    /// 当前批次在本字典中的字节范围
+    batch: Range<u64>,
+}
+
+impl ByteProgress {
+    /// 报告当前批次已完成 `done / len` 时整个阶段的完成比例
+    #[allow(clippy::cast_precision_loss)]
+    fn report(&self, monitor: &Monitor, done: usize, len: usize) {
+        let span = (self.batch.end - self.batch.start) as f64 * done as f64 / len.max(1) as f64;
+        let read = (self.before + self.batch.start) as f64 + span;
+        monitor
+            .progress()
+            .set_fraction(read / self.total.max(1) as f64);
+    }
+}
 
-    // 加载字典（去重）
-    let passwords = load_dictionary_unique(dict_path).expect("无法加载字典文件");
+/// 流式测试单个字典中 `range` 范围内的条目（键空间分片时跳过前面的条目）
+///
+/// 断点续传时从第 `resume` 条开始，`range.start` 到 `resume` 之间已测试过的条目记入 `seen`
+fn stream_source(
+    params: &DictionaryParams,
+    source_index: usize,
+    reader: &mut WordlistReader,
+    seen: &mut SeenFilter,
+    range: Range<u64>,
+    resume: u64,
+    mut bytes: ByteProgress,
+) -> StreamOutcome {
+    let mut outcome = StreamOutcome {
+        password: None,
+        tested: 0,
+        duplicates: 0,
+        interrupted: false,
+    };
+    let mut current = Vec::with_capacity(BATCH_SIZE);
+    let mut next = Vec::with_capacity(BATCH_SIZE);
 
-    println!(
-        "=== {} 密码字典攻击器 (Rust 多线程版) ===",
-        handler.format_name()
+    // 已测试完的条目数（断点位置）
+    let mut consumed = skip_candidates(reader, &mut current, range.start, None);
+    consumed += skip_candidates(
+        reader,
+        &mut current,
+        resume.saturating_sub(range.start),
+        Some(seen),
     );
-    println!("目标文件: {archive_path}");
-    println!("CPU 核心数: {num_cpus}");
-    println!("字典文件: {dict_path}");
-    println!("字典条目: {} 个密码", passwords.len());
-    println!();
-    println!("检测到目标文件: {} (索引 {})", target.name, target.index);
-    println!("文件扩展名: .{}", target.extension);
-    println!("验证方式: 使用 infer 库检测解密后内容是否匹配扩展名");
-    println!();
-    println!("压缩包包含 {file_count} 个文件");
-    println!();
-    println!("开始破解...");
+    if consumed > 0 {
+        params.monitor.message(&format!("从第 {consumed} 条开始"));
+    }
+    bytes.batch = reader.bytes_read()..reader.bytes_read();
+
+    let mut has_more = read_batch(
+        reader,
+        &mut current,
+        batch_limit(&range, consumed),
+        params.monitor,
+    );
+
+    while has_more {
+        // 去重后记录每个候选在原始批次中的结束位置，断点按原始条目数计算
+        let batch_len = current.len() as u64;
+        let mut raw_ends = Vec::with_capacity(current.len());
+        let mut position = 0;
+        current.retain(|pwd| {
+            position += 1;
+            let unique = seen.insert(pwd);
+            if unique {
+                raw_ends.push(position);
+            }
+            unique
+        });
+        outcome.duplicates += batch_len - current.len() as u64;
+
+        // 测试当前批次的同时读取下一批
+        bytes.batch = bytes.batch.end..reader.bytes_read().min(reader.total_bytes());
+        let next_limit = batch_limit(&range, consumed + batch_len);
+        let ((hit, tested), more) = rayon::join(
+            || find_in_chunks(params, &current, &raw_ends, source_index, consumed, &bytes),
+            || read_batch(reader, &mut next, next_limit, params.monitor),
+        );
+        outcome.tested += tested;
+        if hit.is_some() {
+            outcome.password = hit;
+            break;
+        }
+        // 被中断的块不计入已完成，恢复时重新测试
+        if params.monitor.cancelled() {
+            outcome.interrupted = true;
+            break;
+        }
+        if params.budget.expired() {
+            break;
+        }
+
+        consumed += batch_len;
+        save_progress(params, source_index, consumed);
+        bytes.report(params.monitor, 1, 1);
+        std::mem::swap(&mut current, &mut next);
+        has_more = more;
+    }
 
+    if reader.skipped() > 0 {
+        params.monitor.message(&format!(
+            "已跳过 {} 行非 UTF-8 编码的条目",
+            reader.skipped()
+        ));
+    }
+
+    outcome
+}
+
+/// 分块测试一批（已去重的）候选，每块完成后记录断点，返回找到的密码和实际测试的候选数
+///
+/// `raw_ends[i]` 为第 `i` 个候选在去重前批次中的结束位置，`consumed` 为本批之前已完成的条目数
+fn find_in_chunks(
+    params: &DictionaryParams,
+    batch: &[String],
+    raw_ends: &[u64],
+    source_index: usize,
+    consumed: u64,
+    bytes: &ByteProgress,
+) -> (Option<String>, u64) {
+    let mut chunks = ChunkSizer::new();
+    let mut tested = 0;
+    let mut start = 0;
+    while start < batch.len() {
+        let end = (start + chunks.size()).min(batch.len());
+        let timer = Instant::now();
+        let (hit, chunk_tested) = find_in_batch(
+            &batch[start..end],
+            params.archive_path,
+            params.targets,
+            params.handler,
+            params.monitor,
+        );
+        tested += chunk_tested;
+        if hit.is_some() || params.monitor.cancelled() {
+            return (hit, tested);
+        }
+        save_progress(params, source_index, consumed + raw_ends[end - 1]);
+        bytes.report(params.monitor, end, batch.len());
+        if params.budget.expired() || params.monitor.checkpoint() {
+            break;
+        }
+        chunks.adjust(timer.elapsed());
+        start = end;
+    }
+    (None, tested)
+}
+
+/// 记录断点，自动保存失败时报告警告
+fn save_progress(params: &DictionaryParams, source_index: usize, line: u64) {
+    if let Some(tracker) = params.session
+        && let Err(e) = tracker.update_dictionary(source_index, line)
+    {
+        params.monitor.warning(&format!("无法保存会话: {e}"));
+    }
+}
+
+/// 把 `range` 范围内的条目记入去重记录（恢复会话时这些条目已测试过）
+fn remember_range(reader: &mut WordlistReader, seen: &mut SeenFilter, range: Range<u64>) {
+    let mut buffer = Vec::new();
+    skip_candidates(reader, &mut buffer, range.start, None);
+    skip_candidates(reader, &mut buffer, range.end - range.start, Some(seen));
+}
+
+/// 跳过前 `count` 条候选，返回实际跳过的数量
+///
+/// 跳过的候选已在之前测试过（恢复会话）时传入 `seen`，把它们记入去重记录
+fn skip_candidates(
+    reader: &mut WordlistReader,
+    buffer: &mut Vec<String>,
+    count: u64,
+    mut seen: Option<&mut SeenFilter>,
+) -> u64 {
+    let mut skipped = 0;
+    while skipped < count {
+        let want = usize::try_from(count - skipped).map_or(BATCH_SIZE, |n| n.min(BATCH_SIZE));
+        if !matches!(reader.next_batch(buffer, want), Ok(true)) {
+            break;
+        }
+        skipped += buffer.len() as u64;
+        if let Some(seen) = seen.as_deref_mut() {
+            for word in buffer.iter() {
+                seen.insert(word);
+            }
+        }
+    }
+    buffer.clear();
+    skipped
+}
+
+/// 内存候选列表攻击参数
+pub struct WordlistParams<'a> {
+    /// 候选来源（用于显示）
+    pub source: &'a str,
+    pub passwords: &'a [String],
+    pub archive_path: &'a Path,
+    /// 验证条目，候选必须全部通过
+    pub targets: &'a [TargetFile],
+    pub file_count: usize,
+    pub handler: &'a dyn ArchiveHandler,
+    /// 本阶段的时间和候选数量预算，超出预算的候选不再测试
+    pub budget: Budget,
+    /// 报告过程、取消和暂停
+    pub monitor: &'a Monitor<'a>,
+}
+
+/// 对内存中的候选密码列表执行攻击
+#[must_use]
+pub fn wordlist_attack(params: &WordlistParams) -> DictionaryResult {
+    let WordlistParams {
+        source,
+        passwords,
+        archive_path,
+        targets,
+        handler,
+        budget,
+        monitor,
+        ..
+    } = *params;
+    monitor.message(&banner(
+        handler,
+        archive_path,
+        source,
+        &format!("候选条目: {} 个密码", passwords.len()),
+        targets,
+        params.file_count,
+    ));
+
+    monitor.progress().set_detail(source.to_string());
     let start = Instant::now();
-    let total_tested = passwords.len() as u64;
+    let allowed =
+        usize::try_from(budget.remaining(0)).map_or(passwords.len(), |n| n.min(passwords.len()));
+    monitor.progress().set_keyspace(allowed as u128, 0);
+    let mut chunks = ChunkSizer::new();
+    let mut position = 0;
+    let mut tested = 0;
+    let mut password = None;
+    while position < allowed && password.is_none() && !monitor.checkpoint() && !budget.expired() {
+        let end = (position + chunks.size()).min(allowed);
+        let timer = Instant::now();
+        let chunk_tested;
+        (password, chunk_tested) = find_in_batch(
+            &passwords[position..end],
+            archive_path,
+            targets,
+            handler,
+            monitor,
+        );
+        tested += chunk_tested;
+        chunks.adjust(timer.elapsed());
+        position = end;
+    }
+    let interrupted = password.is_none() && monitor.cancelled();
+
+    DictionaryResult {
+        budget_exhausted: password.is_none() && !interrupted && position < passwords.len(),
+        password,
+        total_tested: tested,
+        elapsed_secs: start.elapsed().as_secs_f64(),
+        sources: Vec::new(),
+        interrupted,
+    }
+}
 
+/// 并行测试一批候选密码，返回找到的密码和实际测试的候选数（找到或取消后其余候选不再测试）
+fn find_in_batch(
+    passwords: &[String],
+    archive_path: &Path,
+    targets: &[TargetFile],
+    handler: &dyn ArchiveHandler,
+    monitor: &Monitor,
+) -> (Option<String>, u64) {
     let found = Arc::new(AtomicBool::new(false));
+    let tested = AtomicU64::new(0);
     let password = passwords
         .par_iter()
         .find_any(|password| {
-            if found.load(Ordering::Relaxed) {
+            if found.load(Ordering::Relaxed) || monitor.cancelled() {
                 return false;
             }
-            if handler.try_password(archive_path, password, target) {
+            tested.fetch_add(1, Ordering::Relaxed);
+            monitor.progress().record();
+            if monitor.record(
+                password,
+                &handler.try_targets(archive_path, password, targets),
+            ) {
                 found.store(true, Ordering::Relaxed);
                 return true;
             }
             false
         })
         .cloned();
+    (password, tested.into_inner())
+}
 
-    let elapsed = start.elapsed();
+/// 从 `position` 开始还能读取的批大小（不超出 `range` 的结尾）
+fn batch_limit(range: &Range<u64>, position: u64) -> usize {
+    usize::try_from(range.end.saturating_sub(position)).map_or(BATCH_SIZE, |n| n.min(BATCH_SIZE))
+}
 
-    DictionaryResult {
-        password,
-        total_tested,
-        elapsed_secs: elapsed.as_secs_f64(),
+/// 读取下一批（最多 `max` 条），读取出错时提前结束
+fn read_batch(
+    reader: &mut WordlistReader,
+    batch: &mut Vec<String>,
+    max: usize,
+    monitor: &Monitor,
+) -> bool {
+    if max == 0 {
+        batch.clear();
+        return false;
     }
+    match reader.next_batch(batch, max) {
+        Ok(more) => more,
+        Err(e) => {
+            monitor.warning(&format!("读取字典出错，提前结束: {e}"));
+            false
+        }
+    }
+}
+
+/// 攻击信息
+fn banner(
+    handler: &dyn ArchiveHandler,
+    archive_path: &Path,
+    source: &str,
+    size_line: &str,
+    targets: &[TargetFile],
+    file_count: usize,
+) -> String {
+    let mut lines = vec![
+        format!(
+            "=== {} 密码字典攻击器 (Rust 多线程版) ===",
+            handler.format_name()
+        ),
+        format!("目标文件: {}", archive_path.display()),
+        format!("CPU 核心数: {}", num_cpus::get()),
+        format!("候选来源: {source}"),
+        size_line.to_string(),
+        String::new(),
+    ];
+    lines.extend(targets.iter().map(TargetFile::describe));
+    lines.extend([
+        String::new(),
+        format!("压缩包包含 {file_count} 个文件"),
+        String::new(),
+        "开始破解...".to_string(),
+    ]);
+    lines.join("\n")
 }
@@ -0,0 +1,319 @@
+use std::borrow::Cow;
+use std::collections::HashSet;
+use std::fs::File;
+use std::hash::{BuildHasher, BuildHasherDefault, DefaultHasher};
+use std::io::{self, BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::archive::common::{from_hex, to_hex};
+
+/// 每批送入 rayon 的候选密码数量
+pub const BATCH_SIZE: usize = 64 * 1024;
+
+/// 去重集合最多记录的条目数（8M 个 64 位哈希，约 150 MB）
+pub const MAX_SEEN_ENTRIES: usize = 8 * 1024 * 1024;
+
+/// 读取缓冲区大小
+const READ_BUFFER_SIZE: usize = 1024 * 1024;
+
+/// 十六进制编码的密码行前缀（与 hashcat 相同）
+const HEX_PREFIX: &str = "$HEX[";
+
+/// 按行保存密码时的写法：含换行符、以 `#` 开头或本身形如 `$HEX[` 的密码写成 `$HEX[十六进制]`
+///
+/// 用于字典、potfile 和分布式协议，读取时用 [`decode_word`] 还原
+#[must_use]
+pub fn encode_word(word: &str) -> Cow<'_, str> {
+    if word.contains(['\n', '\r']) || word.starts_with('#') || word.starts_with(HEX_PREFIX) {
+        Cow::Owned(format!("{HEX_PREFIX}{}]", to_hex(word.as_bytes())))
+    } else {
+        Cow::Borrowed(word)
+    }
+}
+
+/// 还原 `$HEX[十六进制]` 形式的密码行，其他行（包括解码后不是 UTF-8 的）原样返回
+#[must_use]
+pub fn decode_word(line: &str) -> Cow<'_, str> {
+    line.strip_prefix(HEX_PREFIX)
+        .and_then(|rest| rest.strip_suffix(']'))
+        .and_then(from_hex)
+        .and_then(|bytes| String::from_utf8(bytes).ok())
+        .map_or(Cow::Borrowed(line), Cow::Owned)
+}
+
+/// 字典文件压缩格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    Gzip,
+    Xz,
+    Zstd,
+}
+
+impl Compression {
+    /// 通过文件魔数检测压缩格式
+    #[must_use]
+    pub fn detect(header: &[u8]) -> Self {
+        if header.starts_with(&[0x1f, 0x8b]) {
+            Self::Gzip
+        } else if header.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0x00]) {
+            Self::Xz
+        } else if header.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            Self::Zstd
+        } else {
+            Self::None
+        }
+    }
+
+    /// 格式名称
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::None => "纯文本",
+            Self::Gzip => "gzip",
+            Self::Xz => "xz",
+            Self::Zstd => "zstd",
+        }
+    }
+}
+
+/// 统计已读取字节数的包装读取器（压缩文件统计的是压缩后的字节）
+struct CountingReader<R> {
+    inner: R,
+    count: Arc<AtomicU64>,
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.count.fetch_add(n as u64, Ordering::Relaxed);
+        Ok(n)
+    }
+}
+
+/// 流式字典读取器
+///
+/// 按批读取，不把整个字典载入内存；自动识别 `.gz`、`.xz`、`.zst` 压缩格式，
+/// 去除行尾 `\r`，跳过空行和 `#` 注释行，还原 `$HEX[...]` 行，进度以原始文件的字节偏移计算
+pub struct WordlistReader {
+    reader: Box<dyn BufRead + Send>,
+    bytes_read: Arc<AtomicU64>,
+    total_bytes: u64,
+    compression: Compression,
+    line: Vec<u8>,
+    skipped: u64,
+}
+
+impl WordlistReader {
+    /// 打开字典文件
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let mut file = File::open(path)?;
+        let total_bytes = file.metadata()?.len();
+
+        let mut header = [0u8; 6];
+        let header_len = read_header(&mut file, &mut header)?;
+        let compression = Compression::detect(&header[..header_len]);
+
+        file.seek(SeekFrom::Start(0))?;
+
+        let bytes_read = Arc::new(AtomicU64::new(0));
+        let counting = CountingReader {
+            inner: file,
+            count: Arc::clone(&bytes_read),
+        };
+
+        let raw = BufReader::with_capacity(READ_BUFFER_SIZE, counting);
+        let reader: Box<dyn BufRead + Send> = match compression {
+            Compression::None => Box::new(raw),
+            Compression::Gzip => Box::new(BufReader::with_capacity(
+                READ_BUFFER_SIZE,
+                flate2::bufread::MultiGzDecoder::new(raw),
+            )),
+            Compression::Xz => Box::new(BufReader::with_capacity(
+                READ_BUFFER_SIZE,
+                lzma_rust2::XzReader::new(raw, true),
+            )),
+            Compression::Zstd => Box::new(BufReader::with_capacity(
+                READ_BUFFER_SIZE,
+                zstd::stream::read::Decoder::with_buffer(raw)?,
+            )),
+        };
+
+        Ok(Self {
+            reader,
+            bytes_read,
+            total_bytes,
+            compression,
+            line: Vec::new(),
+            skipped: 0,
+        })
+    }
+
+    /// 读取下一批候选密码到 `batch`（先清空），返回是否读到了内容
+    pub fn next_batch(&mut self, batch: &mut Vec<String>, max: usize) -> io::Result<bool> {
+        batch.clear();
+
+        while batch.len() < max {
+            self.line.clear();
+            if self.reader.read_until(b'\n', &mut self.line)? == 0 {
+                break;
+            }
+
+            let mut line = self.line.as_slice();
+            if let Some(rest) = line.strip_suffix(b"\n") {
+                line = rest;
+            }
+            if let Some(rest) = line.strip_suffix(b"\r") {
+                line = rest;
+            }
+            if line.is_empty() || line.starts_with(b"#") {
+                continue;
+            }
+
+            // 密码按 UTF-8 字节提交给压缩库，无法表示的行只能跳过
+            match std::str::from_utf8(line) {
+                Ok(password) => batch.push(decode_word(password).into_owned()),
+                Err(_) => self.skipped += 1,
+            }
+        }
+
+        Ok(!batch.is_empty())
+    }
+
+    /// 已读取的原始文件字节数
+    #[must_use]
+    pub fn bytes_read(&self) -> u64 {
+        self.bytes_read.load(Ordering::Relaxed)
+    }
+
+    /// 原始文件总字节数
+    #[must_use]
+    pub const fn total_bytes(&self) -> u64 {
+        self.total_bytes
+    }
+
+    /// 读取进度百分比
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn progress_percent(&self) -> f64 {
+        if self.total_bytes == 0 {
+            100.0
+        } else {
+            (self.bytes_read() as f64 / self.total_bytes as f64 * 100.0).min(100.0)
+        }
+    }
+
+    /// 字典压缩格式
+    #[must_use]
+    pub const fn compression(&self) -> Compression {
+        self.compression
+    }
+
+    /// 因非 UTF-8 编码被跳过的行数
+    #[must_use]
+    pub const fn skipped(&self) -> u64 {
+        self.skipped
+    }
+}
+
+/// 读取文件开头用于检测格式的魔数
+fn read_header(file: &mut File, header: &mut [u8]) -> io::Result<usize> {
+    let mut filled = 0;
+    while filled < header.len() {
+        let n = file.read(&mut header[filled..])?;
+        if n == 0 {
+            break;
+        }
+        filled += n;
+    }
+    Ok(filled)
+}
+
+/// 有容量上限的去重集合，只保存 64 位哈希
+///
+/// 超过上限后不再记录新条目（仍会过滤已记录的重复项），保证超大字典的内存占用可控
+#[derive(Default)]
+pub struct SeenFilter {
+    hashes: HashSet<u64>,
+}
+
+impl SeenFilter {
+    /// 返回该密码是否首次出现
+    pub fn insert(&mut self, password: &str) -> bool {
+        let hash = BuildHasherDefault::<DefaultHasher>::default().hash_one(password);
+        if self.hashes.len() >= MAX_SEEN_ENTRIES {
+            return !self.hashes.contains(&hash);
+        }
+        self.hashes.insert(hash)
+    }
+}
+
+/// 格式化字节数
+#[must_use]
+#[allow(clippy::cast_precision_loss)]
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: &[&str] = &["B", "KB", "MB", "GB", "TB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{value:.1} {}", UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seen_filter_reports_first_occurrence_only() {
+        let mut seen = SeenFilter::default();
+        assert!(seen.insert("123456"));
+        assert!(seen.insert("password"));
+        assert!(!seen.insert("123456"));
+        assert!(!seen.insert("password"));
+    }
+
+    #[test]
+    fn plain_words_are_written_as_is() {
+        for word in ["123456", "pass word", "a#b", "$HEX", "密码"] {
+            assert_eq!(encode_word(word), word);
+            assert_eq!(decode_word(word), word);
+        }
+    }
+
+    #[test]
+    fn special_words_round_trip_through_hex() {
+        for word in ["a\nb", "line\r", "#comment", "$HEX[41]", "\n"] {
+            let line = encode_word(word);
+            assert!(line.starts_with("$HEX["), "{line}");
+            assert!(!line.contains(['\n', '\r']));
+            assert_eq!(decode_word(&line), word);
+        }
+        assert_eq!(encode_word("a\nb"), "$HEX[610a62]");
+    }
+
+    #[test]
+    fn invalid_hex_lines_are_kept() {
+        for line in ["$HEX[", "$HEX[zz]", "$HEX[4]", "$HEX[ff]", "$HEX[41"] {
+            assert_eq!(decode_word(line), line);
+        }
+    }
+
+    #[test]
+    fn seen_filter_is_case_and_whitespace_sensitive() {
+        let mut seen = SeenFilter::default();
+        assert!(seen.insert("abc"));
+        assert!(seen.insert("ABC"));
+        assert!(seen.insert("abc "));
+        assert!(seen.insert(""));
+        assert!(!seen.insert(""));
+    }
+}
@@ -0,0 +1,331 @@
+//! 基准测试：测量各加密方式和验证方式下每秒能测试的候选数量
+//!
+//! 在内存中生成 `ZipCrypto`、AES-128/AES-256 ZIP 和 7z AES 样本（每种加密方式按文件头、文本、
+//! 仅 CRC 三种验证方式各一个条目），写入临时目录后用正式破解时的处理器测试错误密码，
+//! 分别测量单线程和全部核心的速度。结果保存到 `~/.archive_cracker/bench.toml`，
+//! 运行前估算耗时时按目标条目的加密方式和验证方式查找
+
+use serde::{Deserialize, Serialize};
+use sevenz_rust::{AesEncoderOptions, SevenZArchiveEntry, SevenZMethod, SevenZWriter};
+use std::fs;
+use std::io::{self, Cursor, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use zip::unstable::write::FileOptionsExt;
+use zip::write::SimpleFileOptions;
+use zip::{AesMode, CompressionMethod, ZipWriter};
+
+use crate::archive::{
+    ArchiveFormat, ArchiveHandler, SEVENZ_KEY_CYCLES_POWER, TargetFile, Verification, get_handler,
+};
+use crate::observer::Monitor;
+use crate::paths::data_dir;
+
+/// 样本的密码（测试的都是错误密码）
+const SAMPLE_PASSWORD: &str = "bench-sample";
+
+/// 样本条目的大小
+const SAMPLE_SIZE: usize = 4096;
+
+/// 默认的每项测量时长
+pub const DEFAULT_DURATION: Duration = Duration::from_secs(1);
+
+/// 样本的加密方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Cipher {
+    ZipCrypto,
+    ZipAes128,
+    ZipAes256,
+    SevenZAes,
+}
+
+impl Cipher {
+    pub const ALL: [Self; 4] = [
+        Self::ZipCrypto,
+        Self::ZipAes128,
+        Self::ZipAes256,
+        Self::SevenZAes,
+    ];
+
+    /// 显示名称
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::ZipCrypto => "ZIP ZipCrypto",
+            Self::ZipAes128 => "ZIP AES-128",
+            Self::ZipAes256 => "ZIP AES-256",
+            Self::SevenZAes => "7z AES-256",
+        }
+    }
+
+    /// 条目加密方式（[`EntryInfo::encryption`](crate::archive::EntryInfo::encryption)）对应的样本，
+    /// AES-192 的密钥派生开销介于两者之间，按 AES-256 估算
+    #[must_use]
+    pub fn for_entry(format: ArchiveFormat, encryption: &str) -> Option<Self> {
+        match format {
+            ArchiveFormat::SevenZ => Some(Self::SevenZAes),
+            ArchiveFormat::Zip => match encryption {
+                "ZipCrypto" => Some(Self::ZipCrypto),
+                "AES-128" => Some(Self::ZipAes128),
+                "AES-192" | "AES-256" => Some(Self::ZipAes256),
+                _ => None,
+            },
+        }
+    }
+
+    const fn extension(self) -> &'static str {
+        match self {
+            Self::SevenZAes => "7z",
+            _ => "zip",
+        }
+    }
+}
+
+/// 一项测量结果（次/秒）
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Measurement {
+    pub cipher: Cipher,
+    pub verification: Verification,
+    /// 单线程速度
+    pub single_thread: f64,
+    /// 全部核心的速度
+    pub all_cores: f64,
+}
+
+impl Measurement {
+    /// 多核加速比
+    #[must_use]
+    pub fn scaling(&self) -> f64 {
+        if self.single_thread > 0.0 {
+            self.all_cores / self.single_thread
+        } else {
+            0.0
+        }
+    }
+}
+
+/// 基准测试结果
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BenchResults {
+    /// 测量时的线程数
+    pub threads: usize,
+    /// 测量时间（Unix 时间戳，秒）
+    pub measured_at: u64,
+    pub measurements: Vec<Measurement>,
+}
+
+impl BenchResults {
+    /// 保存路径 (~/.`archive_cracker/bench.toml`)
+    pub fn path() -> io::Result<PathBuf> {
+        Ok(data_dir()?.join("bench.toml"))
+    }
+
+    /// 读取保存的结果，没有运行过基准测试或文件无法解析时返回 `None`
+    #[must_use]
+    pub fn load() -> Option<Self> {
+        let content = fs::read_to_string(Self::path().ok()?).ok()?;
+        toml::from_str(&content).ok()
+    }
+
+    pub fn save(&self) -> io::Result<PathBuf> {
+        let path = Self::path()?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let content = toml::to_string(self).map_err(io::Error::other)?;
+        fs::write(&path, content)?;
+        Ok(path)
+    }
+
+    /// 全部核心的速度：优先使用验证方式相同的测量，已知内容按文件头估算，都没有时取该加密方式中最慢的
+    #[must_use]
+    pub fn speed(&self, cipher: Cipher, verification: Verification) -> Option<f64> {
+        let verification = match verification {
+            Verification::Known => Verification::Magic,
+            v => v,
+        };
+        let same_cipher = || self.measurements.iter().filter(|m| m.cipher == cipher);
+        same_cipher()
+            .find(|m| m.verification == verification)
+            .or_else(|| same_cipher().min_by(|a, b| a.all_cores.total_cmp(&b.all_cores)))
+            .map(|m| m.all_cores)
+    }
+
+    /// 测试 `candidates` 个候选的预计耗时
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn estimate(
+        &self,
+        cipher: Cipher,
+        verification: Verification,
+        candidates: u128,
+    ) -> Option<Duration> {
+        let speed = self.speed(cipher, verification).filter(|s| *s > 0.0)?;
+        Duration::try_from_secs_f64(candidates as f64 / speed).ok()
+    }
+}
+
+/// 压缩包中验证条目的加密方式对应的样本（条目未加密或无法读取时为 `None`）
+#[must_use]
+pub fn target_cipher(
+    handler: &dyn ArchiveHandler,
+    archive_path: &Path,
+    target: &TargetFile,
+) -> Option<Cipher> {
+    let format = ArchiveFormat::detect(archive_path)?;
+    let entries = handler.entries(archive_path).ok()?;
+    let entry = entries.iter().find(|entry| entry.index == target.index)?;
+    Cipher::for_entry(format, entry.encryption.as_deref()?)
+}
+
+/// 运行基准测试，每项测量 `duration`（7z 单次尝试较慢时会略长），通过 `monitor` 报告每项结果
+///
+/// 被取消时返回已完成的测量
+pub fn run_bench(duration: Duration, monitor: &Monitor) -> io::Result<BenchResults> {
+    let dir = std::env::temp_dir().join(format!("archive_cracker_bench_{}", std::process::id()));
+    fs::create_dir_all(&dir)?;
+    let result = measure_all(&dir, duration, monitor);
+    let _ = fs::remove_dir_all(&dir);
+    Ok(BenchResults {
+        threads: rayon::current_num_threads(),
+        measured_at: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs()),
+        measurements: result?,
+    })
+}
+
+fn measure_all(dir: &Path, duration: Duration, monitor: &Monitor) -> io::Result<Vec<Measurement>> {
+    let mut measurements = Vec::new();
+    for cipher in Cipher::ALL {
+        let path = dir.join(format!("{cipher:?}.{}", cipher.extension()));
+        fs::write(&path, build_sample(cipher)?)?;
+        let format = ArchiveFormat::detect(&path).ok_or_else(|| io::Error::other("未知格式"))?;
+        let handler = get_handler(format);
+
+        for entry in handler.entries(&path).map_err(io::Error::other)? {
+            if monitor.cancelled() {
+                return Ok(measurements);
+            }
+            let target = TargetFile::new(entry.index, entry.name);
+            let targets = [target];
+            let try_one = |password: &str| {
+                let _ = handler.try_targets(&path, password, &targets);
+            };
+            let measurement = Measurement {
+                cipher,
+                verification: targets[0].verification,
+                single_thread: measure_single(&try_one, duration),
+                all_cores: measure_parallel(&try_one, duration),
+            };
+            monitor.message(&format!(
+                "{:<14} {:<8} 单线程 {:>12.0} 次/秒  全部核心 {:>12.0} 次/秒 ({:.1}x)",
+                cipher.name(),
+                measurement.verification.label(),
+                measurement.single_thread,
+                measurement.all_cores,
+                measurement.scaling()
+            ));
+            measurements.push(measurement);
+        }
+    }
+    Ok(measurements)
+}
+
+/// 第 `n` 个错误密码（各线程使用不同的序列）
+fn wrong_password(thread: usize, n: u64) -> String {
+    format!("{thread:x}-{n:08x}")
+}
+
+/// 在当前线程测试错误密码，直到经过 `duration`
+#[allow(clippy::cast_precision_loss)]
+fn measure_single(try_one: &(dyn Fn(&str) + Sync), duration: Duration) -> f64 {
+    let start = Instant::now();
+    let mut count = 0;
+    while start.elapsed() < duration {
+        try_one(&wrong_password(0, count));
+        count += 1;
+    }
+    count as f64 / start.elapsed().as_secs_f64()
+}
+
+/// 在 rayon 线程池的每个线程上同时测试错误密码，直到经过 `duration`
+#[allow(clippy::cast_precision_loss)]
+pub(crate) fn measure_parallel(try_one: &(dyn Fn(&str) + Sync), duration: Duration) -> f64 {
+    let total = AtomicU64::new(0);
+    let start = Instant::now();
+    rayon::broadcast(|ctx| {
+        let mut count = 0;
+        while start.elapsed() < duration {
+            try_one(&wrong_password(ctx.index() + 1, count));
+            count += 1;
+        }
+        total.fetch_add(count, Ordering::Relaxed);
+    });
+    total.into_inner() as f64 / start.elapsed().as_secs_f64()
+}
+
+/// 三个条目的内容：PNG 文件头（按文件头验证）、文本、伪随机数据（仅 CRC）
+fn sample_entries() -> [(&'static str, Vec<u8>); 3] {
+    let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR\0\0\0\x10\0\0\0\x10\x08\x06\0\0\0".to_vec();
+    png.resize(SAMPLE_SIZE, 0);
+    let text = "基准测试样本 benchmark sample line\n"
+        .repeat(SAMPLE_SIZE / 40)
+        .into_bytes();
+    // xorshift 伪随机数据，不可压缩
+    let mut state = 0x2545_f491_4f6c_dd1d_u64;
+    let random = (0..SAMPLE_SIZE)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state.to_le_bytes()[0]
+        })
+        .collect();
+    [
+        ("sample.png", png),
+        ("sample.txt", text),
+        ("sample.bin", random),
+    ]
+}
+
+/// 在内存中生成样本压缩包
+fn build_sample(cipher: Cipher) -> io::Result<Vec<u8>> {
+    if cipher == Cipher::SevenZAes {
+        return build_sevenz_sample();
+    }
+    let mut writer = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+    let options = match cipher {
+        Cipher::ZipAes128 => options.with_aes_encryption(AesMode::Aes128, SAMPLE_PASSWORD),
+        Cipher::ZipAes256 => options.with_aes_encryption(AesMode::Aes256, SAMPLE_PASSWORD),
+        _ => options
+            .with_deprecated_encryption(SAMPLE_PASSWORD.as_bytes())
+            .map_err(io::Error::other)?,
+    };
+    for (name, data) in sample_entries() {
+        writer.start_file(name, options).map_err(io::Error::other)?;
+        writer.write_all(&data)?;
+    }
+    Ok(writer.finish().map_err(io::Error::other)?.into_inner())
+}
+
+fn build_sevenz_sample() -> io::Result<Vec<u8>> {
+    let mut writer = SevenZWriter::new(Cursor::new(Vec::new())).map_err(io::Error::other)?;
+    // 使用 7-Zip 默认的密钥派生强度，密钥派生占了单次尝试的绝大部分时间
+    let mut aes = AesEncoderOptions::new(SAMPLE_PASSWORD.into());
+    aes.num_cycles_power = SEVENZ_KEY_CYCLES_POWER;
+    writer.set_content_methods(vec![aes.into(), SevenZMethod::LZMA2.into()]);
+    // 只加密数据，文件头不加密，与常见的 7z 压缩包一致，也便于读取条目列表
+    writer.set_encrypt_header(false);
+    for (name, data) in sample_entries() {
+        let mut entry = SevenZArchiveEntry::new();
+        entry.name = name.to_string();
+        writer
+            .push_archive_entry(entry, Some(data.as_slice()))
+            .map_err(io::Error::other)?;
+    }
+    Ok(writer.finish()?.into_inner())
+}
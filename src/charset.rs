@@ -80,7 +80,7 @@ fn get_chinese_charset() -> Vec<char> {
     chars
 }
 
-/// 合并多个字符集（去重）
+/// 合并多个字符集（去重），未选择字符集时返回空集合
 #[must_use]
 pub fn get_combined_charset(charsets: &[Charset]) -> (String, Vec<char>) {
     // 去重字符集选择
@@ -90,11 +90,6 @@ pub fn get_combined_charset(charsets: &[Charset]) -> (String, Vec<char>) {
         .into_iter()
         .collect();
 
-    if unique_charsets.is_empty() {
-        eprintln!("错误: 必须至少选择一个字符集");
-        std::process::exit(1);
-    }
-
     // 合并所有字符集
     let mut char_set = HashSet::new();
     let mut names = Vec::new();
@@ -113,9 +108,11 @@ pub fn get_combined_charset(charsets: &[Charset]) -> (String, Vec<char>) {
 }
 
 /// 将索引转换为密码字符串
+///
+/// 索引为 u128，`ascii` 字符集可覆盖到 19 位、`chinese` 可覆盖到 10 位
 #[must_use]
-pub fn index_to_password(mut index: u64, chars: &[char], length: usize) -> String {
-    let base = chars.len() as u64;
+pub fn index_to_password(mut index: u128, chars: &[char], length: usize) -> String {
+    let base = chars.len() as u128;
     let mut result = String::with_capacity(length);
 
     for _ in 0..length {
@@ -1,15 +1,33 @@
 use clap::{Parser, ValueEnum};
+use serde::{Deserialize, Serialize};
+use std::io::{IsTerminal, Write};
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
 
-#[derive(Parser)]
+use crate::CrackError;
+use crate::archive::Verifier;
+use crate::archive::common::from_hex;
+use crate::attack::budget::{parse_deadline, parse_duration};
+use crate::config::{
+    BruteforceConfig, CrackConfig, ExpectedContent, KeyspaceSelection, Limits, ServeConfig,
+    StageSelection,
+};
+use crate::extract::{ExtractOptions, NameEncoding, Overwrite, parse_size};
+use crate::keyspace::Part;
+use crate::rewrap::{NewEncryption, RewrapOptions};
+
+#[derive(Parser, Clone, Debug)]
+#[allow(clippy::struct_excessive_bools)]
 #[command(name = "archive_cracker")]
 #[command(about = "压缩包密码破解器 - 支持 ZIP/7z/RAR (多线程)", long_about = None)]
 pub struct Args {
-    /// 压缩包文件路径 (支持 .zip, .7z, .rar)
+    /// 压缩包文件路径 (支持 .zip, .7z, .rar；--worker 模式下由协调进程下发)
+    #[arg(required_unless_present_any = ["worker", "bench"], default_value = "")]
     pub archive_path: String,
 
-    /// 字典文件路径 (默认: ~/.`archive_cracker/dictionary.txt`)
+    /// 字典文件或目录 (可多次指定，按指定顺序尝试；内置字典 ~/.`archive_cracker/dictionary.txt` 总是最先尝试)
     #[arg(short = 'D', long)]
-    pub dictionary: Option<String>,
+    pub dictionary: Vec<String>,
 
     /// 字符集选择 (可多选，用逗号分隔，如: lower,upper,digit)
     #[arg(
@@ -35,9 +53,347 @@ pub struct Args {
     /// 跳过字典攻击，直接暴力破解
     #[arg(long)]
     pub skip_dictionary: bool,
+
+    /// 不读取也不写入 potfile (~/.`archive_cracker/potfile.txt`，记录已破解压缩包的密码)
+    #[arg(long)]
+    pub skip_potfile: bool,
+
+    /// 跳过元数据候选词（文件名、注释、条目名、未加密说明文件）
+    #[arg(long)]
+    pub skip_harvest: bool,
+
+    /// 扫描压缩包所在目录的说明文件 (.txt/.url/.html/.nfo) 和同源压缩包，优先尝试其中的密码线索
+    #[arg(long)]
+    pub harvest_neighbors: bool,
+
+    /// 扫描邻近文件时递归的子目录层数 (0 表示只扫描压缩包所在目录)
+    #[arg(long, default_value = "0")]
+    pub harvest_depth: usize,
+
+    /// 跳过键空间的前 N 个候选 (键空间依次为字典条目和暴力破解索引)
+    #[arg(long, conflicts_with = "part")]
+    pub skip: Option<u128>,
+
+    /// 最多测试 N 个候选 (从 --skip 之后开始)
+    #[arg(long, conflicts_with = "part")]
+    pub limit: Option<u128>,
+
+    /// 只测试键空间均分为 M 份后的第 N 份，用于多台机器分工 (如 2/5)
+    #[arg(long, value_name = "N/M")]
+    pub part: Option<Part>,
+
+    /// 以协调进程运行：监听该地址 (如 0.0.0.0:7700)，把字典和暴力破解的键空间分块分发给 worker
+    #[arg(long, value_name = "ADDR", conflicts_with_all = ["worker", "skip", "limit", "part"])]
+    pub serve: Option<String>,
+
+    /// 以 worker 运行：连接协调进程 (如 192.168.1.10:7700)，从协调进程获取压缩包和任务
+    #[arg(long, value_name = "ADDR")]
+    pub worker: Option<String>,
+
+    /// 基准测试：测量各加密方式和验证方式的单线程和全部核心速度，结果保存到 ~/.`archive_cracker/bench.toml`
+    #[arg(long, conflicts_with = "worker")]
+    pub bench: bool,
+
+    /// 基准测试中每项测量的时长 (如 1、5、1m)
+    #[arg(long, value_name = "DURATION", default_value = "1", value_parser = parse_duration, requires = "bench")]
+    pub bench_time: Duration,
+
+    /// 只估算不破解：按实际顺序列出各阶段的键空间和预计耗时 (速度取基准测试结果，没有时实测)
+    #[arg(long, conflicts_with_all = ["worker", "bench"])]
+    pub dry_run: bool,
+
+    /// 单个阶段的预计耗时上限 (如 12h、30d)：--dry-run 时标出超出的阶段，正式运行时拒绝开始
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    pub horizon: Option<Duration>,
+
+    /// 按攻击计划执行 (计划名或 TOML 文件路径，计划名对应 ~/.`archive_cracker/plans/<name>.toml`)
+    #[arg(long, value_name = "NAME|FILE", conflicts_with_all = ["serve", "skip", "limit", "part"])]
+    pub plan: Option<String>,
+
+    /// 整个运行最多花费的时间 (如 90、10m、2h、1h30m)，用尽后保存会话并停止
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    pub max_time: Option<Duration>,
+
+    /// 整个运行最多测试的候选数量，用尽后保存会话并停止
+    #[arg(long, value_name = "N")]
+    pub max_candidates: Option<u64>,
+
+    /// 截止时间 (RFC 3339，如 2026-10-18T23:00:00+08:00)，到达后保存会话并停止
+    #[arg(long, value_name = "TIME", value_parser = parse_deadline)]
+    pub deadline: Option<SystemTime>,
+
+    /// 每隔多少秒输出一次进度、速度和预计剩余时间 (0 表示只在按回车键时输出)
+    #[arg(long, value_name = "SECS", default_value = "10")]
+    pub status_interval: u64,
+
+    /// 协调进程和 worker 之间的共享令牌：从环境变量 `ARCHIVE_CRACKER_TOKEN` 读取，未设置时交互输入
+    /// (用于 --serve 和 --worker；令牌不出现在命令行，也不写入会话文件)
+    #[arg(long)]
+    pub token: bool,
+
+    /// 协调模式下每个任务块的候选数量
+    #[arg(long, default_value = "100000")]
+    pub chunk_size: u64,
+
+    /// 协调模式下任务租约的超时秒数 (不少于 10)，worker 超时未汇报进度时任务重新分配
+    #[arg(long, default_value = "120")]
+    pub lease_timeout: u64,
+
+    /// 用于验证密码的条目：完整路径、通配符 (如 '*.txt') 或索引，代替自动选择的目标文件 (可多次指定，候选密码必须全部通过)
+    #[arg(long, value_name = "NAME|GLOB|INDEX")]
+    pub entry: Vec<String>,
+
+    /// 按条目破解：各条目使用不同密码时，按加密方式分组逐组破解，输出每个条目的密码
+    #[arg(long)]
+    pub per_entry: bool,
+
+    /// 用已知内容验证密码的条目 (压缩包内完整路径)，代替自动选择的目标文件，需配合 --expect-bytes
+    #[arg(long, value_name = "NAME", requires = "expect_bytes")]
+    pub expect_entry: Option<String>,
+
+    /// 该条目开头的已知内容：十六进制字符串 (如 89504e470d0a) 或文件路径 (比较该文件的全部内容)
+    #[arg(long, value_name = "HEX|FILE", requires = "expect_entry", value_parser = parse_expected_bytes)]
+    pub expect_bytes: Option<Verifier>,
+
+    /// 找到密码后解压到该目录 (不存在时创建)
+    #[arg(long, value_name = "DIR")]
+    pub extract: Option<String>,
+
+    /// 解压时目标文件已存在的处理方式
+    #[arg(long, value_enum, default_value = "skip", requires = "extract")]
+    pub overwrite: Overwrite,
+
+    /// 只解压名称匹配该通配符的条目 (如 '*.docx'、'照片/*')
+    #[arg(long, value_name = "GLOB", requires = "extract")]
+    pub extract_filter: Option<String>,
+
+    /// 解压和重新打包时 ZIP 条目名的编码 (auto: 有效的 UTF-8 按 UTF-8，否则按 GBK)
+    #[arg(long, value_enum, default_value = "auto")]
+    pub name_encoding: NameEncoding,
+
+    /// 解压总大小上限 (如 500M、16G)，防止压缩炸弹
+    #[arg(long, value_name = "SIZE", default_value = "16G", value_parser = parse_size, requires = "extract")]
+    pub extract_max_size: u64,
+
+    /// 单个条目的压缩比上限，超过时跳过该条目 (0 表示不限制)
+    #[arg(long, value_name = "N", default_value = "1000", requires = "extract")]
+    pub extract_max_ratio: u64,
+
+    /// 找到密码后重新打包到该文件 (格式与原压缩包相同，文件必须不存在)，默认去掉密码
+    #[arg(long, value_name = "FILE")]
+    pub rewrap: Option<String>,
+
+    /// 重新打包时用新密码加密：从环境变量 `ARCHIVE_CRACKER_NEW_PASSWORD` 读取，未设置时交互输入
+    /// (密码不出现在命令行，也不写入会话文件)
+    #[arg(long, requires = "rewrap")]
+    pub new_password: bool,
+
+    /// 新密码的加密方式 (7z 只支持 aes256)
+    #[arg(long, value_enum, default_value = "aes256", requires = "new_password")]
+    pub new_encryption: NewEncryption,
+
+    /// 会话名 (默认为压缩包文件名加路径哈希，如 data-1a2b3c4d)，中断后可用 --restore 继续
+    #[arg(long)]
+    pub session: Option<String>,
+
+    /// 从保存的会话继续 (~/.`archive_cracker/sessions/<name>.session`)，此时其余参数从会话文件读取
+    #[arg(long, value_name = "NAME")]
+    pub restore: Option<String>,
+}
+
+/// 不通过命令行参数传递的机密，由调用方另行读取（见 [`read_token`]、[`read_new_password`]）
+#[derive(Debug, Clone, Default)]
+pub struct Secrets {
+    /// `--serve --token` 时协调进程要求 worker 提供的令牌
+    pub token: Option<String>,
+    /// `--rewrap --new-password` 时重新打包使用的新密码
+    pub new_password: Option<String>,
+}
+
+impl Args {
+    /// 转换为破解配置，检查参数之间的冲突
+    ///
+    /// 只做转换，不读取终端或环境变量；`--token` 和 `--new-password` 需要的机密由 `secrets` 提供
+    pub fn to_config(&self, secrets: Secrets) -> Result<CrackConfig, CrackError> {
+        let (min_length, max_length) = match (self.length, self.max_length) {
+            (Some(len), None) => (len, len),
+            (None, Some(max)) => (self.min_length, max),
+            (Some(_), Some(_)) => return Err(CrackError::ConflictingLengthParams),
+            (None, None) => (1, 5),
+        };
+        let config = CrackConfig {
+            archive: PathBuf::from(&self.archive_path),
+            dictionaries: self.dictionary.iter().map(PathBuf::from).collect(),
+            stages: StageSelection {
+                potfile: !self.skip_potfile,
+                metadata: !self.skip_harvest,
+                neighbors: self.harvest_neighbors.then_some(self.harvest_depth),
+                dictionary: !self.skip_dictionary,
+            },
+            bruteforce: BruteforceConfig {
+                charsets: self.charset.clone(),
+                min_length,
+                max_length,
+            },
+            plan: self.plan.clone(),
+            limits: Limits {
+                max_time: self.max_time,
+                max_candidates: self.max_candidates,
+                deadline: self.deadline,
+            },
+            horizon: self.horizon,
+            keyspace: KeyspaceSelection::from_options(self.skip, self.limit, self.part)?,
+            serve: self.serve.as_ref().map(|addr| ServeConfig {
+                addr: addr.clone(),
+                chunk_size: self.chunk_size,
+                lease_timeout: Duration::from_secs(self.lease_timeout),
+                require_token: self.token,
+                token: secrets.token.filter(|_| self.token),
+            }),
+            session: self.session.clone(),
+            restore: self.restore.clone(),
+            status_interval: (self.status_interval > 0)
+                .then(|| Duration::from_secs(self.status_interval)),
+            entries: self.entry.clone(),
+            per_entry: self.per_entry,
+            extract: self.extract.as_ref().map(|dir| ExtractOptions {
+                dir: PathBuf::from(dir),
+                overwrite: self.overwrite,
+                filter: self.extract_filter.clone(),
+                encoding: self.name_encoding,
+                max_size: self.extract_max_size,
+                max_ratio: self.extract_max_ratio,
+            }),
+            rewrap: self.rewrap.as_ref().map(|output| RewrapOptions {
+                output: PathBuf::from(output),
+                encryption: self.new_password.then_some(self.new_encryption),
+                password: secrets.new_password.filter(|_| self.new_password),
+                encoding: self.name_encoding,
+            }),
+            expect: self
+                .expect_entry
+                .clone()
+                .zip(self.expect_bytes.clone())
+                .map(|(entry, verifier)| ExpectedContent { entry, verifier }),
+        };
+        config.validate()?;
+        Ok(config)
+    }
+}
+
+/// 解析 `--expect-bytes`：已存在的文件读取全部内容，否则按十六进制解析
+fn parse_expected_bytes(value: &str) -> Result<Verifier, String> {
+    let path = std::path::Path::new(value);
+    let bytes = if path.is_file() {
+        std::fs::read(path).map_err(|e| format!("无法读取 {value}: {e}"))?
+    } else {
+        from_hex(value).ok_or_else(|| format!("既不是文件也不是有效的十六进制字符串: {value}"))?
+    };
+    if bytes.is_empty() {
+        return Err("已知内容不能为空".to_string());
+    }
+    Ok(Verifier::Prefix(bytes))
+}
+
+/// 提供重新打包新密码的环境变量
+pub const NEW_PASSWORD_ENV: &str = "ARCHIVE_CRACKER_NEW_PASSWORD";
+
+/// 读取重新打包的新密码：优先环境变量 [`NEW_PASSWORD_ENV`]，否则从标准输入读取
+///
+/// 标准输入是终端时关闭回显并要求输入两次
+pub fn read_new_password() -> Result<String, CrackError> {
+    if let Ok(password) = std::env::var(NEW_PASSWORD_ENV)
+        && !password.is_empty()
+    {
+        return Ok(password);
+    }
+
+    let invalid = |message: &str| CrackError::InvalidRewrap(message.to_string());
+    let unreadable = |e: std::io::Error| invalid(&format!("无法读取新密码: {e}"));
+    let password = if std::io::stdin().is_terminal() {
+        let first = prompt_hidden("新密码: ").map_err(unreadable)?;
+        if prompt_hidden("再次输入新密码: ").map_err(unreadable)? != first {
+            return Err(invalid("两次输入的新密码不一致"));
+        }
+        first
+    } else {
+        read_stdin_line().map_err(unreadable)?
+    };
+    if password.is_empty() {
+        return Err(invalid("新密码不能为空"));
+    }
+    Ok(password)
+}
+
+/// 提供协调进程和 worker 之间令牌的环境变量
+pub const TOKEN_ENV: &str = "ARCHIVE_CRACKER_TOKEN";
+
+/// 读取协调进程和 worker 之间的令牌：优先环境变量 [`TOKEN_ENV`]，否则从标准输入读取
+///
+/// 标准输入是终端时关闭回显
+pub fn read_token() -> Result<String, CrackError> {
+    if let Ok(token) = std::env::var(TOKEN_ENV)
+        && !token.is_empty()
+    {
+        return Ok(token);
+    }
+
+    let token = if std::io::stdin().is_terminal() {
+        prompt_hidden("令牌: ")
+    } else {
+        read_stdin_line()
+    }
+    .map_err(|e| CrackError::InvalidToken(format!("无法读取令牌: {e}")))?;
+    if token.is_empty() {
+        return Err(CrackError::InvalidToken("令牌不能为空".to_string()));
+    }
+    Ok(token)
+}
+
+/// 关闭终端回显后读取一行（`stty` 不可用时照常回显）
+fn prompt_hidden(prompt: &str) -> std::io::Result<String> {
+    let stty = |arg: &str| {
+        std::process::Command::new("stty")
+            .arg(arg)
+            .stdin(std::process::Stdio::inherit())
+            .status()
+            .is_ok_and(|status| status.success())
+    };
+    print!("{prompt}");
+    let _ = std::io::stdout().flush();
+    let hidden = stty("-echo");
+    let line = read_stdin_line();
+    if hidden {
+        stty("echo");
+        println!();
+    }
+    line
+}
+
+/// 从标准输入读取一行，去掉行尾换行
+fn read_stdin_line() -> std::io::Result<String> {
+    let mut line = String::new();
+    std::io::stdin().read_line(&mut line)?;
+    let len = line.trim_end_matches(['\n', '\r']).len();
+    line.truncate(len);
+    Ok(line)
+}
+
+/// 在完整解析参数前查找 `--restore NAME`（恢复会话时不需要再提供压缩包路径）
+pub fn restore_session_name(mut args: impl Iterator<Item = String>) -> Option<String> {
+    while let Some(arg) = args.next() {
+        if arg == "--restore" {
+            return args.next();
+        }
+        if let Some(name) = arg.strip_prefix("--restore=") {
+            return Some(name.to_string());
+        }
+    }
+    None
 }
 
-#[derive(Clone, ValueEnum, PartialEq, Eq, Hash)]
+#[derive(Clone, Debug, ValueEnum, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Charset {
     /// 拼音声母 (20字符)
     Pinyin,
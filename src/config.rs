@@ -0,0 +1,602 @@
+//! 破解配置
+//!
+//! [`CrackConfig`] 是 [`crack_archive`](crate::crack_archive) 的输入：可以用 [`CrackConfig::builder`] 构建，
+//! 也可以用 serde 保存或传输。命令行参数 [`Args`](crate::Args) 通过 `Args::to_config` 转换为配置
+
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime};
+
+use crate::CrackError;
+use crate::archive::Verifier;
+use crate::attack::Budget;
+use crate::charset::get_combined_charset;
+use crate::cli::Charset;
+use crate::extract::ExtractOptions;
+use crate::keyspace::{KeyspaceSlice, Part, bruteforce_keyspace};
+use crate::rewrap::RewrapOptions;
+use crate::session::Session;
+
+/// 默认的进度报告间隔
+const DEFAULT_STATUS_INTERVAL: Duration = Duration::from_secs(10);
+
+/// 一次破解运行的完整配置
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CrackConfig {
+    /// 压缩包路径
+    pub archive: PathBuf,
+    /// 字典文件或目录，按顺序尝试（内置字典总是最先尝试）
+    pub dictionaries: Vec<PathBuf>,
+    /// 默认顺序中执行哪些阶段（使用攻击计划时只有 `potfile` 生效）
+    pub stages: StageSelection,
+    /// 暴力破解的字符集和长度范围
+    pub bruteforce: BruteforceConfig,
+    /// 攻击计划（计划名或 TOML 文件路径），设置后按计划中的阶段执行
+    pub plan: Option<String>,
+    /// 整个运行的预算
+    pub limits: Limits,
+    /// 单个阶段的预计耗时上限，开始新运行前有阶段超出时拒绝运行（恢复会话时不检查）
+    pub horizon: Option<Duration>,
+    /// 只测试键空间的一部分，用于多台机器分工
+    pub keyspace: Option<KeyspaceSelection>,
+    /// 作为协调进程把字典和暴力破解的键空间分发给 worker
+    pub serve: Option<ServeConfig>,
+    /// 会话名，默认为压缩包文件名加路径哈希（见 [`Session::default_name`]）
+    pub session: Option<String>,
+    /// 从该名称的已保存会话继续（由 [`CrackConfig::from_session`] 设置）
+    pub restore: Option<String>,
+    /// 定期报告进度的间隔，`None` 表示只在按回车键时报告（序列化为 0）
+    #[serde(with = "interval")]
+    pub status_interval: Option<Duration>,
+    /// 用于验证密码的条目（完整名称、通配符或索引），候选必须全部通过；为空时自动选择
+    pub entries: Vec<String>,
+    /// 用条目开头的已知内容验证密码，代替自动选择的目标文件
+    pub expect: Option<ExpectedContent>,
+    /// 按条目破解：条目使用不同密码时逐组破解（由 [`crack_entries`](crate::per_entry::crack_entries) 执行）
+    pub per_entry: bool,
+    /// 找到密码后解压
+    pub extract: Option<ExtractOptions>,
+    /// 找到密码后重新打包（去掉密码或换成新密码）
+    pub rewrap: Option<RewrapOptions>,
+}
+
+impl Default for CrackConfig {
+    fn default() -> Self {
+        Self {
+            archive: PathBuf::new(),
+            dictionaries: Vec::new(),
+            stages: StageSelection::default(),
+            bruteforce: BruteforceConfig::default(),
+            plan: None,
+            limits: Limits::default(),
+            horizon: None,
+            keyspace: None,
+            serve: None,
+            session: None,
+            restore: None,
+            status_interval: Some(DEFAULT_STATUS_INTERVAL),
+            entries: Vec::new(),
+            expect: None,
+            per_entry: false,
+            extract: None,
+            rewrap: None,
+        }
+    }
+}
+
+/// 默认顺序中的可选阶段
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct StageSelection {
+    /// 读取和写入 potfile（记录已破解压缩包的密码）
+    pub potfile: bool,
+    /// 尝试压缩包元数据中的候选词（文件名、注释、条目名、未加密说明文件）
+    pub metadata: bool,
+    /// 扫描压缩包所在目录的说明文件和同源压缩包，值为递归的子目录层数
+    pub neighbors: Option<usize>,
+    /// 字典攻击
+    pub dictionary: bool,
+}
+
+impl Default for StageSelection {
+    fn default() -> Self {
+        Self {
+            potfile: true,
+            metadata: true,
+            neighbors: None,
+            dictionary: true,
+        }
+    }
+}
+
+/// 暴力破解阶段的参数
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct BruteforceConfig {
+    /// 字符集（合并去重）
+    pub charsets: Vec<Charset>,
+    /// 最小密码长度
+    pub min_length: u32,
+    /// 最大密码长度
+    pub max_length: u32,
+}
+
+impl Default for BruteforceConfig {
+    fn default() -> Self {
+        Self {
+            charsets: vec![Charset::Lower, Charset::Upper, Charset::Digit],
+            min_length: 1,
+            max_length: 5,
+        }
+    }
+}
+
+impl BruteforceConfig {
+    /// 键空间大小，参数无效时返回对应的错误
+    pub fn keyspace(&self) -> Result<u128, CrackError> {
+        if self.min_length > self.max_length {
+            return Err(CrackError::InvalidLengthRange(
+                self.min_length,
+                self.max_length,
+            ));
+        }
+        if self.min_length == 0 {
+            return Err(CrackError::ZeroLength);
+        }
+        if self.charsets.is_empty() {
+            return Err(CrackError::EmptyCharset);
+        }
+        let (_, chars) = get_combined_charset(&self.charsets);
+        bruteforce_keyspace(chars.len(), self.min_length, self.max_length)
+            .ok_or(CrackError::KeyspaceTooLarge(chars.len(), self.max_length))
+    }
+}
+
+/// 整个运行的预算，用尽后保存会话并停止
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Limits {
+    /// 最多运行的时间
+    pub max_time: Option<Duration>,
+    /// 最多测试的候选数量
+    pub max_candidates: Option<u64>,
+    /// 截止时间（恢复会话时仍然有效）
+    pub deadline: Option<SystemTime>,
+}
+
+impl Limits {
+    /// 从现在开始计时的预算
+    #[must_use]
+    pub fn budget(&self) -> Budget {
+        Budget::new(self.max_time, self.max_candidates).with_deadline(self.deadline)
+    }
+}
+
+/// 本次运行负责的键空间范围（键空间依次为字典条目和暴力破解索引）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase", deny_unknown_fields)]
+pub enum KeyspaceSelection {
+    /// 跳过前 `skip` 个候选，之后最多测试 `limit` 个
+    Range {
+        #[serde(default, with = "decimal::option")]
+        skip: Option<u128>,
+        #[serde(default, with = "decimal::option")]
+        limit: Option<u128>,
+    },
+    /// 均分后的第 N 份
+    Part(Part),
+}
+
+impl KeyspaceSelection {
+    /// 由 `--skip`/`--limit`/`--part` 形式的参数生成，都未设置时为 `None`
+    pub const fn from_options(
+        skip: Option<u128>,
+        limit: Option<u128>,
+        part: Option<Part>,
+    ) -> Result<Option<Self>, CrackError> {
+        match (part, skip, limit) {
+            (Some(_), Some(_), _) | (Some(_), _, Some(_)) => {
+                Err(CrackError::ConflictingPartitionParams)
+            }
+            (Some(part), None, None) => Ok(Some(Self::Part(part))),
+            (None, None, None) => Ok(None),
+            (None, skip, limit) => Ok(Some(Self::Range { skip, limit })),
+        }
+    }
+
+    /// 字典部分共 `dictionary_total` 个条目时本次运行负责的分片
+    #[must_use]
+    pub fn slice(self, dictionary_total: u64, bruteforce_total: u128) -> KeyspaceSlice {
+        let (skip, limit, part) = match self {
+            Self::Range { skip, limit } => (skip, limit, None),
+            Self::Part(part) => (None, None, Some(part)),
+        };
+        KeyspaceSlice::new(dictionary_total, bruteforce_total, skip, limit, part)
+    }
+}
+
+/// 可选的时间间隔序列化为 [`Duration`]，0 表示 `None`
+///
+/// 默认值不是 `None`，省略字段无法表示关闭
+mod interval {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+    use std::time::Duration;
+
+    #[allow(clippy::ref_option)]
+    pub fn serialize<S: Serializer>(
+        value: &Option<Duration>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        value.unwrap_or(Duration::ZERO).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Duration>, D::Error> {
+        Ok(Some(Duration::deserialize(deserializer)?).filter(|interval| !interval.is_zero()))
+    }
+}
+
+/// `u128` 序列化为十进制字符串（TOML 的整数只有 64 位）
+pub(crate) mod decimal {
+    use serde::{Deserialize, Deserializer, Serializer, de};
+
+    pub fn serialize<S: Serializer>(value: &u128, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(value)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u128, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+
+    pub mod option {
+        use serde::{Deserialize, Deserializer, Serializer};
+
+        #[allow(clippy::ref_option)]
+        pub fn serialize<S: Serializer>(
+            value: &Option<u128>,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            match value {
+                Some(value) => super::serialize(value, serializer),
+                None => serializer.serialize_none(),
+            }
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>>(
+            deserializer: D,
+        ) -> Result<Option<u128>, D::Error> {
+            #[derive(Deserialize)]
+            struct Decimal(#[serde(with = "super")] u128);
+
+            Ok(Option::<Decimal>::deserialize(deserializer)?.map(|Decimal(value)| value))
+        }
+    }
+}
+
+/// 已知内容：条目 `entry` 解密后的开头应通过 `verifier` 的检查
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ExpectedContent {
+    /// 条目名称（压缩包内的完整路径）
+    pub entry: String,
+    /// 已知的开头字节（序列化为十六进制），或库调用方提供的验证函数（不能序列化，也不会保存到会话）
+    pub verifier: Verifier,
+}
+
+/// 协调进程参数
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServeConfig {
+    /// 监听地址（如 0.0.0.0:7700）
+    pub addr: String,
+    /// 每个任务块的候选数量
+    pub chunk_size: u64,
+    /// 任务租约的超时，worker 超时未汇报进度时任务重新分配
+    pub lease_timeout: Duration,
+    /// 是否要求 worker 提供令牌（令牌本身不保存到会话，恢复时重新读取）
+    pub require_token: bool,
+    /// worker 需要提供的令牌
+    #[serde(skip)]
+    pub token: Option<String>,
+}
+
+impl Default for ServeConfig {
+    fn default() -> Self {
+        Self {
+            addr: String::new(),
+            chunk_size: 100_000,
+            lease_timeout: Duration::from_mins(2),
+            require_token: false,
+            token: None,
+        }
+    }
+}
+
+impl CrackConfig {
+    /// 以默认参数开始构建 `archive` 的配置
+    pub fn builder(archive: impl Into<PathBuf>) -> CrackConfigBuilder {
+        CrackConfigBuilder {
+            config: Self {
+                archive: archive.into(),
+                ..Self::default()
+            },
+            skip: None,
+            limit: None,
+            part: None,
+        }
+    }
+
+    /// 已保存会话的配置，用它运行时从断点继续
+    pub fn from_session(name: &str) -> Result<Self, CrackError> {
+        let session = Session::load(name)
+            .map_err(|e| CrackError::SessionLoad(name.to_string(), e.to_string()))?;
+        Ok(Self {
+            restore: Some(name.to_string()),
+            ..session.config
+        })
+    }
+
+    /// 检查配置，返回暴力破解部分的键空间大小
+    pub fn validate(&self) -> Result<u128, CrackError> {
+        if let Some(KeyspaceSelection::Part(part)) = self.keyspace
+            && (part.index == 0 || part.index > part.count)
+        {
+            return Err(CrackError::InvalidPart(part));
+        }
+        if self.per_entry
+            && (!self.entries.is_empty() || self.expect.is_some() || self.serve.is_some())
+        {
+            return Err(CrackError::ConflictingPerEntry);
+        }
+        // 攻击计划按自己的阶段顺序在本机运行，不支持分布式和键空间分片
+        if self.plan.is_some() && (self.serve.is_some() || self.keyspace.is_some()) {
+            return Err(CrackError::ConflictingPlan);
+        }
+        if let Some(name) = &self.session
+            && !Session::is_valid_name(name)
+        {
+            return Err(CrackError::InvalidSessionName(name.clone()));
+        }
+        if let Some(rewrap) = &self.rewrap {
+            rewrap.check(&self.archive)?;
+        }
+        self.bruteforce.keyspace()
+    }
+}
+
+/// [`CrackConfig`] 的构建器
+///
+/// ```
+/// use archive_cracker::CrackConfig;
+/// use archive_cracker::cli::Charset;
+/// use std::time::Duration;
+///
+/// let config = CrackConfig::builder("file.zip")
+///     .dictionary("/data/wordlists")
+///     .charsets([Charset::Lower, Charset::Digit])
+///     .length_range(1, 6)
+///     .max_time(Duration::from_mins(30))
+///     .build()
+///     .unwrap();
+/// assert_eq!(config.bruteforce.max_length, 6);
+/// ```
+#[derive(Debug, Clone)]
+#[must_use]
+pub struct CrackConfigBuilder {
+    config: CrackConfig,
+    skip: Option<u128>,
+    limit: Option<u128>,
+    part: Option<Part>,
+}
+
+impl CrackConfigBuilder {
+    /// 添加字典文件或目录
+    pub fn dictionary(mut self, path: impl Into<PathBuf>) -> Self {
+        self.config.dictionaries.push(path.into());
+        self
+    }
+
+    /// 设置暴力破解字符集
+    pub fn charsets(mut self, charsets: impl IntoIterator<Item = Charset>) -> Self {
+        self.config.bruteforce.charsets = charsets.into_iter().collect();
+        self
+    }
+
+    /// 只尝试长度为 `length` 的密码
+    pub const fn length(self, length: u32) -> Self {
+        self.length_range(length, length)
+    }
+
+    /// 从 `min` 到 `max` 逐一尝试各长度
+    pub const fn length_range(mut self, min: u32, max: u32) -> Self {
+        self.config.bruteforce.min_length = min;
+        self.config.bruteforce.max_length = max;
+        self
+    }
+
+    /// 是否跳过字典攻击
+    pub const fn skip_dictionary(mut self, skip: bool) -> Self {
+        self.config.stages.dictionary = !skip;
+        self
+    }
+
+    /// 是否不读取也不写入 potfile
+    pub const fn skip_potfile(mut self, skip: bool) -> Self {
+        self.config.stages.potfile = !skip;
+        self
+    }
+
+    /// 是否跳过元数据候选词
+    pub const fn skip_harvest(mut self, skip: bool) -> Self {
+        self.config.stages.metadata = !skip;
+        self
+    }
+
+    /// 扫描邻近文件，递归 `depth` 层子目录
+    pub const fn harvest_neighbors(mut self, depth: usize) -> Self {
+        self.config.stages.neighbors = Some(depth);
+        self
+    }
+
+    /// 按攻击计划执行（计划名或 TOML 文件路径）
+    pub fn plan(mut self, plan: impl Into<String>) -> Self {
+        self.config.plan = Some(plan.into());
+        self
+    }
+
+    /// 整个运行最多花费的时间
+    pub const fn max_time(mut self, max_time: Duration) -> Self {
+        self.config.limits.max_time = Some(max_time);
+        self
+    }
+
+    /// 整个运行最多测试的候选数量
+    pub const fn max_candidates(mut self, max_candidates: u64) -> Self {
+        self.config.limits.max_candidates = Some(max_candidates);
+        self
+    }
+
+    /// 截止时间
+    pub const fn deadline(mut self, deadline: SystemTime) -> Self {
+        self.config.limits.deadline = Some(deadline);
+        self
+    }
+
+    /// 单个阶段的预计耗时上限，有阶段超出时拒绝运行
+    pub const fn horizon(mut self, horizon: Duration) -> Self {
+        self.config.horizon = Some(horizon);
+        self
+    }
+
+    /// 跳过键空间的前 `skip` 个候选
+    pub const fn skip(mut self, skip: u128) -> Self {
+        self.skip = Some(skip);
+        self
+    }
+
+    /// 最多测试 `limit` 个候选（从 `skip` 之后开始）
+    pub const fn limit(mut self, limit: u128) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// 只测试键空间均分后的第 N 份（不能与 `skip`/`limit` 同时使用）
+    pub const fn part(mut self, part: Part) -> Self {
+        self.part = Some(part);
+        self
+    }
+
+    /// 作为协调进程监听 `addr`
+    pub fn serve(mut self, addr: impl Into<String>) -> Self {
+        self.config
+            .serve
+            .get_or_insert_with(ServeConfig::default)
+            .addr = addr.into();
+        self
+    }
+
+    /// 协调模式下每个任务块的候选数量
+    pub fn chunk_size(mut self, chunk_size: u64) -> Self {
+        self.config
+            .serve
+            .get_or_insert_with(ServeConfig::default)
+            .chunk_size = chunk_size;
+        self
+    }
+
+    /// 协调模式下任务租约的超时
+    pub fn lease_timeout(mut self, lease_timeout: Duration) -> Self {
+        self.config
+            .serve
+            .get_or_insert_with(ServeConfig::default)
+            .lease_timeout = lease_timeout;
+        self
+    }
+
+    /// 协调模式下要求 worker 提供的令牌
+    pub fn token(mut self, token: impl Into<String>) -> Self {
+        let serve = self.config.serve.get_or_insert_with(ServeConfig::default);
+        serve.require_token = true;
+        serve.token = Some(token.into());
+        self
+    }
+
+    /// 会话名
+    pub fn session(mut self, name: impl Into<String>) -> Self {
+        self.config.session = Some(name.into());
+        self
+    }
+
+    /// 添加用于验证密码的条目：完整名称、通配符（`*`、`?`）或索引
+    pub fn entry(mut self, selector: impl Into<String>) -> Self {
+        self.config.entries.push(selector.into());
+        self
+    }
+
+    /// 按条目破解：条目使用不同密码时逐组破解
+    pub const fn per_entry(mut self, enabled: bool) -> Self {
+        self.config.per_entry = enabled;
+        self
+    }
+
+    /// 找到密码后解压，如 `ExtractOptions::new("out")`
+    pub fn extract(mut self, options: ExtractOptions) -> Self {
+        self.config.extract = Some(options);
+        self
+    }
+
+    /// 找到密码后重新打包，如 `RewrapOptions::new("明文.zip")`
+    pub fn rewrap(mut self, options: RewrapOptions) -> Self {
+        self.config.rewrap = Some(options);
+        self
+    }
+
+    /// 用已知内容验证条目 `entry`，如 `Verifier::Prefix` 或 `Verifier::custom`
+    pub fn expect(mut self, entry: impl Into<String>, verifier: Verifier) -> Self {
+        self.config.expect = Some(ExpectedContent {
+            entry: entry.into(),
+            verifier,
+        });
+        self
+    }
+
+    /// 定期报告进度的间隔，`None` 表示只在按回车键时报告
+    pub const fn status_interval(mut self, interval: Option<Duration>) -> Self {
+        self.config.status_interval = interval;
+        self
+    }
+
+    /// 检查参数并生成配置
+    pub fn build(mut self) -> Result<CrackConfig, CrackError> {
+        self.config.keyspace = KeyspaceSelection::from_options(self.skip, self.limit, self.part)?;
+        self.config.validate()?;
+        Ok(self.config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plan_conflicts_with_serve_and_partitioning() {
+        let plan = || CrackConfig::builder("data.zip").plan("quick");
+        assert!(matches!(
+            plan().serve("127.0.0.1:7700").build(),
+            Err(CrackError::ConflictingPlan)
+        ));
+        assert!(matches!(
+            plan().part(Part { index: 1, count: 2 }).build(),
+            Err(CrackError::ConflictingPlan)
+        ));
+        assert!(matches!(
+            plan().skip(10).build(),
+            Err(CrackError::ConflictingPlan)
+        ));
+        assert!(plan().build().is_ok());
+    }
+}
@@ -0,0 +1,6 @@
+pub mod coordinator;
+pub mod protocol;
+pub mod worker;
+
+pub use coordinator::{CoordinatorParams, CoordinatorResult, coordinate};
+pub use worker::{WorkerSummary, run_worker};
@@ -0,0 +1,576 @@
+use std::collections::{HashMap, VecDeque};
+use std::io;
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::protocol::{
+    Connection, Job, JobTarget, MAX_ARCHIVE_SIZE, MAX_EXPECTED_SIZE, Reply, Request, token_matches,
+};
+use crate::archive::{ArchiveHandler, TargetFile, Verifier};
+use crate::attack::Budget;
+use crate::attack::wordlist::{BATCH_SIZE, SeenFilter, WordlistReader};
+use crate::cli::Charset;
+use crate::observer::Monitor;
+
+/// 暂无可分配任务时让 worker 等待的时间
+const WAIT_DELAY: Duration = Duration::from_millis(500);
+
+/// 租约超时的下限：worker 测试中约每秒续租一次，超时过短会把在线 worker 的任务重新分配
+const MIN_LEASE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 结束后等待 worker 收到 `STOP` 的最长时间
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(5);
+
+/// 同时处理的连接数上限，超出时直接关闭新连接
+const MAX_CONNECTIONS: usize = 256;
+
+/// 连接后发送认证和 `HELLO` 的时限
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 协调进程参数
+pub struct CoordinatorParams<'a> {
+    /// worker 需要提供的令牌（`None` 时接受任何连接）
+    pub token: Option<&'a str>,
+    pub archive_path: &'a Path,
+    /// 字典（为空时只分发暴力破解任务）
+    pub dict_paths: &'a [PathBuf],
+    pub charsets: &'a [Charset],
+    pub min_len: u32,
+    pub max_len: u32,
+    /// 暴力破解部分的键空间大小
+    pub bruteforce_total: u128,
+    /// 验证条目，候选必须全部通过（同时下发给 worker）
+    pub targets: &'a [TargetFile],
+    pub handler: &'a dyn ArchiveHandler,
+    /// 每个任务块的候选数量
+    pub chunk_size: u64,
+    /// 租约超时：worker 超过该时间未汇报进度，任务重新分配（不少于 10 秒）
+    pub lease_timeout: Duration,
+    /// 时间和候选数量预算（候选数量按已完成的任务块计算）
+    pub budget: Budget,
+    /// 报告过程、取消和暂停（暂停时不再分配新任务）
+    pub monitor: &'a Monitor<'a>,
+}
+
+/// 协调进程结果
+pub struct CoordinatorResult {
+    /// 找到的密码及报告该密码的 worker
+    pub found: Option<(String, String)>,
+    /// 已完成的候选数量
+    pub total_tested: u64,
+    pub elapsed_secs: f64,
+    /// 是否因 Ctrl-C 中断
+    pub interrupted: bool,
+    /// 是否因预算用尽提前结束
+    pub budget_exhausted: bool,
+    /// 生成的任务块数量
+    pub chunks: u64,
+    /// 分配租约的次数（任务块被重新分配时多于任务块数量）
+    pub leases: u64,
+}
+
+/// 一个任务块
+#[derive(Clone)]
+enum Work {
+    /// 字典候选（已去重）
+    Words(Arc<Vec<String>>),
+    /// 暴力破解全局索引范围
+    Range(Range<u128>),
+}
+
+impl Work {
+    fn len(&self) -> u64 {
+        match self {
+            Self::Words(words) => words.len() as u64,
+            // 任务块大小不超过 chunk_size (u64)
+            Self::Range(range) => u64::try_from(range.end - range.start).unwrap_or(u64::MAX),
+        }
+    }
+}
+
+/// 已分配给 worker 的任务
+struct Lease {
+    work: Work,
+    worker: String,
+    deadline: Instant,
+}
+
+/// 按顺序读取各字典，生成去重后的候选块
+struct DictionaryFeed {
+    paths: Vec<PathBuf>,
+    next_path: usize,
+    reader: Option<WordlistReader>,
+    seen: SeenFilter,
+    batch: Vec<String>,
+}
+
+impl DictionaryFeed {
+    fn next_chunk(&mut self, size: usize, monitor: &Monitor) -> Option<Vec<String>> {
+        let mut words = Vec::new();
+        while words.len() < size {
+            let Some(reader) = self.reader.as_mut() else {
+                let Some(path) = self.paths.get(self.next_path).cloned() else {
+                    break;
+                };
+                self.next_path += 1;
+                match WordlistReader::open(&path) {
+                    Ok(reader) => self.reader = Some(reader),
+                    Err(e) => {
+                        monitor.warning(&format!("无法打开字典 {}，已跳过: {e}", path.display()));
+                    }
+                }
+                continue;
+            };
+            let want = (size - words.len()).min(BATCH_SIZE);
+            if !matches!(reader.next_batch(&mut self.batch, want), Ok(true)) {
+                self.reader = None;
+                continue;
+            }
+            let seen = &mut self.seen;
+            words.extend(self.batch.drain(..).filter(|word| seen.insert(word)));
+        }
+        (!words.is_empty()).then_some(words)
+    }
+}
+
+/// 任务队列：按顺序生成任务块、管理租约、回收失效 worker 的任务
+struct WorkQueue<'a> {
+    monitor: &'a Monitor<'a>,
+    dictionary: DictionaryFeed,
+    bruteforce: Range<u128>,
+    chunk_size: u64,
+    lease_timeout: Duration,
+    retry: VecDeque<Work>,
+    leases: HashMap<u64, Lease>,
+    next_id: u64,
+    chunks: u64,
+    tested: u64,
+    found: Option<(String, String)>,
+    finished: bool,
+}
+
+impl<'a> WorkQueue<'a> {
+    fn new(params: &CoordinatorParams<'a>, lease_timeout: Duration) -> Self {
+        Self {
+            monitor: params.monitor,
+            dictionary: DictionaryFeed {
+                paths: params.dict_paths.to_vec(),
+                next_path: 0,
+                reader: None,
+                seen: SeenFilter::default(),
+                batch: Vec::new(),
+            },
+            bruteforce: 0..params.bruteforce_total,
+            chunk_size: params.chunk_size.max(1),
+            lease_timeout,
+            retry: VecDeque::new(),
+            leases: HashMap::new(),
+            next_id: 1,
+            chunks: 0,
+            tested: 0,
+            found: None,
+            finished: false,
+        }
+    }
+
+    /// 分配下一个任务
+    fn next(&mut self, worker: &str) -> Reply {
+        if self.found.is_some() || self.finished {
+            return Reply::Stop;
+        }
+        self.reclaim_expired();
+        if self.monitor.paused() {
+            return Reply::Wait(WAIT_DELAY);
+        }
+
+        let work = self.retry.pop_front().or_else(|| {
+            let work = self.generate();
+            self.chunks += u64::from(work.is_some());
+            work
+        });
+        let Some(work) = work else {
+            if self.leases.is_empty() {
+                self.finished = true;
+                return Reply::Stop;
+            }
+            return Reply::Wait(WAIT_DELAY);
+        };
+
+        let id = self.next_id;
+        self.next_id += 1;
+        let reply = match &work {
+            Work::Words(words) => Reply::Words(id, words.to_vec()),
+            Work::Range(range) => Reply::Range(id, range.clone()),
+        };
+        self.leases.insert(
+            id,
+            Lease {
+                work,
+                worker: worker.to_string(),
+                deadline: Instant::now() + self.lease_timeout,
+            },
+        );
+        reply
+    }
+
+    /// 生成新任务：先字典后暴力破解
+    fn generate(&mut self) -> Option<Work> {
+        let size = usize::try_from(self.chunk_size).unwrap_or(usize::MAX);
+        if let Some(words) = self.dictionary.next_chunk(size, self.monitor) {
+            return Some(Work::Words(Arc::new(words)));
+        }
+        if self.bruteforce.is_empty() {
+            return None;
+        }
+        let start = self.bruteforce.start;
+        let end = start
+            .saturating_add(u128::from(self.chunk_size))
+            .min(self.bruteforce.end);
+        self.bruteforce.start = end;
+        Some(Work::Range(start..end))
+    }
+
+    /// 续租 `worker` 持有的租约，返回是否应继续
+    fn renew(&mut self, id: u64, worker: &str) -> bool {
+        if let Some(lease) = self.leases.get_mut(&id)
+            && lease.worker == worker
+        {
+            lease.deadline = Instant::now() + self.lease_timeout;
+        }
+        self.found.is_none() && !self.finished
+    }
+
+    /// 取出 `worker` 持有的租约（其他 worker 的租约保持不变）
+    fn take_lease(&mut self, id: u64, worker: &str) -> Option<Lease> {
+        if self.leases.get(&id)?.worker != worker {
+            self.monitor
+                .warning(&format!("{worker} 汇报了不属于它的任务 #{id}，已忽略"));
+            return None;
+        }
+        self.leases.remove(&id)
+    }
+
+    /// `worker` 完成了任务
+    fn complete(&mut self, id: u64, worker: &str) {
+        if let Some(lease) = self.take_lease(id, worker) {
+            self.tested += lease.work.len();
+            self.monitor.progress().add(lease.work.len());
+        }
+    }
+
+    /// 把超时租约的任务放回队列
+    fn reclaim_expired(&mut self) {
+        let now = Instant::now();
+        let expired: Vec<u64> = self
+            .leases
+            .iter()
+            .filter(|(_, lease)| lease.deadline <= now)
+            .map(|(&id, _)| id)
+            .collect();
+        for id in expired {
+            if let Some(lease) = self.leases.remove(&id) {
+                self.monitor
+                    .warning(&format!("{} 的任务 #{id} 租约超时，重新分配", lease.worker));
+                self.retry.push_back(lease.work);
+            }
+        }
+    }
+
+    /// worker 断开后回收它持有的任务
+    fn release_worker(&mut self, worker: &str) {
+        let ids: Vec<u64> = self
+            .leases
+            .iter()
+            .filter(|(_, lease)| lease.worker == worker)
+            .map(|(&id, _)| id)
+            .collect();
+        for id in ids {
+            if let Some(lease) = self.leases.remove(&id) {
+                self.monitor
+                    .message(&format!("{worker} 断开，任务 #{id} 重新分配"));
+                self.retry.push_back(lease.work);
+            }
+        }
+    }
+}
+
+/// 各连接线程共享的状态
+struct Shared<'a> {
+    monitor: &'a Monitor<'a>,
+    queue: Mutex<WorkQueue<'a>>,
+    job: Job,
+    token: Option<&'a str>,
+    archive_path: &'a Path,
+    targets: &'a [TargetFile],
+    handler: &'a dyn ArchiveHandler,
+    active: AtomicUsize,
+    lease_timeout: Duration,
+    /// 处理中的连接的副本，结束时用于强制关闭无响应的 worker
+    streams: Mutex<HashMap<SocketAddr, TcpStream>>,
+}
+
+/// 连接处理结束时从 [`Shared::streams`] 中移除该连接的副本
+struct Registration<'s> {
+    streams: &'s Mutex<HashMap<SocketAddr, TcpStream>>,
+    peer: SocketAddr,
+}
+
+impl Drop for Registration<'_> {
+    fn drop(&mut self) {
+        self.streams
+            .lock()
+            .expect("连接列表锁已损坏")
+            .remove(&self.peer);
+    }
+}
+
+/// 运行协调进程：在 `listener` 上接受 worker 连接并分发任务，直到找到密码、键空间耗尽或被中断
+pub fn coordinate(
+    listener: &TcpListener,
+    params: &CoordinatorParams,
+) -> io::Result<CoordinatorResult> {
+    let monitor = params.monitor;
+    let archive = std::fs::read(params.archive_path)?;
+    if archive.len() > MAX_ARCHIVE_SIZE {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("压缩包过大，分布式模式最多支持 {MAX_ARCHIVE_SIZE} 字节"),
+        ));
+    }
+    listener.set_nonblocking(true)?;
+    if params.token.is_none() && !listener.local_addr()?.ip().is_loopback() {
+        monitor
+            .warning("未设置令牌：任何能连接该端口的人都能下载压缩包并领取任务，建议使用 --token");
+    }
+    // 租约和连接的读取超时使用同一个值，避免在线 worker 的任务被重新分配
+    let lease_timeout = params.lease_timeout.max(MIN_LEASE_TIMEOUT);
+    monitor.message(&format!(
+        "协调进程监听 {}\n任务块大小: {} 个候选, 租约超时: {} 秒\n等待 worker 连接 (archive_cracker --worker <地址>) ...\n",
+        listener.local_addr()?,
+        params.chunk_size,
+        lease_timeout.as_secs()
+    ));
+
+    let shared = Shared {
+        monitor,
+        queue: Mutex::new(WorkQueue::new(params, lease_timeout)),
+        job: Job {
+            file_name: archive_file_name(params.archive_path),
+            archive,
+            charsets: params.charsets.to_vec(),
+            min_len: params.min_len,
+            max_len: params.max_len,
+            targets: params.targets.iter().map(job_target).collect(),
+        },
+        token: params.token,
+        archive_path: params.archive_path,
+        targets: params.targets,
+        handler: params.handler,
+        active: AtomicUsize::new(0),
+        lease_timeout,
+        streams: Mutex::new(HashMap::new()),
+    };
+
+    let start = Instant::now();
+    let interrupted = thread::scope(|scope| -> io::Result<bool> {
+        loop {
+            match listener.accept() {
+                Ok((stream, peer)) if shared.active.load(Ordering::Relaxed) >= MAX_CONNECTIONS => {
+                    monitor.warning(&format!(
+                        "连接数已达上限 {MAX_CONNECTIONS}，拒绝 {peer} 的连接"
+                    ));
+                    let _ = stream.shutdown(Shutdown::Both);
+                }
+                Ok((stream, peer)) => {
+                    shared.active.fetch_add(1, Ordering::Relaxed);
+                    let shared = &shared;
+                    scope.spawn(move || {
+                        if let Err(e) = serve_worker(shared, stream, peer) {
+                            monitor.warning(&format!("与 {peer} 的连接出错: {e}"));
+                        }
+                        shared.active.fetch_sub(1, Ordering::Relaxed);
+                    });
+                }
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                    thread::sleep(Duration::from_millis(100));
+                }
+                Err(e) => return Err(e),
+            }
+
+            let (stop, tested, leases) = {
+                let queue = shared.queue.lock().expect("任务队列锁已损坏");
+                (
+                    queue.found.is_some() || queue.finished,
+                    queue.tested,
+                    queue.leases.len(),
+                )
+            };
+            if stop || monitor.cancelled() || params.budget.exhausted(tested) {
+                break;
+            }
+            monitor.progress().set_detail(format!(
+                "在线 worker {} 个, 进行中的任务 {leases} 个",
+                shared.active.load(Ordering::Relaxed),
+            ));
+        }
+
+        shut_down(&shared);
+        Ok(monitor.cancelled())
+    })?;
+
+    let queue = shared.queue.into_inner().expect("任务队列锁已损坏");
+    Ok(CoordinatorResult {
+        interrupted: interrupted && queue.found.is_none(),
+        budget_exhausted: !interrupted
+            && queue.found.is_none()
+            && params.budget.exhausted(queue.tested),
+        found: queue.found,
+        total_tested: queue.tested,
+        chunks: queue.chunks,
+        leases: queue.next_id - 1,
+        elapsed_secs: start.elapsed().as_secs_f64(),
+    })
+}
+
+/// 通知在线 worker 停止（它们在下次心跳或请求任务时收到 `STOP`），超时后强制关闭剩余连接
+fn shut_down(shared: &Shared) {
+    shared.queue.lock().expect("任务队列锁已损坏").finished = true;
+    let deadline = Instant::now() + SHUTDOWN_GRACE;
+    while shared.active.load(Ordering::Relaxed) > 0 && Instant::now() < deadline {
+        thread::sleep(Duration::from_millis(100));
+    }
+    for stream in shared.streams.lock().expect("连接列表锁已损坏").values() {
+        let _ = stream.shutdown(Shutdown::Both);
+    }
+}
+
+/// 处理一个 worker 连接
+fn serve_worker(shared: &Shared, stream: TcpStream, peer: SocketAddr) -> io::Result<()> {
+    stream.set_nonblocking(false)?;
+    stream.set_read_timeout(Some(HANDSHAKE_TIMEOUT))?;
+    let mut conn = Connection::new(stream.try_clone()?)?;
+    shared
+        .streams
+        .lock()
+        .expect("连接列表锁已损坏")
+        .insert(peer, stream);
+    let _registration = Registration {
+        streams: &shared.streams,
+        peer,
+    };
+
+    let mut request = conn.read_request()?;
+    let authorized = match (&request, shared.token) {
+        (Some(Request::Auth(given)), Some(token)) => token_matches(token, given),
+        (_, token) => token.is_none(),
+    };
+    if !authorized {
+        shared
+            .monitor
+            .warning(&format!("{peer} 未提供正确的令牌，已拒绝连接"));
+        return conn.send_reply(&Reply::Stop);
+    }
+    if matches!(request, Some(Request::Auth(_))) {
+        conn.send_reply(&Reply::Ok)?;
+        request = conn.read_request()?;
+    }
+
+    let worker = match request {
+        Some(Request::Hello(name)) => format!("{name}@{peer}"),
+        _ => return Ok(()),
+    };
+    shared.monitor.message(&format!("worker 已连接: {worker}"));
+    // worker 在租约期内必然会发送心跳或请求，超时未收到消息视为失联
+    conn.set_read_timeout(shared.lease_timeout)?;
+    conn.send_reply(&Reply::Job(shared.job.clone()))?;
+
+    let result = worker_loop(shared, &mut conn, &worker);
+    shared
+        .queue
+        .lock()
+        .expect("任务队列锁已损坏")
+        .release_worker(&worker);
+    shared.monitor.message(&format!("worker 已断开: {worker}"));
+    result
+}
+
+fn worker_loop(shared: &Shared, conn: &mut Connection, worker: &str) -> io::Result<()> {
+    while let Some(request) = conn.read_request()? {
+        let reply = match request {
+            Request::Auth(_) | Request::Hello(_) => Reply::Ok,
+            Request::Next => shared.queue.lock().expect("任务队列锁已损坏").next(worker),
+            Request::Progress(id) => {
+                let keep_going = shared
+                    .queue
+                    .lock()
+                    .expect("任务队列锁已损坏")
+                    .renew(id, worker);
+                if keep_going { Reply::Ok } else { Reply::Stop }
+            }
+            Request::Done(id) => {
+                let mut queue = shared.queue.lock().expect("任务队列锁已损坏");
+                queue.complete(id, worker);
+                queue.next(worker)
+            }
+            Request::Found(id, password) => report_found(shared, worker, id, password),
+        };
+        let stop = matches!(reply, Reply::Stop);
+        conn.send_reply(&reply)?;
+        if stop {
+            break;
+        }
+    }
+    Ok(())
+}
+
+/// 验证 worker 报告的密码，验证失败时把任务放回队列
+fn report_found(shared: &Shared, worker: &str, id: u64, password: String) -> Reply {
+    let valid = shared
+        .handler
+        .try_targets(shared.archive_path, &password, shared.targets)
+        .is_verified();
+
+    let mut queue = shared.queue.lock().expect("任务队列锁已损坏");
+    if !valid {
+        shared
+            .monitor
+            .warning(&format!("{worker} 报告的密码验证失败，任务 #{id} 重新分配"));
+        if let Some(lease) = queue.take_lease(id, worker) {
+            queue.retry.push_back(lease.work);
+        }
+        return queue.next(worker);
+    }
+
+    shared.monitor.message(&format!("✅ {worker} 找到密码"));
+    queue.complete(id, worker);
+    queue
+        .found
+        .get_or_insert_with(|| (password, worker.to_string()));
+    Reply::Stop
+}
+
+/// 下发给 worker 的验证条目（自定义验证函数无法下发，worker 改用自动选择的验证方式）
+fn job_target(target: &TargetFile) -> JobTarget {
+    JobTarget {
+        index: target.index,
+        expected: match &target.verifier {
+            Some(Verifier::Prefix(bytes)) => {
+                Some(bytes[..bytes.len().min(MAX_EXPECTED_SIZE)].to_vec())
+            }
+            _ => None,
+        },
+    }
+}
+
+/// 下发给 worker 的文件名（worker 按扩展名识别格式）
+fn archive_file_name(archive_path: &Path) -> String {
+    archive_path.file_name().map_or_else(
+        || "archive".to_string(),
+        |name| name.to_string_lossy().to_string(),
+    )
+}
@@ -0,0 +1,307 @@
+//! 协调进程与 worker 之间的文本协议
+//!
+//! 每条消息占一行（UTF-8），部分消息后跟附加数据：
+//!
+//! | 方向 | 消息 | 说明 |
+//! |------|------|------|
+//! | worker → 协调 | `AUTH <令牌>` | 协调进程要求令牌时，连接后第一条消息；回复 `OK` 或 `STOP` |
+//! | worker → 协调 | `HELLO <名称>` | 认证后（或不需要令牌时连接后）的第一条消息 |
+//! | 协调 → worker | `JOB <字节数> <最小长度> <最大长度> <字符集> <文件名>` | 后跟压缩包原始字节和 `TARGETS` 行 |
+//! | 协调 → worker | `TARGETS <索引>[:<十六进制>] ...` | 验证条目及其已知开头内容，为空时由 worker 自动选择 |
+//! | worker → 协调 | `NEXT` | 请求任务 |
+//! | 协调 → worker | `WORDS <租约> <条数>` | 后跟若干行候选密码 |
+//! | 协调 → worker | `RANGE <租约> <起始> <结束>` | 暴力破解全局索引 `[起始, 结束)` |
+//! | 协调 → worker | `WAIT <毫秒>` | 暂无可分配的任务，稍后再请求 |
+//! | worker → 协调 | `PROGRESS <租约>` | 心跳，续租；回复 `OK` 或 `STOP` |
+//! | worker → 协调 | `DONE <租约>` | 任务完成，回复下一个任务 |
+//! | worker → 协调 | `FOUND <租约> <密码>` | 找到密码，回复 `STOP` |
+//! | 协调 → worker | `OK` / `STOP` | 继续 / 停止 |
+
+use std::io::{self, BufRead, BufReader, BufWriter, Read, Write};
+use std::net::TcpStream;
+use std::ops::Range;
+use std::str::FromStr;
+use std::time::Duration;
+
+use clap::ValueEnum;
+
+use crate::archive::common::{from_hex, to_hex};
+use crate::attack::wordlist::{decode_word, encode_word};
+use crate::cli::Charset;
+
+/// 单个任务块最多包含的候选数量（防止异常消息耗尽内存）
+const MAX_WORDS: usize = 16 * 1024 * 1024;
+
+/// 任务中压缩包的最大字节数（防止异常消息按声明长度分配过多内存）
+pub const MAX_ARCHIVE_SIZE: usize = 1024 * 1024 * 1024;
+
+/// 单行消息的最大字节数（认证前同样生效，防止不带换行的数据耗尽内存）
+const MAX_LINE: usize = 64 * 1024;
+
+/// 下发的已知开头内容的最大字节数（只用于快速排除，确认仍靠完整的 CRC 校验）
+pub const MAX_EXPECTED_SIZE: usize = 1024 * 1024;
+
+/// `TARGETS` 行的最大字节数（已知内容按十六进制下发）
+const MAX_TARGETS_LINE: usize = MAX_LINE + 2 * MAX_EXPECTED_SIZE;
+
+/// worker 发给协调进程的消息
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Request {
+    Auth(String),
+    Hello(String),
+    Next,
+    Progress(u64),
+    Done(u64),
+    Found(u64, String),
+}
+
+/// 协调进程发给 worker 的消息
+#[derive(Debug, Clone)]
+pub enum Reply {
+    Job(Job),
+    Words(u64, Vec<String>),
+    Range(u64, Range<u128>),
+    Wait(Duration),
+    Ok,
+    Stop,
+}
+
+/// 破解任务描述：压缩包内容和暴力破解参数
+#[derive(Debug, Clone)]
+pub struct Job {
+    pub file_name: String,
+    pub archive: Vec<u8>,
+    pub charsets: Vec<Charset>,
+    pub min_len: u32,
+    pub max_len: u32,
+    /// 验证条目，为空时由 worker 自动选择
+    pub targets: Vec<JobTarget>,
+}
+
+/// 下发给 worker 的验证条目
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JobTarget {
+    pub index: usize,
+    /// 已知的开头内容（自定义验证函数无法下发，此时为 `None`）
+    pub expected: Option<Vec<u8>>,
+}
+
+/// 一条 TCP 连接
+pub struct Connection {
+    reader: BufReader<TcpStream>,
+    writer: BufWriter<TcpStream>,
+    line: String,
+}
+
+impl Connection {
+    pub fn new(stream: TcpStream) -> io::Result<Self> {
+        stream.set_nodelay(true)?;
+        Ok(Self {
+            reader: BufReader::new(stream.try_clone()?),
+            writer: BufWriter::new(stream),
+            line: String::new(),
+        })
+    }
+
+    /// 读取超时（超时后读取返回错误）
+    pub fn set_read_timeout(&self, timeout: Duration) -> io::Result<()> {
+        self.writer.get_ref().set_read_timeout(Some(timeout))
+    }
+
+    /// 发送请求
+    pub fn send_request(&mut self, request: &Request) -> io::Result<()> {
+        match request {
+            Request::Auth(token) => writeln!(self.writer, "AUTH {}", encode_word(token))?,
+            Request::Hello(name) => writeln!(self.writer, "HELLO {name}")?,
+            Request::Next => writeln!(self.writer, "NEXT")?,
+            Request::Progress(id) => writeln!(self.writer, "PROGRESS {id}")?,
+            Request::Done(id) => writeln!(self.writer, "DONE {id}")?,
+            Request::Found(id, password) => {
+                writeln!(self.writer, "FOUND {id} {}", encode_word(password))?;
+            }
+        }
+        self.writer.flush()
+    }
+
+    /// 读取请求，对方关闭连接时返回 `None`
+    pub fn read_request(&mut self) -> io::Result<Option<Request>> {
+        let Some(line) = self.read_line()? else {
+            return Ok(None);
+        };
+        let (command, rest) = line.split_once(' ').unwrap_or((line.as_str(), ""));
+        let request = match command {
+            "AUTH" => Request::Auth(decode_word(rest).into_owned()),
+            "HELLO" => Request::Hello(rest.to_string()),
+            "NEXT" => Request::Next,
+            "PROGRESS" => Request::Progress(parse_field(rest)?),
+            "DONE" => Request::Done(parse_field(rest)?),
+            "FOUND" => {
+                let (id, password) = rest.split_once(' ').ok_or_else(|| protocol_error(&line))?;
+                Request::Found(parse_field(id)?, decode_word(password).into_owned())
+            }
+            _ => return Err(protocol_error(&line)),
+        };
+        Ok(Some(request))
+    }
+
+    /// 发送回复
+    pub fn send_reply(&mut self, reply: &Reply) -> io::Result<()> {
+        match reply {
+            Reply::Job(job) => {
+                let charsets: Vec<_> = job
+                    .charsets
+                    .iter()
+                    .filter_map(|c| c.to_possible_value().map(|v| v.get_name().to_string()))
+                    .collect();
+                writeln!(
+                    self.writer,
+                    "JOB {} {} {} {} {}",
+                    job.archive.len(),
+                    job.min_len,
+                    job.max_len,
+                    charsets.join(","),
+                    job.file_name
+                )?;
+                self.writer.write_all(&job.archive)?;
+                write!(self.writer, "TARGETS")?;
+                for target in &job.targets {
+                    write!(self.writer, " {}", target.index)?;
+                    if let Some(bytes) = &target.expected {
+                        write!(self.writer, ":{}", to_hex(bytes))?;
+                    }
+                }
+                writeln!(self.writer)?;
+            }
+            Reply::Words(id, words) => {
+                writeln!(self.writer, "WORDS {id} {}", words.len())?;
+                // 每行一个候选，含换行符的候选写成 `$HEX[...]`
+                for word in words {
+                    writeln!(self.writer, "{}", encode_word(word))?;
+                }
+            }
+            Reply::Range(id, range) => {
+                writeln!(self.writer, "RANGE {id} {} {}", range.start, range.end)?;
+            }
+            Reply::Wait(delay) => writeln!(self.writer, "WAIT {}", delay.as_millis())?,
+            Reply::Ok => writeln!(self.writer, "OK")?,
+            Reply::Stop => writeln!(self.writer, "STOP")?,
+        }
+        self.writer.flush()
+    }
+
+    /// 读取回复，对方关闭连接视为 `STOP`
+    pub fn read_reply(&mut self) -> io::Result<Reply> {
+        let Some(line) = self.read_line()? else {
+            return Ok(Reply::Stop);
+        };
+        let fields: Vec<&str> = line.split(' ').collect();
+        let reply = match fields.as_slice() {
+            ["JOB", len, min_len, max_len, charsets, ..] => {
+                let file_name = fields[5..].join(" ");
+                let len: usize = parse_field(len)?;
+                if len > MAX_ARCHIVE_SIZE {
+                    return Err(protocol_error(&line));
+                }
+                let mut archive = vec![0; len];
+                self.reader.read_exact(&mut archive)?;
+                let targets = self
+                    .read_limited_line(MAX_TARGETS_LINE)?
+                    .ok_or_else(|| protocol_error(&line))?;
+                Reply::Job(Job {
+                    file_name,
+                    archive,
+                    charsets: charsets
+                        .split(',')
+                        .map(|c| Charset::from_str(c, true).map_err(|_| protocol_error(&line)))
+                        .collect::<io::Result<_>>()?,
+                    min_len: parse_field(min_len)?,
+                    max_len: parse_field(max_len)?,
+                    targets: parse_targets(&targets)?,
+                })
+            }
+            ["WORDS", id, count] => {
+                let id = parse_field(id)?;
+                let count: usize = parse_field(count)?;
+                if count > MAX_WORDS {
+                    return Err(protocol_error(&line));
+                }
+                let mut words = Vec::with_capacity(count);
+                for _ in 0..count {
+                    let word = self.read_line()?.ok_or_else(|| protocol_error(&line))?;
+                    words.push(decode_word(&word).into_owned());
+                }
+                Reply::Words(id, words)
+            }
+            ["RANGE", id, start, end] => {
+                Reply::Range(parse_field(id)?, parse_field(start)?..parse_field(end)?)
+            }
+            ["WAIT", millis] => Reply::Wait(Duration::from_millis(parse_field(millis)?)),
+            ["OK"] => Reply::Ok,
+            ["STOP"] => Reply::Stop,
+            _ => return Err(protocol_error(&line)),
+        };
+        Ok(reply)
+    }
+
+    /// 读取一行（去除行尾换行），对方关闭连接时返回 `None`
+    fn read_line(&mut self) -> io::Result<Option<String>> {
+        self.read_limited_line(MAX_LINE)
+    }
+
+    /// 读取不超过 `limit` 字节的一行，更长的行视为协议错误
+    fn read_limited_line(&mut self, limit: usize) -> io::Result<Option<String>> {
+        self.line.clear();
+        let read = (&mut self.reader)
+            .take(limit as u64 + 1)
+            .read_line(&mut self.line)?;
+        if read == 0 {
+            return Ok(None);
+        }
+        if read > limit && !self.line.ends_with('\n') {
+            return Err(protocol_error(&format!("消息超过 {limit} 字节")));
+        }
+        let line = self.line.strip_suffix('\n').unwrap_or(&self.line);
+        Ok(Some(line.strip_suffix('\r').unwrap_or(line).to_string()))
+    }
+}
+
+/// 解析 `TARGETS` 行
+fn parse_targets(line: &str) -> io::Result<Vec<JobTarget>> {
+    let mut fields = line.split_whitespace();
+    if fields.next() != Some("TARGETS") {
+        return Err(protocol_error(line));
+    }
+    fields
+        .map(|field| {
+            let (index, hex) = field
+                .split_once(':')
+                .map_or((field, None), |(index, hex)| (index, Some(hex)));
+            Ok(JobTarget {
+                index: parse_field(index)?,
+                expected: hex
+                    .map(|hex| from_hex(hex).ok_or_else(|| protocol_error(line)))
+                    .transpose()?,
+            })
+        })
+        .collect()
+}
+
+/// 比较令牌，耗时与令牌内容无关（只取决于长度）
+#[must_use]
+pub fn token_matches(expected: &str, given: &str) -> bool {
+    let (expected, given) = (expected.as_bytes(), given.as_bytes());
+    expected.len() == given.len()
+        && expected
+            .iter()
+            .zip(given)
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+fn parse_field<T: FromStr>(value: &str) -> io::Result<T> {
+    value.parse().map_err(|_| protocol_error(value))
+}
+
+fn protocol_error(line: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, format!("协议错误: {line}"))
+}
@@ -0,0 +1,347 @@
+use rayon::prelude::*;
+use std::collections::hash_map::RandomState;
+use std::fs;
+use std::hash::{BuildHasher, Hasher};
+use std::io;
+use std::net::TcpStream;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use super::protocol::{Connection, Job, Reply, Request};
+use crate::archive::{ArchiveFormat, ArchiveHandler, TargetFile, Verifier, get_handler};
+use crate::charset::get_combined_charset;
+use crate::keyspace::bruteforce_candidate;
+use crate::observer::Monitor;
+use crate::session::ChunkSizer;
+
+/// 心跳间隔：测试任务块期间至少每隔这么久向协调进程汇报一次
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// worker 运行结果
+pub struct WorkerSummary {
+    /// 本 worker 找到的密码
+    pub password: Option<String>,
+    /// 本 worker 测试的候选数量
+    pub total_tested: u64,
+    pub elapsed_secs: f64,
+    /// 完成的任务块数量
+    pub chunks_done: u64,
+}
+
+/// 本地解码后的任务
+struct LocalJob {
+    /// 存放压缩包的临时目录，任务结束时删除
+    _dir: TempDir,
+    archive_path: PathBuf,
+    handler: Box<dyn ArchiveHandler>,
+    targets: Vec<TargetFile>,
+    chars: Vec<char>,
+    min_len: u32,
+    max_len: u32,
+}
+
+/// 单个任务块的结果
+enum ChunkOutcome {
+    Found(String),
+    Done,
+    Stop,
+}
+
+/// 运行 worker：连接协调进程（提供 `token` 时先认证），领取并测试任务块，直到收到 `STOP` 或被取消
+///
+/// 暂停超过租约超时时，协调进程会把当前任务重新分配给其他 worker
+pub fn run_worker(addr: &str, token: Option<&str>, monitor: &Monitor) -> io::Result<WorkerSummary> {
+    let stream = TcpStream::connect(addr)?;
+    let mut conn = Connection::new(stream)?;
+    monitor.message(&format!("已连接协调进程 {addr}"));
+
+    if let Some(token) = token {
+        conn.send_request(&Request::Auth(token.to_string()))?;
+        if !matches!(conn.read_reply()?, Reply::Ok) {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                "协调进程拒绝了令牌",
+            ));
+        }
+    }
+    conn.send_request(&Request::Hello(worker_name()))?;
+    let Reply::Job(job) = conn.read_reply()? else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "协调进程未下发任务（协调进程要求令牌时需使用 --token）",
+        ));
+    };
+    let local = prepare_job(&job)?;
+    monitor.message(&format!(
+        "收到压缩包: {} ({} 格式, 目标文件 {})\nCPU 核心数: {}\n",
+        local.archive_path.display(),
+        local.handler.format_name(),
+        local
+            .targets
+            .iter()
+            .map(|target| target.name.as_str())
+            .collect::<Vec<_>>()
+            .join(", "),
+        num_cpus::get()
+    ));
+
+    let start = Instant::now();
+    let mut summary = WorkerSummary {
+        password: None,
+        total_tested: 0,
+        elapsed_secs: 0.0,
+        chunks_done: 0,
+    };
+    let result = work_loop(&mut conn, &local, &mut summary, monitor);
+    summary.elapsed_secs = start.elapsed().as_secs_f64();
+    result.map(|()| summary)
+}
+
+fn work_loop(
+    conn: &mut Connection,
+    job: &LocalJob,
+    summary: &mut WorkerSummary,
+    monitor: &Monitor,
+) -> io::Result<()> {
+    let mut reply = {
+        conn.send_request(&Request::Next)?;
+        conn.read_reply()?
+    };
+
+    loop {
+        let (id, len, outcome) = match reply {
+            Reply::Words(id, words) => {
+                let len = words.len() as u64;
+                let outcome = test_chunk(conn, job, monitor, id, len, |i| {
+                    words.get(usize::try_from(i).ok()?).cloned()
+                })?;
+                (id, len, outcome)
+            }
+            Reply::Range(id, range) => {
+                let len = range
+                    .end
+                    .checked_sub(range.start)
+                    .and_then(|len| u64::try_from(len).ok())
+                    .ok_or_else(|| invalid_chunk("任务块范围无效"))?;
+                let outcome = test_chunk(conn, job, monitor, id, len, |i| {
+                    bruteforce_candidate(
+                        range.start + u128::from(i),
+                        &job.chars,
+                        job.min_len,
+                        job.max_len,
+                    )
+                })?;
+                (id, len, outcome)
+            }
+            Reply::Wait(delay) => {
+                std::thread::sleep(delay);
+                conn.send_request(&Request::Next)?;
+                reply = conn.read_reply()?;
+                continue;
+            }
+            Reply::Job(_) | Reply::Ok => {
+                conn.send_request(&Request::Next)?;
+                reply = conn.read_reply()?;
+                continue;
+            }
+            Reply::Stop => {
+                monitor.message("协调进程通知停止");
+                return Ok(());
+            }
+        };
+
+        match outcome {
+            ChunkOutcome::Found(password) => {
+                monitor.message("✅ 找到密码，已报告协调进程");
+                conn.send_request(&Request::Found(id, password.clone()))?;
+                summary.password = Some(password);
+                let _ = conn.read_reply();
+                return Ok(());
+            }
+            ChunkOutcome::Stop => {
+                monitor.message("协调进程通知停止");
+                return Ok(());
+            }
+            ChunkOutcome::Done => {}
+        }
+        // 本机无法测试（如临时文件被删除），未完成的任务由协调进程重新分配
+        if let Some(e) = monitor.fault() {
+            return Err(io::Error::other(e));
+        }
+        if monitor.checkpoint() {
+            monitor.message("已中断，未完成的任务将由协调进程重新分配");
+            return Ok(());
+        }
+
+        summary.chunks_done += 1;
+        summary.total_tested += len;
+        conn.send_request(&Request::Done(id))?;
+        reply = conn.read_reply()?;
+    }
+}
+
+/// 分块并行测试一个任务块，期间定期发送心跳，收到 `STOP` 时提前结束
+///
+/// `candidate` 对超出键空间的索引返回 `None`，此时任务块无效，返回 `InvalidData`
+fn test_chunk(
+    conn: &mut Connection,
+    job: &LocalJob,
+    monitor: &Monitor,
+    id: u64,
+    len: u64,
+    candidate: impl Fn(u64) -> Option<String> + Sync,
+) -> io::Result<ChunkOutcome> {
+    let found = AtomicBool::new(false);
+    let mut chunks = ChunkSizer::new();
+    let mut last_heartbeat = Instant::now();
+    let mut start = 0;
+
+    while start < len {
+        let end = start.saturating_add(chunks.size() as u64).min(len);
+        let timer = Instant::now();
+        let hit = (start..end).into_par_iter().find_map_any(|i| {
+            if found.load(Ordering::Relaxed) || monitor.cancelled() {
+                return None;
+            }
+            let Some(password) = candidate(i) else {
+                found.store(true, Ordering::Relaxed);
+                return Some(None);
+            };
+            monitor.progress().record();
+            let outcome = job
+                .handler
+                .try_targets(&job.archive_path, &password, &job.targets);
+            if monitor.record(&password, &outcome) {
+                found.store(true, Ordering::Relaxed);
+                return Some(Some(password));
+            }
+            None
+        });
+        match hit {
+            Some(Some(password)) => return Ok(ChunkOutcome::Found(password)),
+            Some(None) => return Err(invalid_chunk("任务块索引超出键空间")),
+            None => {}
+        }
+        if monitor.cancelled() {
+            return Ok(ChunkOutcome::Done);
+        }
+        chunks.adjust(timer.elapsed());
+        start = end;
+
+        if last_heartbeat.elapsed() >= HEARTBEAT_INTERVAL {
+            conn.send_request(&Request::Progress(id))?;
+            if matches!(conn.read_reply()?, Reply::Stop) {
+                return Ok(ChunkOutcome::Stop);
+            }
+            last_heartbeat = Instant::now();
+        }
+    }
+    Ok(ChunkOutcome::Done)
+}
+
+fn invalid_chunk(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// 系统临时目录下随机命名的目录，离开作用域时连同内容一起删除
+struct TempDir(PathBuf);
+
+impl TempDir {
+    /// 新建目录；名称已存在时换一个名称重试，不复用他人预先创建的目录或符号链接
+    fn create() -> io::Result<Self> {
+        for _ in 0..16 {
+            let suffix = RandomState::new().build_hasher().finish();
+            let path = std::env::temp_dir().join(format!(
+                "archive_cracker_worker_{}_{suffix:016x}",
+                std::process::id()
+            ));
+            let mut builder = fs::DirBuilder::new();
+            #[cfg(unix)]
+            std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+            match builder.create(&path) {
+                Ok(()) => return Ok(Self(path)),
+                Err(e) if e.kind() == io::ErrorKind::AlreadyExists => {}
+                Err(e) => return Err(e),
+            }
+        }
+        Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            "无法创建 worker 临时目录",
+        ))
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// 把收到的压缩包写入临时目录并准备处理器
+fn prepare_job(job: &Job) -> io::Result<LocalJob> {
+    let dir = TempDir::create()?;
+    // 文件名来自网络，只取最后一段，避免写到临时目录之外
+    let file_name = Path::new(&job.file_name)
+        .file_name()
+        .map_or_else(|| "archive".into(), std::ffi::OsStr::to_os_string);
+    let archive_path = dir.0.join(file_name);
+    fs::write(&archive_path, &job.archive)?;
+
+    let unsupported = || io::Error::new(io::ErrorKind::InvalidData, "不支持的压缩包格式");
+    let format = ArchiveFormat::detect(&archive_path).ok_or_else(unsupported)?;
+    let handler = get_handler(format);
+    let targets = job_targets(handler.as_ref(), &archive_path, job)?;
+    let (_, chars) = get_combined_charset(&job.charsets);
+
+    Ok(LocalJob {
+        _dir: dir,
+        archive_path,
+        handler,
+        targets,
+        chars,
+        min_len: job.min_len,
+        max_len: job.max_len,
+    })
+}
+
+/// 按协调进程下发的索引准备验证条目，未指定时自动选择
+fn job_targets(
+    handler: &dyn ArchiveHandler,
+    path: &Path,
+    job: &Job,
+) -> io::Result<Vec<TargetFile>> {
+    let invalid = |message: String| io::Error::new(io::ErrorKind::InvalidData, message);
+    if job.targets.is_empty() {
+        let target = handler
+            .detect_target(path)
+            .map_err(|e| invalid(e.to_string()))?
+            .ok_or_else(|| invalid("压缩包中没有可用于验证密码的加密文件".to_string()))?;
+        return Ok(vec![target]);
+    }
+    let entries = handler.entries(path).map_err(|e| invalid(e.to_string()))?;
+    job.targets
+        .iter()
+        .map(|job_target| {
+            let entry = entries
+                .iter()
+                .find(|entry| entry.index == job_target.index)
+                .ok_or_else(|| {
+                    invalid(format!("压缩包中没有索引为 {} 的条目", job_target.index))
+                })?;
+            let target = TargetFile::new(entry.index, entry.name.clone());
+            Ok(match &job_target.expected {
+                Some(bytes) => target.with_verifier(Verifier::Prefix(bytes.clone())),
+                None => target,
+            })
+        })
+        .collect()
+}
+
+/// worker 名称：主机名（取不到时用进程号）
+fn worker_name() -> String {
+    std::env::var("HOSTNAME")
+        .ok()
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| format!("worker-{}", std::process::id()))
+}
@@ -0,0 +1,445 @@
+//! 运行前估算：按实际运行的阶段顺序列出各阶段的键空间和预计耗时（`--dry-run`）
+//!
+//! 键空间按实际运行时的来源统计：potfile 和元数据候选词按收集到的数量，字典按条目数（与字典攻击的
+//! 计数方式一致，条目不做规则变换，每个条目一个候选），暴力破解按字符集大小和长度范围，使用
+//! `--skip`/`--limit`/`--part` 时只计本分片。速度优先使用基准测试（`--bench`）中验证条目的加密方式
+//! 和验证方式对应的结果，没有时在验证条目上实测。阶段和整个运行的预算依次限制各阶段的候选数量和
+//! 耗时；设置了时限时标出预计耗时超过时限的阶段，正式运行时拒绝开始
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use crate::archive::{ArchiveFormat, ArchiveHandler, TargetFile, get_handler};
+use crate::attack::{Budget, ensure_dictionary_exists, get_default_dictionary_path};
+use crate::bench::{self, BenchResults, Cipher};
+use crate::charset::get_combined_charset;
+use crate::cli::Charset;
+use crate::harvest::{harvest_candidates, harvest_neighbors};
+use crate::keyspace::{bruteforce_keyspace, count_dictionary_entries};
+use crate::observer::Monitor;
+use crate::plan::{Plan, StageAttack};
+use crate::potfile::{self, get_default_potfile_path};
+use crate::progress::format_eta;
+use crate::{
+    CrackConfig, CrackError, harvest_stage_name, load_plan, run_dictionaries, select_targets,
+    stage_dictionaries,
+};
+
+/// 没有基准测试结果时实测速度的时长
+pub const MEASURE_DURATION: Duration = Duration::from_secs(1);
+
+/// 速度的来源
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpeedSource {
+    /// `--bench` 保存的结果
+    Bench,
+    /// 在验证条目上实测
+    Measured,
+}
+
+impl SpeedSource {
+    /// 显示名称
+    #[must_use]
+    pub const fn label(self) -> &'static str {
+        match self {
+            Self::Bench => "基准测试",
+            Self::Measured => "实测",
+        }
+    }
+}
+
+/// 一个阶段的估算
+#[derive(Debug, Clone)]
+pub struct StageEstimate {
+    pub name: String,
+    /// 键空间的构成，如字典条目数或字符集大小和长度范围
+    pub detail: String,
+    /// 键空间大小
+    pub keyspace: u128,
+    /// 预算内会测试的候选数量
+    pub candidates: u128,
+    /// 预计耗时（秒），速度未知时为 `None`
+    pub eta_secs: Option<f64>,
+}
+
+impl StageEstimate {
+    /// 预算在测试完整个键空间之前用尽
+    #[must_use]
+    pub const fn limited(&self) -> bool {
+        self.candidates < self.keyspace
+    }
+}
+
+/// 整个运行的估算
+#[derive(Debug, Clone)]
+pub struct RunEstimate {
+    /// 验证条目（有多个时为最先验证的条目）
+    pub target: TargetFile,
+    /// 验证条目的加密方式对应的基准测试样本
+    pub cipher: Option<Cipher>,
+    /// 全部核心每秒测试的候选数量及其来源，无法测量时为 `None`
+    pub speed: Option<(f64, SpeedSource)>,
+    /// 按执行顺序排列的阶段
+    pub stages: Vec<StageEstimate>,
+    /// 单个阶段的预计耗时上限
+    pub horizon: Option<Duration>,
+}
+
+impl RunEstimate {
+    /// 所有阶段在预算内会测试的候选数量
+    #[must_use]
+    pub fn total_candidates(&self) -> u128 {
+        self.stages
+            .iter()
+            .fold(0, |total, stage| total.saturating_add(stage.candidates))
+    }
+
+    /// 所有阶段的预计耗时（秒）
+    #[must_use]
+    pub fn total_eta_secs(&self) -> Option<f64> {
+        self.stages.iter().map(|stage| stage.eta_secs).sum()
+    }
+
+    /// 该阶段的预计耗时是否超过时限
+    #[must_use]
+    pub fn exceeds_horizon(&self, stage: &StageEstimate) -> bool {
+        self.horizon
+            .zip(stage.eta_secs)
+            .is_some_and(|(horizon, eta)| eta > horizon.as_secs_f64())
+    }
+
+    /// 预计耗时超过时限的阶段
+    pub fn over_horizon(&self) -> impl Iterator<Item = &StageEstimate> {
+        self.stages
+            .iter()
+            .filter(|stage| self.exceeds_horizon(stage))
+    }
+
+    /// 有阶段超过时限时返回 [`CrackError::HorizonExceeded`]
+    pub fn check_horizon(&self) -> Result<(), CrackError> {
+        match (self.horizon, self.over_horizon().next()) {
+            (Some(horizon), Some(stage)) => Err(CrackError::HorizonExceeded(
+                stage.name.clone(),
+                format_eta(stage.eta_secs),
+                format_eta(Some(horizon.as_secs_f64())),
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// 估算 `config` 的各阶段，不测试任何密码（没有基准测试结果时除外，此时实测 [`MEASURE_DURATION`]）
+///
+/// # 示例
+/// ```no_run
+/// use archive_cracker::CrackConfig;
+/// use archive_cracker::estimate::estimate_run;
+/// use archive_cracker::observer::{CrackControl, Monitor, SilentObserver};
+/// use archive_cracker::progress::format_eta;
+///
+/// let config = CrackConfig::builder("file.zip").length_range(1, 8).build().unwrap();
+/// let monitor = Monitor::new(&SilentObserver, CrackControl::new());
+/// let estimate = estimate_run(&config, &monitor).unwrap();
+/// for stage in &estimate.stages {
+///     println!("{}: {} 个候选，预计 {}", stage.name, stage.keyspace, format_eta(stage.eta_secs));
+/// }
+/// ```
+pub fn estimate_run(config: &CrackConfig, monitor: &Monitor) -> Result<RunEstimate, CrackError> {
+    let archive_path = config.archive.as_path();
+    let format = ArchiveFormat::detect(archive_path).ok_or(CrackError::UnsupportedFormat)?;
+    let handler = get_handler(format);
+    let bruteforce_total = config.validate()?;
+    let plan = config.plan.as_deref().map(load_plan).transpose()?;
+    let targets = select_targets(handler.as_ref(), config)?;
+    let target = targets
+        .first()
+        .cloned()
+        .ok_or(CrackError::NoRecognizableFile)?;
+
+    // 与实际运行一致：内置字典不存在时先创建
+    let builtin = get_default_dictionary_path().map_err(CrackError::data_dir)?;
+    let _ = ensure_dictionary_exists(&builtin);
+    let sources = Sources {
+        config,
+        archive_path,
+        handler: handler.as_ref(),
+        dict_paths: run_dictionaries(&builtin, config),
+        builtin,
+    };
+    let stages = plan.as_ref().map_or_else(
+        || default_stages(&sources, bruteforce_total, monitor),
+        |plan| plan_stages(&sources, plan, monitor),
+    );
+
+    let cipher = bench::target_cipher(handler.as_ref(), archive_path, &target);
+    let speed = cipher
+        .zip(BenchResults::load())
+        .and_then(|(cipher, results)| results.speed(cipher, target.verification))
+        .map(|speed| (speed, SpeedSource::Bench))
+        .or_else(|| {
+            monitor.message("没有该加密方式的基准测试结果，实测验证速度...");
+            measure_speed(handler.as_ref(), archive_path, &targets)
+                .map(|speed| (speed, SpeedSource::Measured))
+        });
+
+    let budget = config
+        .limits
+        .budget()
+        .intersect(plan.as_ref().map_or(Budget::unlimited(), Plan::budget));
+    Ok(RunEstimate {
+        target,
+        cipher,
+        stages: apply_budgets(stages, budget, speed.map(|(speed, _)| speed)),
+        speed,
+        horizon: config.horizon,
+    })
+}
+
+/// 统计各阶段候选来源所需的上下文
+struct Sources<'a> {
+    config: &'a CrackConfig,
+    archive_path: &'a Path,
+    handler: &'a dyn ArchiveHandler,
+    dict_paths: Vec<PathBuf>,
+    /// 内置字典路径
+    builtin: PathBuf,
+}
+
+/// 应用预算之前的阶段
+struct Stage {
+    name: String,
+    detail: String,
+    keyspace: u128,
+    budget: Budget,
+}
+
+impl Stage {
+    fn new(name: impl Into<String>, (detail, keyspace): (String, u128)) -> Self {
+        Self {
+            name: name.into(),
+            detail,
+            keyspace,
+            budget: Budget::unlimited(),
+        }
+    }
+}
+
+/// 默认顺序：potfile、邻近文件和元数据候选词、已破解密码、字典、暴力破解（与实际运行的条件一致）
+fn default_stages(sources: &Sources, bruteforce_total: u128, monitor: &Monitor) -> Vec<Stage> {
+    let config = sources.config;
+    let selection = &config.stages;
+    let dict_paths = if selection.dictionary {
+        sources.dict_paths.as_slice()
+    } else {
+        &[]
+    };
+    let (dictionary, dictionary_total) = dictionary_keyspace(dict_paths, monitor);
+    let slice = config
+        .keyspace
+        .map(|selection| selection.slice(dictionary_total, bruteforce_total));
+
+    let mut stages = Vec::new();
+    if slice.as_ref().is_none_or(|slice| slice.start == 0) {
+        if selection.potfile {
+            stages.push(Stage::new("potfile", potfile_lookup(sources)));
+        }
+        if selection.neighbors.is_some() || selection.metadata {
+            stages.push(Stage::new(
+                harvest_stage_name(selection.neighbors.is_some(), selection.metadata),
+                harvest_keyspace(sources, selection.neighbors, selection.metadata),
+            ));
+        }
+        if selection.dictionary && selection.potfile {
+            stages.push(Stage::new("已破解密码", potfile_cracked()));
+        }
+    }
+
+    let (dictionary_range, bruteforce_range) = slice
+        .as_ref()
+        .map_or((0..dictionary_total, 0..bruteforce_total), |slice| {
+            (slice.dictionary_range(), slice.bruteforce_range())
+        });
+    if !dict_paths.is_empty() && !dictionary_range.is_empty() {
+        let count = dictionary_range.end - dictionary_range.start;
+        let detail = if slice.is_some() {
+            format!("{dictionary}，本分片 {count} 个")
+        } else {
+            dictionary
+        };
+        stages.push(Stage::new("字典", (detail, u128::from(count))));
+    }
+    if !bruteforce_range.is_empty() {
+        let bruteforce = &config.bruteforce;
+        let (min, max) = (bruteforce.min_length, bruteforce.max_length);
+        let (chars, _) = bruteforce_size(&bruteforce.charsets, min, max);
+        let lengths = if min == max {
+            format!("长度 {min}")
+        } else {
+            format!("长度 {min}~{max}")
+        };
+        let count = bruteforce_range.end - bruteforce_range.start;
+        stages.push(Stage::new(
+            "暴力破解",
+            (format!("{chars} 个字符，{lengths}"), count),
+        ));
+    }
+    stages
+}
+
+/// 攻击计划中的阶段
+fn plan_stages(sources: &Sources, plan: &Plan, monitor: &Monitor) -> Vec<Stage> {
+    plan.stages
+        .iter()
+        .map(|stage| {
+            let keyspace = match &stage.attack {
+                StageAttack::Potfile => {
+                    let (_, lookup) = potfile_lookup(sources);
+                    let (detail, cracked) = if sources.config.stages.potfile {
+                        potfile_cracked()
+                    } else {
+                        ("未启用 potfile".to_string(), 0)
+                    };
+                    (detail, lookup + cracked)
+                }
+                StageAttack::Harvest { neighbors, depth } => {
+                    harvest_keyspace(sources, neighbors.then_some(*depth), true)
+                }
+                StageAttack::Dictionary { paths, builtin } => {
+                    let dict_paths =
+                        stage_dictionaries(&sources.builtin, &sources.dict_paths, paths, *builtin);
+                    let (detail, count) = dictionary_keyspace(&dict_paths, monitor);
+                    (detail, u128::from(count))
+                }
+                StageAttack::Bruteforce {
+                    charset,
+                    min_length,
+                    max_length,
+                } => {
+                    let (chars, keyspace) = bruteforce_size(charset, *min_length, *max_length);
+                    (format!("{chars} 个字符"), keyspace)
+                }
+            };
+            Stage {
+                budget: stage.budget(),
+                ..Stage::new(stage.attack.to_string(), keyspace)
+            }
+        })
+        .collect()
+}
+
+/// potfile 中本压缩包（或其副本）已记录的密码
+fn potfile_lookup(sources: &Sources) -> (String, u128) {
+    let found = sources.config.stages.potfile
+        && get_default_potfile_path().is_ok_and(|path| {
+            sources
+                .handler
+                .fingerprint(sources.archive_path)
+                .and_then(|fingerprint| potfile::lookup(&path, &fingerprint))
+                .is_some()
+        });
+    let detail = if found {
+        "已记录本压缩包的密码"
+    } else {
+        "未记录本压缩包"
+    };
+    (detail.to_string(), u128::from(found))
+}
+
+/// potfile 中所有已破解过的密码
+fn potfile_cracked() -> (String, u128) {
+    let count = get_default_potfile_path().map_or(0, |path| potfile::passwords(&path).len());
+    (format!("{count} 个已破解的密码"), count as u128)
+}
+
+/// 邻近文件（`neighbors` 为递归层数）和压缩包元数据中的候选词
+fn harvest_keyspace(sources: &Sources, neighbors: Option<usize>, metadata: bool) -> (String, u128) {
+    let neighbors = neighbors.map_or(0, |depth| {
+        harvest_neighbors(sources.archive_path, depth).len()
+    });
+    let metadata = if metadata {
+        harvest_candidates(sources.archive_path, sources.handler).len()
+    } else {
+        0
+    };
+    let count = neighbors + metadata;
+    (format!("{count} 个候选词"), count as u128)
+}
+
+/// 各字典的条目数之和及其说明
+fn dictionary_keyspace(dict_paths: &[PathBuf], monitor: &Monitor) -> (String, u64) {
+    if dict_paths.is_empty() {
+        return ("没有字典".to_string(), 0);
+    }
+    monitor.message("统计字典条目数...");
+    let count = count_dictionary_entries(dict_paths).iter().sum();
+    (
+        format!("{} 个字典，共 {count} 个条目", dict_paths.len()),
+        count,
+    )
+}
+
+/// 暴力破解的字符集大小和键空间（加载配置和计划时已检查不超出 u128）
+fn bruteforce_size(charsets: &[Charset], min_length: u32, max_length: u32) -> (usize, u128) {
+    let (_, chars) = get_combined_charset(charsets);
+    let keyspace = bruteforce_keyspace(chars.len(), min_length, max_length).unwrap_or(u128::MAX);
+    (chars.len(), keyspace)
+}
+
+/// 在验证条目上用全部核心测试错误密码，返回每秒测试的数量
+fn measure_speed(
+    handler: &dyn ArchiveHandler,
+    archive_path: &Path,
+    targets: &[TargetFile],
+) -> Option<f64> {
+    let try_one = |password: &str| {
+        let _ = handler.try_targets(archive_path, password, targets);
+    };
+    Some(bench::measure_parallel(&try_one, MEASURE_DURATION)).filter(|speed| *speed > 0.0)
+}
+
+/// 按执行顺序应用阶段预算和整个运行的预算（前面的阶段消耗整个运行的预算），计算预计耗时
+#[allow(
+    clippy::cast_precision_loss,
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss
+)]
+fn apply_budgets(stages: Vec<Stage>, run: Budget, speed: Option<f64>) -> Vec<StageEstimate> {
+    let now = Instant::now();
+    let seconds = |budget: Budget| {
+        budget
+            .deadline
+            .map(|deadline| deadline.saturating_duration_since(now).as_secs_f64())
+    };
+    let mut run_candidates = run.max_candidates.map(u128::from);
+    let mut run_seconds = seconds(run);
+
+    stages
+        .into_iter()
+        .map(|stage| {
+            let mut candidates = [stage.budget.max_candidates.map(u128::from), run_candidates]
+                .into_iter()
+                .flatten()
+                .fold(stage.keyspace, u128::min);
+            if let Some(speed) = speed
+                && let Some(secs) = [seconds(stage.budget), run_seconds]
+                    .into_iter()
+                    .flatten()
+                    .reduce(f64::min)
+            {
+                candidates = candidates.min((secs * speed) as u128);
+            }
+            let eta_secs = speed.map(|speed| candidates as f64 / speed);
+
+            run_candidates = run_candidates.map(|remaining| remaining.saturating_sub(candidates));
+            run_seconds =
+                run_seconds.map(|remaining| (remaining - eta_secs.unwrap_or(0.0)).max(0.0));
+            StageEstimate {
+                name: stage.name,
+                detail: stage.detail,
+                keyspace: stage.keyspace,
+                candidates,
+                eta_secs,
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,342 @@
+//! 找到密码后解压压缩包
+//!
+//! 各格式的处理器逐个读取条目，交给 [`Extractor`] 写入目标目录。[`Extractor`] 负责过滤条目、
+//! 检查路径（拒绝 `..` 和经过符号链接的路径）、按覆盖策略处理已存在的文件，并限制解压总大小和
+//! 单个条目的压缩比，防止压缩炸弹
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+use crate::archive::common::{decode_text, wildcard_match};
+
+/// 默认的解压总大小上限（16 GiB）
+pub const DEFAULT_MAX_SIZE: u64 = 16 << 30;
+
+/// 默认的单个条目压缩比上限
+pub const DEFAULT_MAX_RATIO: u64 = 1000;
+
+/// 目标文件已存在时的处理方式
+#[derive(Clone, Copy, Debug, Default, ValueEnum, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Overwrite {
+    /// 跳过已存在的文件
+    #[default]
+    Skip,
+    /// 覆盖已存在的文件
+    Replace,
+    /// 另存为 `名称 (1).扩展名`
+    Rename,
+}
+
+/// ZIP 条目名的编码（7z 条目名总是 Unicode）
+#[derive(Clone, Copy, Debug, Default, ValueEnum, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NameEncoding {
+    /// 有效的 UTF-8 按 UTF-8 解码，否则按 GBK 解码
+    #[default]
+    Auto,
+    Utf8,
+    Gbk,
+}
+
+impl NameEncoding {
+    /// 解码原始条目名
+    #[must_use]
+    pub fn decode(self, raw: &[u8]) -> String {
+        match self {
+            Self::Auto => decode_text(raw),
+            Self::Utf8 => String::from_utf8_lossy(raw).into_owned(),
+            Self::Gbk => encoding_rs::GBK.decode(raw).0.into_owned(),
+        }
+    }
+}
+
+/// 解压选项
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ExtractOptions {
+    /// 目标目录（不存在时创建）
+    pub dir: PathBuf,
+    pub overwrite: Overwrite,
+    /// 只解压名称匹配该通配符（`*`、`?`）的条目
+    pub filter: Option<String>,
+    pub encoding: NameEncoding,
+    /// 解压总大小上限（字节）
+    pub max_size: u64,
+    /// 单个条目的压缩比上限（解压后大小 / 压缩后大小），0 表示不限制
+    pub max_ratio: u64,
+}
+
+impl Default for ExtractOptions {
+    fn default() -> Self {
+        Self {
+            dir: PathBuf::from("."),
+            overwrite: Overwrite::default(),
+            filter: None,
+            encoding: NameEncoding::default(),
+            max_size: DEFAULT_MAX_SIZE,
+            max_ratio: DEFAULT_MAX_RATIO,
+        }
+    }
+}
+
+impl ExtractOptions {
+    /// 解压到 `dir`，其余选项使用默认值
+    #[must_use]
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            ..Self::default()
+        }
+    }
+}
+
+/// 解压结果
+#[derive(Debug, Clone, Default)]
+pub struct ExtractReport {
+    /// 解压的文件数量
+    pub files: usize,
+    /// 解压的总字节数
+    pub bytes: u64,
+    /// 未解压的条目及原因
+    pub skipped: Vec<(String, String)>,
+}
+
+/// 把条目写入目标目录，由各格式的处理器调用
+pub struct Extractor<'a> {
+    options: &'a ExtractOptions,
+    /// 只解压这些索引的条目，`None` 表示全部
+    only: Option<Vec<usize>>,
+    /// 已达到解压总大小上限
+    full: bool,
+    report: ExtractReport,
+}
+
+impl<'a> Extractor<'a> {
+    #[must_use]
+    pub fn new(options: &'a ExtractOptions) -> Self {
+        Self {
+            options,
+            only: None,
+            full: false,
+            report: ExtractReport::default(),
+        }
+    }
+
+    /// 只解压这些索引的条目（按条目使用不同密码时，每个密码解压各自的条目）
+    pub fn restrict(&mut self, indexes: Vec<usize>) {
+        self.only = Some(indexes);
+    }
+
+    /// 目标目录
+    #[must_use]
+    pub fn dir(&self) -> &Path {
+        &self.options.dir
+    }
+
+    /// 按选项中的编码解码 ZIP 原始条目名
+    #[must_use]
+    pub fn decode_name(&self, raw: &[u8]) -> String {
+        self.options.encoding.decode(raw)
+    }
+
+    /// 是否需要解压该条目（索引和名称过滤条件）
+    #[must_use]
+    pub fn wants(&self, index: usize, name: &str) -> bool {
+        self.only.as_ref().is_none_or(|only| only.contains(&index))
+            && self
+                .options
+                .filter
+                .as_ref()
+                .is_none_or(|filter| wildcard_match(filter, name))
+    }
+
+    /// 是否已达到解压总大小上限（之后的条目都不再解压）
+    #[must_use]
+    pub const fn full(&self) -> bool {
+        self.full
+    }
+
+    /// 准备解压文件条目：检查路径、总大小、压缩比（`compressed` 未知时不检查）和覆盖策略
+    ///
+    /// 返回目标路径；不需要解压时返回 `None`，因限制而跳过时同时记录原因
+    pub fn begin(
+        &mut self,
+        index: usize,
+        name: &str,
+        size: u64,
+        compressed: Option<u64>,
+    ) -> Option<PathBuf> {
+        if !self.wants(index, name) {
+            return None;
+        }
+        let max_ratio = self.options.max_ratio;
+        let reason = if self.full || self.report.bytes.saturating_add(size) > self.options.max_size
+        {
+            format!("超出解压总大小上限 {} 字节", self.options.max_size)
+        } else if let Some(compressed) = compressed
+            && max_ratio > 0
+            && size > compressed.max(1).saturating_mul(max_ratio)
+        {
+            format!("压缩比超过 {max_ratio}:1，可能是压缩炸弹")
+        } else {
+            return self.destination(name);
+        };
+        self.skip(name, reason);
+        None
+    }
+
+    /// 创建目录条目
+    pub fn create_dir(&mut self, index: usize, name: &str) {
+        if !self.wants(index, name) {
+            return;
+        }
+        match safe_path(&self.options.dir, name) {
+            Ok(path) => {
+                if let Err(e) = fs::create_dir_all(&path) {
+                    self.skip(name, e.to_string());
+                }
+            }
+            Err(reason) => self.skip(name, reason),
+        }
+    }
+
+    /// 把 `reader` 的内容写入 [`begin`](Self::begin) 返回的路径，失败或超出大小上限时删除不完整的文件
+    pub fn write(&mut self, name: &str, dest: &Path, reader: &mut dyn Read) {
+        let remaining = self.options.max_size.saturating_sub(self.report.bytes);
+        let result = dest
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|()| File::create(dest))
+            .and_then(|mut file| {
+                io::copy(&mut reader.take(remaining.saturating_add(1)), &mut file)
+            });
+        match result {
+            Ok(written) if written <= remaining => {
+                self.report.files += 1;
+                self.report.bytes += written;
+            }
+            Ok(_) => {
+                self.full = true;
+                let _ = fs::remove_file(dest);
+                self.skip(
+                    name,
+                    format!("超出解压总大小上限 {} 字节", self.options.max_size),
+                );
+            }
+            Err(e) => {
+                let _ = fs::remove_file(dest);
+                self.skip(name, e.to_string());
+            }
+        }
+    }
+
+    /// 记录未解压的条目
+    pub fn skip(&mut self, name: &str, reason: impl Into<String>) {
+        self.report.skipped.push((name.to_string(), reason.into()));
+    }
+
+    #[must_use]
+    pub fn finish(self) -> ExtractReport {
+        self.report
+    }
+
+    /// 按覆盖策略确定目标路径
+    fn destination(&mut self, name: &str) -> Option<PathBuf> {
+        let path = match safe_path(&self.options.dir, name) {
+            Ok(path) => path,
+            Err(reason) => {
+                self.skip(name, reason);
+                return None;
+            }
+        };
+        if fs::symlink_metadata(&path).is_err() {
+            return Some(path);
+        }
+        match self.options.overwrite {
+            Overwrite::Replace if !path.is_symlink() => Some(path),
+            Overwrite::Replace => {
+                self.skip(name, "目标是符号链接");
+                None
+            }
+            Overwrite::Skip => {
+                self.skip(name, "文件已存在");
+                None
+            }
+            Overwrite::Rename => Some(unused_path(&path)),
+        }
+    }
+}
+
+/// 条目在目标目录中的路径：拒绝 `..`，绝对路径按相对路径处理，中间目录不能是符号链接
+fn safe_path(dir: &Path, name: &str) -> Result<PathBuf, String> {
+    let mut parts = Vec::new();
+    for part in name.split(['/', '\\']) {
+        match part {
+            "" | "." => {}
+            ".." => return Err("路径包含 ..".to_string()),
+            part => parts.push(part),
+        }
+    }
+    let Some((file_name, parents)) = parts.split_last() else {
+        return Err("条目名为空".to_string());
+    };
+    let mut path = dir.to_path_buf();
+    for part in parents {
+        path.push(part);
+        if path.is_symlink() {
+            return Err("路径经过符号链接".to_string());
+        }
+    }
+    path.push(file_name);
+    Ok(path)
+}
+
+/// `名称 (1).扩展名`、`名称 (2).扩展名` …… 中第一个不存在的路径
+fn unused_path(path: &Path) -> PathBuf {
+    let stem = path
+        .file_stem()
+        .map_or_else(String::new, |s| s.to_string_lossy().into_owned());
+    let extension = path
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy()));
+    (1..u32::MAX)
+        .map(|n| {
+            path.with_file_name(format!(
+                "{stem} ({n}){}",
+                extension.as_deref().unwrap_or("")
+            ))
+        })
+        .find(|candidate| fs::symlink_metadata(candidate).is_err())
+        .unwrap_or_else(|| path.to_path_buf())
+}
+
+/// 解析大小（如 `500M`、`16G`，单位为 1024 进制），不带单位时为字节
+///
+/// ```
+/// use archive_cracker::extract::parse_size;
+///
+/// assert_eq!(parse_size("4096"), Ok(4096));
+/// assert_eq!(parse_size("2K"), Ok(2048));
+/// assert_eq!(parse_size("16GiB"), Ok(16 << 30));
+/// assert!(parse_size("10X").is_err());
+/// ```
+pub fn parse_size(s: &str) -> Result<u64, String> {
+    let upper = s.trim().to_ascii_uppercase();
+    let digits = upper.trim_end_matches("IB").trim_end_matches('B');
+    let (number, shift) = match digits.chars().last() {
+        Some('K') => (&digits[..digits.len() - 1], 10),
+        Some('M') => (&digits[..digits.len() - 1], 20),
+        Some('G') => (&digits[..digits.len() - 1], 30),
+        Some('T') => (&digits[..digits.len() - 1], 40),
+        _ => (digits, 0),
+    };
+    number
+        .parse::<u64>()
+        .ok()
+        .and_then(|n| n.checked_mul(1 << shift))
+        .ok_or_else(|| format!("无效的大小: {s}（示例: 4096、500M、16G）"))
+}
@@ -0,0 +1,498 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::archive::common::{
+    decode_text, get_extension, is_infer_supported, is_text_extension, is_text_file_name,
+};
+use crate::archive::{ArchiveFormat, ArchiveHandler, get_handler};
+
+/// 候选词最大长度（字符数）
+const MAX_WORD_LEN: usize = 64;
+
+/// 读取邻近文本文件的最大字节数
+const MAX_NEIGHBOR_FILE_SIZE: u64 = 1024 * 1024;
+
+/// 最多扫描的邻近文件数量
+const MAX_NEIGHBOR_FILES: usize = 1000;
+
+/// 提示密码的关键字（长的在前，避免 `pass` 抢先匹配 `password`）
+const HINT_LABELS: &[&str] = &[
+    "解压密码",
+    "压缩密码",
+    "解压码",
+    "提取码",
+    "密码",
+    "口令",
+    "password",
+    "passwd",
+    "pass",
+    "pwd",
+];
+
+/// 成对出现的引号/括号
+const QUOTE_PAIRS: &[(char, char)] = &[
+    ('"', '"'),
+    ('\'', '\''),
+    ('`', '`'),
+    ('“', '”'),
+    ('‘', '’'),
+    ('「', '」'),
+    ('『', '』'),
+    ('【', '】'),
+];
+
+/// 带来源的候选词
+#[derive(Debug, Clone)]
+pub struct HarvestedWord {
+    /// 候选密码
+    pub word: String,
+    /// 提供该候选词的文件
+    pub source: String,
+}
+
+/// 从压缩包文件名、注释、条目名和未加密文本条目中收集候选密码
+///
+/// 返回的列表按可能性排序（文本条目 > 注释 > 文件名 > 条目名），已去重并包含简单变体
+#[must_use]
+pub fn harvest_candidates(archive_path: &Path, handler: &dyn ArchiveHandler) -> Vec<String> {
+    let mut texts: Vec<String> = Vec::new();
+
+    let metadata = handler.metadata(archive_path).unwrap_or_default();
+
+    // 未加密的说明文件（如 密码.txt）最可能直接包含密码
+    for (name, content) in &metadata.text_entries {
+        texts.push(content.clone());
+        texts.push(name.clone());
+    }
+    texts.push(metadata.comment);
+
+    // 压缩包文件名（去掉扩展名和分卷后缀）
+    if let Some(stem) = archive_path.file_stem() {
+        let stem = stem.to_string_lossy();
+        texts.push(strip_volume_suffix(&stem).to_string());
+        texts.push(stem.into_owned());
+    }
+
+    // 条目名及其目录组成部分
+    for name in &metadata.entry_names {
+        for component in name.split(['/', '\\']).filter(|c| !c.is_empty()) {
+            texts.push(strip_extension(component).to_string());
+        }
+    }
+
+    let mut words = Vec::new();
+    for text in &texts {
+        words.extend(extract_words(text));
+    }
+    with_variants(&words)
+}
+
+/// 扫描压缩包所在目录（及 `depth` 层子目录）中的说明文件和同源压缩包，收集候选密码
+///
+/// 支持 `.txt`、`.url`、`.html`、`.nfo` 等文本文件，以及同目录其他压缩包的文件名和元数据。
+/// 返回的列表中，`密码:` 等明确提示和引号内容排在普通分词之前，每个候选词只保留首个来源
+#[must_use]
+pub fn harvest_neighbors(archive_path: &Path, depth: usize) -> Vec<HarvestedWord> {
+    let dir = match archive_path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    let own_name = archive_path.file_name();
+
+    let mut files = Vec::new();
+    collect_neighbor_files(&dir, depth, &mut files);
+    files.retain(|path| path.file_name() != own_name || path.parent() != Some(dir.as_path()));
+
+    // 先收集所有明确提示，再收集普通分词
+    let mut hints: Vec<(String, String)> = Vec::new();
+    let mut words: Vec<(String, String)> = Vec::new();
+
+    for path in &files {
+        let source = path.to_string_lossy().to_string();
+        let Some(text) = read_neighbor_text(path) else {
+            continue;
+        };
+        let text = strip_html_tags(&text);
+
+        hints.extend(
+            extract_hints(&text)
+                .into_iter()
+                .map(|w| (w, source.clone())),
+        );
+        words.extend(
+            extract_words(&text)
+                .into_iter()
+                .map(|w| (w, source.clone())),
+        );
+    }
+
+    let mut seen = HashSet::new();
+    let mut result = Vec::new();
+    for (word, source) in hints.into_iter().chain(words) {
+        for variant in with_variants(&[word]) {
+            if seen.insert(variant.clone()) {
+                result.push(HarvestedWord {
+                    word: variant,
+                    source: source.clone(),
+                });
+            }
+        }
+    }
+
+    result
+}
+
+/// 递归收集邻近的文本文件和压缩包
+fn collect_neighbor_files(dir: &Path, depth: usize, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+
+    let mut paths: Vec<PathBuf> = entries.map_while(Result::ok).map(|e| e.path()).collect();
+    paths.sort();
+
+    for path in paths {
+        if files.len() >= MAX_NEIGHBOR_FILES {
+            return;
+        }
+        if path.is_dir() {
+            if depth > 0 {
+                collect_neighbor_files(&path, depth - 1, files);
+            }
+        } else if is_neighbor_file(&path) {
+            files.push(path);
+        }
+    }
+}
+
+/// 判断是否为需要扫描的邻近文件
+fn is_neighbor_file(path: &Path) -> bool {
+    let name = path.to_string_lossy();
+    ArchiveFormat::detect(path).is_some()
+        || is_text_file_name(&name)
+        || get_extension(&name).is_some_and(|ext| ext == "rar")
+}
+
+/// 读取邻近文件的文本：文本文件读取内容，压缩包读取文件名和元数据
+fn read_neighbor_text(path: &Path) -> Option<String> {
+    let name = path.to_string_lossy();
+
+    if let Some(format) = ArchiveFormat::detect(path) {
+        let mut text = path.file_stem()?.to_string_lossy().to_string();
+        if let Some(metadata) = get_handler(format).metadata(path) {
+            for (_, content) in metadata.text_entries {
+                text.push('\n');
+                text.push_str(&content);
+            }
+            text.push('\n');
+            text.push_str(&metadata.comment);
+        }
+        return Some(text);
+    }
+
+    // RAR 等暂不支持解析的压缩包，只使用文件名
+    if !is_text_file_name(&name) {
+        return Some(path.file_stem()?.to_string_lossy().to_string());
+    }
+
+    if fs::metadata(path).ok()?.len() > MAX_NEIGHBOR_FILE_SIZE {
+        return None;
+    }
+    Some(decode_text(&fs::read(path).ok()?))
+}
+
+/// 去掉 HTML 标签，保留 `href` 等属性中的网址
+fn strip_html_tags(text: &str) -> String {
+    if !text.contains('<') {
+        return text.to_string();
+    }
+
+    let mut result = String::with_capacity(text.len());
+    let mut tag = String::new();
+    let mut in_tag = false;
+
+    for c in text.chars() {
+        match c {
+            '<' => {
+                in_tag = true;
+                tag.clear();
+            }
+            '>' if in_tag => {
+                in_tag = false;
+                // 标签中的网址单独成行，便于提取域名
+                for domain in extract_domains(&tag) {
+                    result.push('\n');
+                    result.push_str(&domain);
+                }
+                result.push('\n');
+            }
+            _ if in_tag => tag.push(c),
+            _ => result.push(c),
+        }
+    }
+
+    result
+}
+
+/// 提取明确的密码提示：`密码: xxx`、`password=xxx` 等标签后的值，以及引号内的内容
+#[must_use]
+pub fn extract_hints(text: &str) -> Vec<String> {
+    let mut hints = Vec::new();
+
+    for line in text.lines() {
+        let lower = line.to_ascii_lowercase();
+
+        for label in HINT_LABELS {
+            let mut from = 0;
+            while let Some(pos) = lower[from..].find(label) {
+                let value_start = from + pos + label.len();
+                from = value_start;
+                if let Some(value) = labelled_value(&line[value_start..]) {
+                    hints.push(value);
+                }
+            }
+        }
+
+        for &(open, close) in QUOTE_PAIRS {
+            let mut rest = line;
+            while let Some(start) = rest.find(open) {
+                let after = &rest[start + open.len_utf8()..];
+                let Some(end) = after.find(close) else {
+                    break;
+                };
+                let quoted = after[..end].trim();
+                if (1..=MAX_WORD_LEN).contains(&quoted.chars().count()) {
+                    hints.push(quoted.to_string());
+                }
+                rest = &after[end + close.len_utf8()..];
+            }
+        }
+    }
+
+    hints
+}
+
+/// 解析标签后的值：允许 `:`、`：`、`=`、`是` 或空白分隔，值可被引号包围
+fn labelled_value(rest: &str) -> Option<String> {
+    let trimmed = rest.trim_start();
+    let separated = trimmed.len() != rest.len();
+    let after_sep = trimmed
+        .strip_prefix([':', '：', '='])
+        .or_else(|| trimmed.strip_prefix('是'));
+
+    let value_part = match after_sep {
+        Some(after) => after.trim_start(),
+        None if separated => trimmed,
+        None => return None,
+    };
+
+    let value = QUOTE_PAIRS
+        .iter()
+        .find_map(|&(open, close)| {
+            let inner = value_part.strip_prefix(open)?;
+            inner.find(close).map(|end| &inner[..end])
+        })
+        .unwrap_or_else(|| {
+            let end = value_part
+                .find(char::is_whitespace)
+                .unwrap_or(value_part.len());
+            value_part[..end]
+                .trim_end_matches(['，', '。', ',', '.', ';', '；', ')', '）', '!', '！'])
+        });
+
+    (1..=MAX_WORD_LEN)
+        .contains(&value.chars().count())
+        .then(|| value.to_string())
+}
+
+/// 从一段文本中提取候选词：明确提示、整行、分词结果和域名
+#[must_use]
+pub fn extract_words(text: &str) -> Vec<String> {
+    let mut words = extract_hints(text);
+
+    for line in text.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        words.push(line.to_string());
+
+        // 粗分词：按空白和括号、引号等分隔，保留 . - _ 连接的整体
+        for token in line.split(is_coarse_separator).filter(|t| !t.is_empty()) {
+            words.push(token.to_string());
+
+            // 细分词：继续按 . - _ 拆分
+            if token.contains(is_fine_separator) {
+                words.extend(
+                    token
+                        .split(is_fine_separator)
+                        .filter(|t| !t.is_empty())
+                        .map(ToString::to_string),
+                );
+            }
+        }
+
+        words.extend(extract_domains(line));
+    }
+
+    words
+        .into_iter()
+        .filter(|w| (2..=MAX_WORD_LEN).contains(&w.chars().count()))
+        .collect()
+}
+
+/// 从文本中提取 URL 和域名，并生成常见形式
+///
+/// 例如 `https://www.example.com/a` 生成 `www.example.com`、`example.com`、`example`
+#[must_use]
+pub fn extract_domains(text: &str) -> Vec<String> {
+    let mut domains = Vec::new();
+
+    let pieces = text.split(|c: char| !(c.is_ascii_alphanumeric() || c == '.' || c == '-'));
+    for piece in pieces {
+        let host = piece.trim_matches(['.', '-']).to_lowercase();
+        if !is_domain(&host) {
+            continue;
+        }
+
+        let bare = host.strip_prefix("www.").unwrap_or(&host).to_string();
+        let labels: Vec<&str> = bare.split('.').collect();
+
+        domains.push(host.clone());
+        if bare != host {
+            domains.push(bare.clone());
+        }
+        // 主域名 (example.com) 与站点名 (example)
+        if labels.len() >= 2 {
+            let name = labels[labels.len() - 2];
+            domains.push(format!("{name}.{}", labels[labels.len() - 1]));
+            domains.push(name.to_string());
+        }
+    }
+
+    domains
+}
+
+/// 为候选词生成简单变体（大小写、去空白），保持顺序并去重
+#[must_use]
+pub fn with_variants(words: &[String]) -> Vec<String> {
+    let mut seen = HashSet::new();
+    let mut result = Vec::new();
+
+    for word in words {
+        let compact: String = word.chars().filter(|c| !c.is_whitespace()).collect();
+        let variants = [
+            word.clone(),
+            compact.clone(),
+            word.to_lowercase(),
+            compact.to_lowercase(),
+            word.to_uppercase(),
+            capitalize(&compact.to_lowercase()),
+        ];
+        for variant in variants {
+            if !variant.is_empty() && seen.insert(variant.clone()) {
+                result.push(variant);
+            }
+        }
+    }
+
+    result
+}
+
+/// 首字母大写
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    chars.next().map_or_else(String::new, |first| {
+        first.to_uppercase().chain(chars).collect()
+    })
+}
+
+/// 粗分隔符：空白、括号、引号和常见标点
+fn is_coarse_separator(c: char) -> bool {
+    const SEPARATORS: &[char] = &[
+        '[', ']', '(', ')', '{', '}', '<', '>', '【', '】', '（', '）', '《', '》', '「', '」',
+        '『', '』', '"', '\'', '“', '”', '‘', '’', ',', '，', ';', '；', ':', '：', '、', '|', '/',
+        '\\', '!', '！', '?', '？', '=',
+    ];
+    c.is_whitespace() || SEPARATORS.contains(&c)
+}
+
+/// 细分隔符：在粗分词基础上继续拆分
+const fn is_fine_separator(c: char) -> bool {
+    matches!(c, '.' | '-' | '_' | '+' | '@' | '#' | '&' | '~')
+}
+
+/// 判断字符串是否像域名：至少两段，末段为 2-6 位字母且不是常见文件扩展名
+fn is_domain(host: &str) -> bool {
+    let labels: Vec<&str> = host.split('.').collect();
+    if labels.len() < 2 || labels.iter().any(|l| l.is_empty()) {
+        return false;
+    }
+    let tld = labels[labels.len() - 1];
+    (2..=6).contains(&tld.len())
+        && tld.chars().all(|c| c.is_ascii_alphabetic())
+        && !is_infer_supported(tld)
+        && !is_text_extension(tld)
+}
+
+/// 去掉文件扩展名（仅当扩展名看起来像真实扩展名时）
+fn strip_extension(name: &str) -> &str {
+    match name.rsplit_once('.') {
+        Some((stem, _)) if !stem.is_empty() && get_extension(name).is_some() => stem,
+        _ => name,
+    }
+}
+
+/// 去掉分卷后缀，如 `name.part1`、`name.7z` (来自 `name.7z.001`)
+fn strip_volume_suffix(stem: &str) -> &str {
+    let lower = stem.to_ascii_lowercase();
+    if let Some(pos) = lower.rfind(".part")
+        && lower[pos + 5..].chars().all(|c| c.is_ascii_digit())
+    {
+        return &stem[..pos];
+    }
+    strip_extension(stem)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hints_after_labels() {
+        // `解压密码` 和其中的 `密码` 都会匹配，重复的结果由调用方去重
+        for (text, value) in [
+            ("解压密码：abc123", "abc123"),
+            ("Password = Secret!", "Secret"),
+            ("pwd: x1, 其他", "x1"),
+            ("密码是 hello。", "hello"),
+        ] {
+            let hints = extract_hints(text);
+            assert!(!hints.is_empty(), "{text}");
+            assert!(hints.iter().all(|hint| hint == value), "{text}: {hints:?}");
+        }
+    }
+
+    #[test]
+    fn hints_keep_quoted_values_whole() {
+        let hints = extract_hints("password: \"two words\"");
+        assert!(hints.contains(&"two words".to_string()));
+        assert!(extract_hints("提取码「a b c」").contains(&"a b c".to_string()));
+    }
+
+    #[test]
+    fn hints_ignore_labels_inside_words() {
+        assert!(extract_hints("passport photos").is_empty());
+        assert!(extract_hints("password").is_empty());
+        assert!(extract_hints("no hints here").is_empty());
+    }
+
+    #[test]
+    fn hints_from_multiple_lines() {
+        let hints = extract_hints("第一卷 密码: one\n第二卷 密码: two");
+        assert_eq!(hints, ["one", "two"]);
+    }
+
+    #[test]
+    fn hints_skip_overlong_values() {
+        let long = "x".repeat(MAX_WORD_LEN + 1);
+        assert!(extract_hints(&format!("密码: {long}")).is_empty());
+    }
+}
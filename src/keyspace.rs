@@ -0,0 +1,287 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer, de};
+use std::fmt;
+use std::ops::Range;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use crate::attack::wordlist::{BATCH_SIZE, WordlistReader};
+use crate::charset::index_to_password;
+
+/// 键空间分片 `N/M`：把完整键空间均分为 `M` 份，取第 `N` 份（从 1 开始）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Part {
+    pub index: u64,
+    pub count: u64,
+}
+
+impl FromStr for Part {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (index, count) = s
+            .split_once('/')
+            .ok_or_else(|| format!("分片格式应为 N/M，例如 2/5: {s}"))?;
+        let index: u64 = index
+            .trim()
+            .parse()
+            .map_err(|_| format!("无效的分片序号: {index}"))?;
+        let count: u64 = count
+            .trim()
+            .parse()
+            .map_err(|_| format!("无效的分片总数: {count}"))?;
+        if count == 0 || index == 0 || index > count {
+            return Err(format!("分片序号应在 1 到 {count} 之间: {s}"));
+        }
+        Ok(Self { index, count })
+    }
+}
+
+impl Part {
+    /// 该分片在大小为 `total` 的键空间中的范围 `[start, end)`
+    #[must_use]
+    pub const fn bounds(self, total: u128) -> (u128, u128) {
+        (
+            part_bound(total, self.index - 1, self.count),
+            part_bound(total, self.index, self.count),
+        )
+    }
+}
+
+impl fmt::Display for Part {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.index, self.count)
+    }
+}
+
+/// 序列化为 `N/M` 形式的字符串
+impl Serialize for Part {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Part {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(de::Error::custom)
+    }
+}
+
+/// 本次运行负责的键空间范围
+///
+/// 键空间依次由各字典的条目（按字典顺序首尾相接，空行和注释行不计）
+/// 和暴力破解的索引（按长度从小到大首尾相接，每个长度内与 `index_to_password` 的索引一致）组成。
+/// 不同机器使用相同的参数和字典即可得到互不重叠的分片
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyspaceSlice {
+    /// 起始索引（包含）
+    pub start: u128,
+    /// 结束索引（不包含）
+    pub end: u128,
+    /// 完整键空间大小
+    pub total: u128,
+    /// 字典部分的大小（键空间中 `[0, dictionary_total)` 为字典条目）
+    pub dictionary_total: u64,
+}
+
+impl KeyspaceSlice {
+    /// 根据 `--skip`/`--limit` 或 `--part` 计算分片
+    #[must_use]
+    pub fn new(
+        dictionary_total: u64,
+        bruteforce_total: u128,
+        skip: Option<u128>,
+        limit: Option<u128>,
+        part: Option<Part>,
+    ) -> Self {
+        let total = u128::from(dictionary_total).saturating_add(bruteforce_total);
+        let (start, end) = part.map_or_else(
+            || {
+                let start = skip.unwrap_or(0).min(total);
+                let end = limit.map_or(total, |limit| start.saturating_add(limit).min(total));
+                (start, end)
+            },
+            |part| part.bounds(total),
+        );
+        Self {
+            start,
+            end,
+            total,
+            dictionary_total,
+        }
+    }
+
+    /// 分片包含的候选数量
+    #[must_use]
+    pub const fn len(&self) -> u128 {
+        self.end - self.start
+    }
+
+    /// 分片是否为空
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.start >= self.end
+    }
+
+    /// 分片在字典部分的范围
+    #[must_use]
+    #[allow(clippy::cast_possible_truncation)]
+    pub fn dictionary_range(&self) -> Range<u64> {
+        // 结果不超过 dictionary_total，转换不会截断
+        let dictionary_total = u128::from(self.dictionary_total);
+        self.start.min(dictionary_total) as u64..self.end.min(dictionary_total) as u64
+    }
+
+    /// 分片在暴力破解部分的范围（暴力破解索引从 0 开始）
+    #[must_use]
+    pub fn bruteforce_range(&self) -> Range<u128> {
+        let dictionary_total = u128::from(self.dictionary_total);
+        self.start.max(dictionary_total) - dictionary_total
+            ..self.end.max(dictionary_total) - dictionary_total
+    }
+}
+
+/// 第 `k` 个分界点：`total * k / count`，拆成商和余数两部分计算以避免 u128 溢出
+const fn part_bound(total: u128, k: u64, count: u64) -> u128 {
+    let (k, count) = (k as u128, count as u128);
+    total / count * k + total % count * k / count
+}
+
+/// 统计各字典的条目数（与字典攻击的计数方式一致）
+#[must_use]
+pub fn count_dictionary_entries(paths: &[PathBuf]) -> Vec<u64> {
+    let mut batch = Vec::new();
+    paths
+        .iter()
+        .map(|path| {
+            let Ok(mut reader) = WordlistReader::open(path) else {
+                return 0;
+            };
+            let mut count = 0;
+            while matches!(reader.next_batch(&mut batch, BATCH_SIZE), Ok(true)) {
+                count += batch.len() as u64;
+            }
+            count
+        })
+        .collect()
+}
+
+/// 把字典部分的全局范围拆成每个字典内的条目范围
+#[must_use]
+pub fn split_dictionary_range(counts: &[u64], range: &Range<u64>) -> Vec<Range<u64>> {
+    let mut offset = 0;
+    counts
+        .iter()
+        .map(|&count| {
+            let start = range.start.clamp(offset, offset + count) - offset;
+            let end = range.end.clamp(offset, offset + count) - offset;
+            offset += count;
+            start..end
+        })
+        .collect()
+}
+
+/// 单个长度的组合数，超出 u128 时返回 `None`
+#[must_use]
+pub const fn length_keyspace(charset_size: usize, len: u32) -> Option<u128> {
+    (charset_size as u128).checked_pow(len)
+}
+
+/// 暴力破解部分的键空间大小（各长度组合数之和），超出 u128 时返回 `None`
+#[must_use]
+pub fn bruteforce_keyspace(charset_size: usize, min_len: u32, max_len: u32) -> Option<u128> {
+    (min_len..=max_len).try_fold(0u128, |total, len| {
+        total.checked_add(length_keyspace(charset_size, len)?)
+    })
+}
+
+/// 暴力破解全局索引（各长度首尾相接）对应的密码，超出键空间时返回 `None`
+#[must_use]
+pub fn bruteforce_candidate(
+    index: u128,
+    chars: &[char],
+    min_len: u32,
+    max_len: u32,
+) -> Option<String> {
+    let mut offset = 0u128;
+    for len in min_len..=max_len {
+        let count = length_keyspace(chars.len(), len)?;
+        if index - offset < count {
+            return Some(index_to_password(index - offset, chars, len as usize));
+        }
+        offset = offset.saturating_add(count);
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn part_parses_valid_input() {
+        assert_eq!("2/5".parse(), Ok(Part { index: 2, count: 5 }));
+        assert_eq!(" 1 / 1 ".parse(), Ok(Part { index: 1, count: 1 }));
+    }
+
+    #[test]
+    fn part_rejects_invalid_input() {
+        for input in ["", "3", "0/5", "6/5", "1/0", "a/5", "1/b", "-1/5", "1/5/7"] {
+            assert!(input.parse::<Part>().is_err(), "{input}");
+        }
+    }
+
+    #[test]
+    fn parts_cover_keyspace_without_overlap() {
+        for total in [0, 1, 7, 100, u128::MAX] {
+            let count = 3;
+            let mut next = 0;
+            for index in 1..=count {
+                let (start, end) = Part { index, count }.bounds(total);
+                assert_eq!(start, next);
+                assert!(start <= end);
+                next = end;
+            }
+            assert_eq!(next, total);
+        }
+    }
+
+    #[test]
+    fn slice_clamps_skip_and_limit() {
+        let slice = KeyspaceSlice::new(10, 90, Some(95), Some(20), None);
+        assert_eq!((slice.start, slice.end, slice.total), (95, 100, 100));
+
+        let slice = KeyspaceSlice::new(10, 90, Some(200), None, None);
+        assert!(slice.is_empty());
+        assert_eq!(slice.len(), 0);
+
+        let slice = KeyspaceSlice::new(0, u128::MAX, Some(u128::MAX - 1), Some(u128::MAX), None);
+        assert_eq!(slice.end, u128::MAX);
+        assert_eq!(slice.len(), 1);
+
+        let slice = KeyspaceSlice::new(u64::MAX, u128::MAX, None, None, None);
+        assert_eq!(slice.total, u128::MAX);
+    }
+
+    #[test]
+    fn slice_splits_into_dictionary_and_bruteforce_ranges() {
+        let slice = KeyspaceSlice::new(10, 90, Some(5), Some(10), None);
+        assert_eq!(slice.dictionary_range(), 5..10);
+        assert_eq!(slice.bruteforce_range(), 0..5);
+
+        let slice = KeyspaceSlice::new(10, 90, None, Some(4), None);
+        assert_eq!(slice.dictionary_range(), 0..4);
+        assert!(slice.bruteforce_range().is_empty());
+
+        let slice = KeyspaceSlice::new(10, 90, Some(50), None, None);
+        assert!(slice.dictionary_range().is_empty());
+        assert_eq!(slice.bruteforce_range(), 40..90);
+    }
+
+    #[test]
+    fn slice_part_overrides_skip_and_limit() {
+        let slice = KeyspaceSlice::new(0, 100, Some(7), Some(3), Some(Part { index: 2, count: 4 }));
+        assert_eq!((slice.start, slice.end), (25, 50));
+    }
+}
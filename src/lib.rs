@@ -1,24 +1,80 @@
 pub mod archive;
 pub mod attack;
+pub mod bench;
 pub mod charset;
 pub mod cli;
+pub mod config;
+pub mod distributed;
+pub mod estimate;
+pub mod extract;
+pub mod harvest;
+pub mod keyspace;
+pub mod observer;
 pub mod passwords;
+pub mod paths;
+pub mod per_entry;
+pub mod plan;
+pub mod potfile;
+pub mod progress;
+pub mod rewrap;
+pub mod session;
 
-use archive::{ArchiveFormat, get_handler};
+use archive::common::wildcard_match;
+use archive::{ArchiveError, ArchiveHandler, EntryInfo, TargetFile};
+use archive::{ArchiveFormat, get_handler, pick_target};
+use attack::Budget;
+use attack::bruteforce::BruteforceParams;
+use attack::dictionary::{DictionaryParams, DictionaryResult, SourceStats, WordlistParams};
 use attack::{
-    bruteforce_attack, dictionary_attack, ensure_dictionary_exists, get_default_dictionary_path,
+    append_to_dictionary, bruteforce_attack, dictionary_attack, ensure_dictionary_exists,
+    get_default_dictionary_path, resolve_dictionaries, wordlist_attack,
 };
+use bench::BenchResults;
 pub use cli::Args;
+use cli::Charset;
+pub use config::{CrackConfig, CrackConfigBuilder};
+use config::{KeyspaceSelection, ServeConfig};
+use extract::{ExtractReport, Extractor};
+use harvest::{harvest_candidates, harvest_neighbors};
+use keyspace::{
+    KeyspaceSlice, Part, bruteforce_keyspace, count_dictionary_entries, split_dictionary_range,
+};
+use observer::{CrackControl, CrackObserver, Monitor, SilentObserver};
+use plan::{Plan, PlanStage, StageAttack};
+use potfile::get_default_potfile_path;
+use progress::Pending;
+use rewrap::{RewrapReport, Rewrapper};
+use session::{Session, SessionStage, SessionTracker};
+use std::io;
+use std::net::TcpListener;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Instant;
 
 /// 密码破解成功结果
 #[derive(Debug, Clone)]
 pub struct CrackSuccess {
     /// 找到的密码
     pub password: String,
+    /// 密码来源（攻击阶段或提供该密码的文件）
+    pub source: String,
     /// 总共测试的密码数量
     pub total_tested: u64,
     /// 总耗时（秒）
     pub elapsed_secs: f64,
+    /// 各字典来源的统计
+    pub dictionary_sources: Vec<SourceStats>,
+    /// 本次运行负责的键空间分片（使用 `--skip`/`--limit`/`--part` 时）
+    pub keyspace: Option<KeyspaceSlice>,
+    /// 各攻击阶段的统计
+    pub stages: Vec<StageReport>,
+    /// 通过快速检查但内容检查未通过的候选数量（近似匹配）
+    pub near_misses: u64,
+    /// 解压结果（使用 `--extract` 时）
+    pub extracted: Option<ExtractReport>,
+    /// 重新打包结果（使用 `--rewrap` 且成功时）
+    pub rewrapped: Option<RewrapReport>,
 }
 
 impl CrackSuccess {
@@ -41,6 +97,16 @@ pub struct CrackFailure {
     pub total_tested: u64,
     /// 总耗时（秒）
     pub elapsed_secs: f64,
+    /// 各字典来源的统计
+    pub dictionary_sources: Vec<SourceStats>,
+    /// 本次运行负责的键空间分片（使用 `--skip`/`--limit`/`--part` 时）
+    pub keyspace: Option<KeyspaceSlice>,
+    /// 各攻击阶段的统计（包括因预算用尽而提前结束或未执行的阶段）
+    pub stages: Vec<StageReport>,
+    /// 通过快速检查但内容检查未通过的候选数量（近似匹配）
+    ///
+    /// 数量很多时可能是验证方式不适合目标文件（如文件本身已损坏）
+    pub near_misses: u64,
 }
 
 impl CrackFailure {
@@ -56,15 +122,63 @@ impl CrackFailure {
     }
 }
 
+/// 攻击阶段的结束状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StageStatus {
+    /// 搜索空间已全部测试
+    Completed,
+    /// 找到密码
+    Found,
+    /// 阶段或整个运行的预算用尽，提前结束
+    CutShort,
+    /// 被 Ctrl-C 中断
+    Interrupted,
+    /// 压缩包无法读取或不受支持，中止
+    Failed,
+    /// 整个运行的预算用尽，未执行
+    Skipped,
+}
+
+impl std::fmt::Display for StageStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Self::Completed => "已完成",
+            Self::Found => "找到密码",
+            Self::CutShort => "预算用尽",
+            Self::Interrupted => "已中断",
+            Self::Failed => "出错中止",
+            Self::Skipped => "未执行",
+        };
+        f.write_str(name)
+    }
+}
+
+/// 单个攻击阶段的统计
+#[derive(Debug, Clone)]
+pub struct StageReport {
+    /// 阶段名称
+    pub name: String,
+    pub status: StageStatus,
+    /// 本阶段测试的候选数量
+    pub tested: u64,
+    /// 本阶段耗时（秒）
+    pub elapsed_secs: f64,
+    /// 本阶段的近似匹配数量
+    pub near_misses: u64,
+}
+
 /// 密码破解错误
 #[derive(Debug, Clone, thiserror::Error)]
 pub enum CrackError {
     #[error("不支持的压缩包格式（支持: ZIP, 7z）")]
     UnsupportedFormat,
 
-    #[error("未找到可识别扩展名的加密文件")]
+    #[error("压缩包中没有可用于验证密码的文件（只有目录或空文件）")]
     NoRecognizableFile,
 
+    #[error("压缩包中没有加密的文件，无需破解")]
+    NotEncrypted,
+
     #[error("--length 和 --max-length 不能同时使用")]
     ConflictingLengthParams,
 
@@ -74,17 +188,97 @@ pub enum CrackError {
     #[error("密码长度不能为 0")]
     ZeroLength,
 
+    #[error("必须至少选择一个字符集")]
+    EmptyCharset,
+
+    #[error("暴力破解键空间超出 u128 范围 ({0} 个字符, 最大长度 {1})，请缩小字符集或长度")]
+    KeyspaceTooLarge(usize, u32),
+
+    #[error("--part 不能与 --skip/--limit 同时使用")]
+    ConflictingPartitionParams,
+
+    #[error("--per-entry 不能与 --entry、--expect-entry 或 --serve 同时使用")]
+    ConflictingPerEntry,
+
+    #[error("--plan 不能与 --serve、--skip、--limit 或 --part 同时使用")]
+    ConflictingPlan,
+
+    #[error("无法重新打包: {0}")]
+    InvalidRewrap(String),
+
+    #[error("阶段「{0}」预计耗时 {1}，超过时限 {2}，未开始破解（可用 --dry-run 查看各阶段的估算）")]
+    HorizonExceeded(String, String, String),
+
+    #[error("分片序号应在 1 到 {count} 之间: {0}", count = .0.count)]
+    InvalidPart(Part),
+
+    #[error("压缩包中没有条目 {0}")]
+    EntryNotFound(String),
+
+    #[error("条目 {0} 无法用于验证密码: {1}")]
+    UnverifiableEntry(String, String),
+
     #[error("未找到密码")]
-    NotFound(CrackFailure),
+    NotFound(Box<CrackFailure>),
+
+    #[error("会话名不能为空，也不能包含路径分隔符或 ..: {0}")]
+    InvalidSessionName(String),
+
+    #[error("无法加载会话 {0}: {1}")]
+    SessionLoad(String, String),
+
+    #[error("无法加载攻击计划 {0}: {1}")]
+    PlanLoad(String, String),
+
+    #[error("网络错误: {0}")]
+    Network(String),
+
+    #[error("令牌无效: {0}")]
+    InvalidToken(String),
+
+    #[error("无法定位数据目录 ~/.archive_cracker: {0}")]
+    DataDir(#[source] Arc<io::Error>),
+
+    #[error(transparent)]
+    Archive(#[from] ArchiveError),
+
+    #[error("已中断，会话 {0} 已保存")]
+    Interrupted(String, Box<CrackFailure>),
+
+    #[error("预算用尽，会话 {0} 已保存")]
+    BudgetExhausted(String, Box<CrackFailure>),
+
+    #[error("{1}，已中止，会话 {0} 已保存")]
+    Aborted(String, #[source] ArchiveError, Box<CrackFailure>),
+}
+
+impl CrackError {
+    fn data_dir(e: io::Error) -> Self {
+        Self::DataDir(Arc::new(e))
+    }
+
+    /// 未找到密码、中断、预算用尽或中止时的统计
+    #[must_use]
+    pub fn failure(&self) -> Option<&CrackFailure> {
+        match self {
+            Self::NotFound(failure)
+            | Self::Interrupted(_, failure)
+            | Self::BudgetExhausted(_, failure)
+            | Self::Aborted(_, _, failure) => Some(failure),
+            _ => None,
+        }
+    }
 }
 
 /// 密码破解结果类型
 pub type CrackResult = Result<CrackSuccess, CrackError>;
 
-/// 执行密码破解
+/// 执行密码破解，不输出任何内容
+///
+/// 需要接收进度、取消或暂停时使用 [`crack_archive_with`]
 ///
 /// # 参数
-/// - `args`: CLI 参数配置
+/// - `config`: 破解配置，可以用 [`CrackConfig::builder`] 构建或由命令行参数转换
 ///
 /// # 返回
 /// 成功返回 `Ok(CrackSuccess)` 包含密码和统计信息
@@ -92,119 +286,1195 @@ pub type CrackResult = Result<CrackSuccess, CrackError>;
 ///
 /// # 示例
 /// ```no_run
-/// use archive_cracker::{crack_archive, Args};
+/// use archive_cracker::{CrackConfig, crack_archive};
 ///
-/// let args = Args {
-///     archive_path: "file.zip".to_string(),
-///     dictionary: None,
-///     charset: vec![],
-///     length: Some(4),
-///     max_length: None,
-///     min_length: 1,
-///     skip_dictionary: false,
-/// };
+/// let config = CrackConfig::builder("file.zip").length(4).build().unwrap();
 ///
-/// match crack_archive(&args) {
+/// match crack_archive(&config) {
 ///     Ok(success) => {
-///         println!("密码: {}", success.password);
+///         println!("密码: {} (来源: {})", success.password, success.source);
 ///         println!("速度: {:.0} 次/秒", success.speed());
 ///     }
 ///     Err(e) => eprintln!("错误: {}", e),
 /// }
 /// ```
-pub fn crack_archive(args: &Args) -> CrackResult {
-    let archive_path = &args.archive_path;
+pub fn crack_archive(config: &CrackConfig) -> CrackResult {
+    crack_archive_with(config, &SilentObserver, &CrackControl::new())
+}
+
+/// 执行密码破解，通过 `observer` 报告阶段、进度和提示，可以通过 `control` 取消或暂停
+///
+/// 取消后返回 `CrackError::Interrupted`，会话已保存，之后可以用 `--restore` 继续
+///
+/// # 示例
+/// ```no_run
+/// use archive_cracker::observer::{CrackControl, CrackObserver};
+/// use archive_cracker::progress::Status;
+/// use archive_cracker::{CrackConfig, StageReport, crack_archive_with};
+///
+/// struct Log;
+///
+/// impl CrackObserver for Log {
+///     fn stage_finished(&self, report: &StageReport) {
+///         println!("{}: {}", report.name, report.status);
+///     }
+///
+///     fn progress(&self, status: &Status) {
+///         println!("{:.0} 次/秒", status.speed);
+///     }
+/// }
+///
+/// let config = CrackConfig::builder("file.zip").build().unwrap();
+/// let control = CrackControl::new();
+/// let canceller = control.clone();
+/// std::thread::spawn(move || {
+///     std::thread::sleep(std::time::Duration::from_secs(60));
+///     canceller.cancel();
+/// });
+/// let result = crack_archive_with(&config, &Log, &control);
+/// ```
+pub fn crack_archive_with(
+    config: &CrackConfig,
+    observer: &dyn CrackObserver,
+    control: &CrackControl,
+) -> CrackResult {
+    let archive_path = config.archive.as_path();
+    let monitor = Monitor::new(observer, control.clone());
 
     // 检测压缩包格式
     let format = ArchiveFormat::detect(archive_path).ok_or(CrackError::UnsupportedFormat)?;
     let handler = get_handler(format);
 
-    // 获取字典路径
-    let default_dict_path = get_default_dictionary_path();
-    let dict_path = args
-        .dictionary
-        .as_ref()
-        .map_or_else(|| default_dict_path.clone(), std::path::PathBuf::from);
+    // 先校验长度参数和键空间大小，避免前面的阶段跑完才报错
+    let bruteforce_total = config.validate()?;
+    let plan = config.plan.as_deref().map(load_plan).transpose()?;
+    if let Some(plan) = &plan
+        && !plan.description.is_empty()
+    {
+        monitor.message(&format!("攻击计划: {}", plan.description));
+    }
 
-    // 确保默认字典存在
-    let _ = ensure_dictionary_exists(&default_dict_path);
+    // 整个运行的预算，计划文件中的预算同时生效
+    let budget = config
+        .limits
+        .budget()
+        .intersect(plan.as_ref().map_or(Budget::unlimited(), Plan::budget));
 
-    // 检测目标文件
-    let target = handler
-        .detect_target(archive_path)
-        .ok_or(CrackError::NoRecognizableFile)?;
+    // 确保默认字典存在，内置字典总是最先尝试
+    let default_dict_path = get_default_dictionary_path().map_err(CrackError::data_dir)?;
+    if let Err(e) = ensure_dictionary_exists(&default_dict_path) {
+        monitor.warning(&format!(
+            "无法创建内置字典 {}: {e}",
+            default_dict_path.display()
+        ));
+    }
+    let dict_paths = run_dictionaries(&default_dict_path, config);
+
+    // 检测目标文件，指定了验证条目或已知内容时使用这些条目
+    let targets = select_targets(handler.as_ref(), config)?;
+    report_estimate(
+        handler.as_ref(),
+        archive_path,
+        &targets,
+        bruteforce_total,
+        &monitor,
+    );
 
-    let file_count = handler.file_count(archive_path).unwrap_or(0);
+    // 设置了时限时先估算各阶段，有阶段超出时不开始（恢复的会话已经开始过，不再检查）
+    if config.horizon.is_some() && config.restore.is_none() {
+        estimate::estimate_run(config, &monitor)?.check_horizon()?;
+    }
 
-    let mut found_password: Option<String> = None;
-    let mut total_tested: u64 = 0;
-    let mut total_elapsed: f64 = 0.0;
+    let file_count = handler.file_count(archive_path)?;
 
-    // 第一阶段：字典攻击
-    if !args.skip_dictionary && dict_path.exists() {
-        let result = dictionary_attack(
-            archive_path,
-            &dict_path.to_string_lossy(),
-            &target,
-            file_count,
-            handler.as_ref(),
+    let potfile_path = get_default_potfile_path().map_err(CrackError::data_dir)?;
+    let fingerprint = if config.stages.potfile {
+        handler.fingerprint(archive_path)
+    } else {
+        None
+    };
+
+    // 键空间分片：各机器使用相同参数即可分到互不重叠的范围
+    let partition = partition_keyspace(config, &dict_paths, bruteforce_total, &monitor);
+    let slice = partition.as_ref().map(|(slice, _)| slice);
+
+    let tracker = SessionTracker::new(open_session(config)?);
+    let resumed = tracker.snapshot();
+
+    let ctx = StageContext {
+        config,
+        archive_path,
+        targets: &targets,
+        file_count,
+        handler: handler.as_ref(),
+        dict_paths: &dict_paths,
+        builtin_dictionary: &default_dict_path,
+        bruteforce_total,
+        budget,
+        monitor: &monitor,
+    };
+    let mut state = RunState {
+        keyspace: slice.cloned(),
+        ..RunState::default()
+    };
+
+    // 攻击计划：按计划文件中的阶段顺序执行，否则按默认顺序
+    monitor.with_reporter(config.status_interval, || {
+        if let Some(plan) = &plan {
+            plan_stages(
+                &ctx,
+                &mut state,
+                &tracker,
+                plan,
+                fingerprint.as_deref(),
+                &potfile_path,
+            );
+            Ok(())
+        } else {
+            default_stages(
+                &ctx,
+                &mut state,
+                &tracker,
+                partition.as_ref(),
+                fingerprint.as_deref(),
+                &potfile_path,
+            )
+        }
+    })?;
+
+    // 出错、被中断或预算用尽时保存会话，下次用 --restore 继续
+    let state = state.suspend(&tracker, resumed.name, &monitor)?;
+    tracker.finish();
+
+    // 如果找到密码，保存到 potfile 和默认字典并返回成功
+    if let Some((password, _)) = &state.found {
+        remember_password(
+            &potfile_path,
+            fingerprint.as_deref(),
+            &default_dict_path,
+            password,
+            &monitor,
         );
+    }
+    let mut success = state.finish()?;
+    deliver(config, handler.as_ref(), &mut success, &monitor);
+    Ok(success)
+}
 
-        total_tested += result.total_tested;
-        total_elapsed += result.elapsed_secs;
+/// 找到密码后按配置解压和重新打包
+fn deliver(
+    config: &CrackConfig,
+    handler: &dyn ArchiveHandler,
+    success: &mut CrackSuccess,
+    monitor: &Monitor,
+) {
+    let archive_path = config.archive.as_path();
+    if let Some(options) = &config.extract {
+        let mut extractor = Extractor::new(options);
+        extract_with(
+            handler,
+            archive_path,
+            &success.password,
+            &mut extractor,
+            monitor,
+        );
+        success.extracted = Some(extractor.finish());
+    }
+    if let Some(options) = &config.rewrap {
+        let rewrapper = Rewrapper::new(options, &success.password);
+        success.rewrapped = rewrap_with(handler, archive_path, rewrapper, monitor);
+    }
+}
 
-        if let Some(pwd) = result.password {
-            found_password = Some(pwd);
+/// 重新打包，失败时删除不完整的新压缩包并给出警告
+pub(crate) fn rewrap_with(
+    handler: &dyn ArchiveHandler,
+    archive_path: &Path,
+    mut rewrapper: Rewrapper,
+    monitor: &Monitor,
+) -> Option<RewrapReport> {
+    let options = rewrapper.options();
+    monitor.message(&format!(
+        "正在重新打包到 {} ({}) ...",
+        options.output.display(),
+        options.describe()
+    ));
+    match handler.rewrap(archive_path, &mut rewrapper) {
+        Ok(()) => Some(rewrapper.finish()),
+        Err(e) => {
+            // 输出文件已存在时不是本次写入的，不能删除
+            if !matches!(&e, ArchiveError::Io(io) if io.kind() == io::ErrorKind::AlreadyExists) {
+                let _ = std::fs::remove_file(&options.output);
+            }
+            monitor.warning(&format!("重新打包失败: {e}"));
+            None
         }
     }
+}
+
+/// 用 `password` 解压，出错时只给出警告，已解压的文件和跳过的条目仍记录在 `extractor` 中
+pub(crate) fn extract_with(
+    handler: &dyn ArchiveHandler,
+    archive_path: &Path,
+    password: &str,
+    extractor: &mut Extractor,
+    monitor: &Monitor,
+) {
+    monitor.message(&format!("正在解压到 {} ...", extractor.dir().display()));
+    if let Err(e) = handler.extract(archive_path, password, extractor) {
+        monitor.warning(&format!("解压出错: {e}"));
+    }
+}
+
+/// 有基准测试结果时按验证条目的加密方式和验证方式给出速度，并估算暴力破解的耗时
+#[allow(clippy::cast_precision_loss)]
+fn report_estimate(
+    handler: &dyn ArchiveHandler,
+    archive_path: &Path,
+    targets: &[TargetFile],
+    bruteforce_total: u128,
+    monitor: &Monitor,
+) {
+    let Some(target) = targets.first() else {
+        return;
+    };
+    let Some((cipher, results)) =
+        bench::target_cipher(handler, archive_path, target).zip(BenchResults::load())
+    else {
+        return;
+    };
+    let Some(speed) = results.speed(cipher, target.verification) else {
+        return;
+    };
+    let eta = if bruteforce_total > 0 {
+        let eta = progress::format_eta(Some(bruteforce_total as f64 / speed));
+        format!("，暴力破解 {bruteforce_total} 个候选预计 {eta}")
+    } else {
+        String::new()
+    };
+    monitor.message(&format!(
+        "基准速度: {speed:.0} 次/秒（{}，{}）{eta}",
+        cipher.name(),
+        target.verification.label()
+    ));
+}
+
+/// 用于验证密码的条目：`--entry` 指定的条目和已知内容对应的条目，都未指定时自动选择
+///
+/// 条目按验证开销排序（已知内容、文件头、文本、仅校验和，同类中小文件在前），
+/// 大多数错误密码在第一个条目就被排除
+pub(crate) fn select_targets(
+    handler: &dyn ArchiveHandler,
+    config: &CrackConfig,
+) -> Result<Vec<TargetFile>, CrackError> {
+    let archive_path = config.archive.as_path();
+    let entries = handler.entries(archive_path)?;
+    if config.entries.is_empty() && config.expect.is_none() {
+        let target = pick_target(entries.iter().cloned()).ok_or_else(|| {
+            if entries.iter().any(|entry| entry.size > 0) {
+                CrackError::NotEncrypted
+            } else {
+                CrackError::NoRecognizableFile
+            }
+        })?;
+        return Ok(vec![target]);
+    }
 
-    // 第二阶段：暴力破解
-    if found_password.is_none() {
-        let (min_len, max_len) = match (args.length, args.max_length) {
-            (Some(len), None) => (len, len),
-            (None, Some(max)) => (args.min_length, max),
-            (Some(_), Some(_)) => return Err(CrackError::ConflictingLengthParams),
-            (None, None) => (1, 5),
+    let mut selected: Vec<&EntryInfo> = Vec::new();
+    for selector in &config.entries {
+        selected.extend(match_entries(&entries, selector)?);
+    }
+    let expect = match &config.expect {
+        Some(expect) => {
+            let entry = entries
+                .iter()
+                .find(|entry| entry.name == expect.entry)
+                .ok_or_else(|| CrackError::EntryNotFound(expect.entry.clone()))?;
+            selected.push(check_verifiable(entry)?);
+            Some((entry.index, &expect.verifier))
+        }
+        None => None,
+    };
+
+    selected.sort_by_key(|entry| entry.index);
+    selected.dedup_by_key(|entry| entry.index);
+    let mut targets: Vec<(u64, TargetFile)> = selected
+        .into_iter()
+        .map(|entry| {
+            let target = TargetFile::new(entry.index, entry.name.clone());
+            let target = match expect {
+                Some((index, verifier)) if index == entry.index => {
+                    target.with_verifier(verifier.clone())
+                }
+                _ => target,
+            };
+            (entry.size, target)
+        })
+        .collect();
+    targets.sort_by_key(|(size, target)| (target.verification, *size));
+    Ok(targets.into_iter().map(|(_, target)| target).collect())
+}
+
+/// `--entry` 选择的条目：完整名称、通配符（`*`、`?`）或索引
+///
+/// 通配符只选择能用于验证的条目（已加密的非空文件），名称和索引选择的条目不能用于验证时报错
+fn match_entries<'a>(
+    entries: &'a [EntryInfo],
+    selector: &str,
+) -> Result<Vec<&'a EntryInfo>, CrackError> {
+    let not_found = || CrackError::EntryNotFound(selector.to_string());
+    if let Some(entry) = entries.iter().find(|entry| entry.name == selector) {
+        return Ok(vec![check_verifiable(entry)?]);
+    }
+    if selector.contains(['*', '?']) {
+        let matched: Vec<_> = entries
+            .iter()
+            .filter(|entry| entry.size > 0 && entry.is_encrypted())
+            .filter(|entry| wildcard_match(selector, &entry.name))
+            .collect();
+        return if matched.is_empty() {
+            Err(not_found())
+        } else {
+            Ok(matched)
         };
+    }
+    let index: usize = selector.parse().map_err(|_| not_found())?;
+    let entry = entries
+        .iter()
+        .find(|entry| entry.index == index)
+        .ok_or_else(not_found)?;
+    Ok(vec![check_verifiable(entry)?])
+}
+
+/// 检查条目能否用于验证密码
+fn check_verifiable(entry: &EntryInfo) -> Result<&EntryInfo, CrackError> {
+    let reason = if entry.size == 0 {
+        "目录或空文件"
+    } else if entry.is_encrypted() {
+        return Ok(entry);
+    } else {
+        "未加密"
+    };
+    Err(CrackError::UnverifiableEntry(
+        entry.name.clone(),
+        reason.to_string(),
+    ))
+}
+
+/// 默认顺序：快速阶段，然后字典攻击和暴力破解
+fn default_stages(
+    ctx: &StageContext,
+    state: &mut RunState,
+    tracker: &SessionTracker,
+    partition: Option<&Partition>,
+    fingerprint: Option<&str>,
+    potfile_path: &Path,
+) -> Result<(), CrackError> {
+    let resumed = tracker.snapshot();
+    let slice = partition.map(|(slice, _)| slice);
+    let dictionary = resumed.stage <= SessionStage::Dictionary && ctx.config.stages.dictionary;
+    ctx.monitor
+        .progress()
+        .set_pending(local_pending(ctx, slice, dictionary));
+
+    // 恢复会话时，字典之前的快速阶段已经跑过；分片运行时只由第一个分片执行
+    if resumed.stage > SessionStage::Start {
+        ctx.monitor
+            .message(&format!("恢复会话 {}，跳过已完成的阶段", resumed.name));
+    } else if slice.is_none_or(|slice| slice.start == 0) {
+        quick_stages(ctx, state, fingerprint, potfile_path);
+    }
 
-        if min_len > max_len {
-            return Err(CrackError::InvalidLengthRange(min_len, max_len));
+    // 字典和暴力破解：本机执行，或作为协调进程分发给 worker
+    if !state.stopped() {
+        if let Some(serve) = &ctx.config.serve {
+            let mut error = None;
+            ctx.monitor.progress().set_pending(Pending::NONE);
+            state.run_stage(ctx, "分布式", |state| {
+                distributed_stage(ctx, state, serve).unwrap_or_else(|e| {
+                    error = Some(e);
+                    false
+                })
+            });
+            if let Some(e) = error {
+                return Err(e);
+            }
+        } else {
+            local_stages(ctx, state, tracker, &resumed, partition);
         }
+    }
+    Ok(())
+}
+
+/// 第三、四阶段：本机执行字典攻击和暴力破解
+fn local_stages(
+    ctx: &StageContext,
+    state: &mut RunState,
+    tracker: &SessionTracker,
+    resumed: &Session,
+    partition: Option<&Partition>,
+) {
+    let slice = partition.map(|(slice, _)| slice);
+
+    // 第三阶段：字典攻击（恢复暴力破解阶段的会话时已完成）
+    if resumed.stage <= SessionStage::Dictionary
+        && ctx.config.stages.dictionary
+        && !ctx.dict_paths.is_empty()
+        && slice.is_none_or(|slice| !slice.dictionary_range().is_empty())
+    {
+        ctx.monitor
+            .progress()
+            .set_pending(local_pending(ctx, slice, false));
+        state.run_stage(ctx, "字典", |state| {
+            let budget = ctx.budget.after(state.total_tested);
+            let ranges = partition.map(|(_, ranges)| ranges.as_slice());
+            dictionary_stage(ctx, state, tracker, ctx.dict_paths, ranges, budget)
+        });
+    }
+
+    // 第四阶段：暴力破解
+    if !state.stopped() && slice.is_none_or(|slice| !slice.bruteforce_range().is_empty()) {
+        ctx.monitor.progress().set_pending(Pending::NONE);
+        state.run_stage(ctx, "暴力破解", |state| {
+            let bruteforce = &ctx.config.bruteforce;
+            bruteforce_stage(
+                ctx,
+                state,
+                tracker,
+                &bruteforce.charsets,
+                (bruteforce.min_length, bruteforce.max_length),
+                slice.map(KeyspaceSlice::bruteforce_range),
+                ctx.budget.after(state.total_tested),
+            )
+        });
+    }
+}
+
+/// 默认顺序中字典之后还要执行的工作量：暴力破解部分的键空间，`dictionary` 表示字典阶段尚未执行
+fn local_pending(ctx: &StageContext, slice: Option<&KeyspaceSlice>, dictionary: bool) -> Pending {
+    let bruteforce = slice.map_or(ctx.bruteforce_total, |slice| {
+        let range = slice.bruteforce_range();
+        range.end - range.start
+    });
+    Pending {
+        candidates: bruteforce,
+        unknown: dictionary,
+    }
+}
+
+/// 字典攻击（从会话断点继续），返回是否因预算用尽提前结束
+fn dictionary_stage(
+    ctx: &StageContext,
+    state: &mut RunState,
+    tracker: &SessionTracker,
+    dict_paths: &[PathBuf],
+    ranges: Option<&[Range<u64>]>,
+    budget: Budget,
+) -> bool {
+    tracker.enter_stage(SessionStage::Dictionary);
+    let mut result = dictionary_attack(&DictionaryParams {
+        archive_path: ctx.archive_path,
+        dict_paths,
+        targets: ctx.targets,
+        file_count: ctx.file_count,
+        handler: ctx.handler,
+        resume: tracker.snapshot().dictionary_resume(),
+        session: Some(tracker),
+        ranges,
+        budget,
+        monitor: ctx.monitor,
+    });
+
+    // 找到密码时，最后一个字典即为密码来源
+    let source = result.sources.last().map(|s| s.source.clone());
+    state.dictionary_sources.append(&mut result.sources);
+    let exhausted = result.budget_exhausted;
+    state.absorb_wordlist(result, format!("字典 {}", source.unwrap_or_default()));
+    exhausted
+}
+
+/// 暴力破解（从会话断点继续），返回是否因预算用尽提前结束
+fn bruteforce_stage(
+    ctx: &StageContext,
+    state: &mut RunState,
+    tracker: &SessionTracker,
+    charsets: &[Charset],
+    (min_len, max_len): (u32, u32),
+    range: Option<Range<u128>>,
+    budget: Budget,
+) -> bool {
+    tracker.enter_stage(SessionStage::Bruteforce);
+    let result = bruteforce_attack(&BruteforceParams {
+        archive_path: ctx.archive_path,
+        charsets,
+        min_len,
+        max_len,
+        targets: ctx.targets,
+        file_count: ctx.file_count,
+        handler: ctx.handler,
+        resume: tracker.snapshot().bruteforce_resume(),
+        session: Some(tracker),
+        range,
+        budget,
+        monitor: ctx.monitor,
+    });
+    state.interrupted = result.interrupted;
+    state.absorb(
+        result.password,
+        result.total_tested,
+        result.elapsed_secs,
+        "暴力破解".to_string(),
+    );
+    result.budget_exhausted
+}
+
+/// 按攻击计划依次执行各阶段（恢复会话时跳过已完成的阶段）
+fn plan_stages(
+    ctx: &StageContext,
+    state: &mut RunState,
+    tracker: &SessionTracker,
+    plan: &Plan,
+    fingerprint: Option<&str>,
+    potfile_path: &Path,
+) {
+    let resumed = tracker.snapshot();
+    if resumed.plan_stage > 0 || resumed.stage > SessionStage::Start {
+        ctx.monitor.message(&format!(
+            "恢复会话 {}，从计划第 {} 阶段继续",
+            resumed.name,
+            resumed.plan_stage + 1
+        ));
+    }
 
-        if min_len == 0 {
-            return Err(CrackError::ZeroLength);
+    let count = plan.stages.len();
+    for (index, stage) in plan.stages.iter().enumerate().skip(resumed.plan_stage) {
+        let name = stage.attack.to_string();
+        // 整个运行的预算用尽后，剩余阶段记为未执行
+        if state.budget_exhausted {
+            state.stages.push(StageReport {
+                name,
+                status: StageStatus::Skipped,
+                tested: 0,
+                elapsed_secs: 0.0,
+                near_misses: 0,
+            });
+            continue;
+        }
+        if state.stopped() {
+            break;
         }
+        tracker.enter_plan_stage(index);
+        ctx.monitor
+            .progress()
+            .set_pending(plan_pending(&plan.stages[index + 1..]));
+        ctx.monitor
+            .message(&format!("\n=== 计划阶段 {}/{count}: {name} ===", index + 1));
 
-        let result = bruteforce_attack(&attack::bruteforce::BruteforceParams {
-            archive_path,
-            charsets: &args.charset,
-            min_len,
-            max_len,
-            target: &target,
-            file_count,
-            handler: handler.as_ref(),
+        state.run_stage(ctx, name, |state| {
+            let budget = stage
+                .budget()
+                .intersect(ctx.budget.after(state.total_tested));
+            plan_stage(
+                ctx,
+                state,
+                tracker,
+                &stage.attack,
+                budget,
+                fingerprint,
+                potfile_path,
+            )
         });
+    }
+}
 
-        total_tested += result.total_tested;
-        total_elapsed += result.elapsed_secs;
+/// 计划中剩余阶段的工作量：暴力破解阶段的键空间之和，字典阶段的大小未知
+fn plan_pending(stages: &[PlanStage]) -> Pending {
+    stages
+        .iter()
+        .fold(Pending::NONE, |pending, stage| match &stage.attack {
+            StageAttack::Bruteforce {
+                charset,
+                min_length,
+                max_length,
+            } => {
+                let (_, chars) = charset::get_combined_charset(charset);
+                // 加载计划时已检查键空间不超出 u128
+                let keyspace =
+                    bruteforce_keyspace(chars.len(), *min_length, *max_length).unwrap_or(u128::MAX);
+                Pending {
+                    candidates: pending.candidates.saturating_add(keyspace),
+                    ..pending
+                }
+            }
+            StageAttack::Dictionary { .. } => Pending {
+                unknown: true,
+                ..pending
+            },
+            StageAttack::Potfile | StageAttack::Harvest { .. } => pending,
+        })
+}
 
-        if let Some(pwd) = result.password {
-            found_password = Some(pwd);
+/// 执行计划中的一个阶段，返回是否因预算用尽提前结束
+fn plan_stage(
+    ctx: &StageContext,
+    state: &mut RunState,
+    tracker: &SessionTracker,
+    attack: &StageAttack,
+    budget: Budget,
+    fingerprint: Option<&str>,
+    potfile_path: &Path,
+) -> bool {
+    match attack {
+        StageAttack::Potfile => {
+            let tested = state.total_tested;
+            potfile_lookup(ctx, state, fingerprint, potfile_path);
+            !state.stopped()
+                && ctx.config.stages.potfile
+                && potfile_cracked(
+                    ctx,
+                    state,
+                    potfile_path,
+                    budget.after(state.total_tested - tested),
+                )
+        }
+        StageAttack::Harvest { neighbors, depth } => {
+            harvest_stage(ctx, state, neighbors.then_some(*depth), true, budget)
         }
+        StageAttack::Dictionary { paths, builtin } => {
+            let dict_paths =
+                stage_dictionaries(ctx.builtin_dictionary, ctx.dict_paths, paths, *builtin);
+            dictionary_stage(ctx, state, tracker, &dict_paths, None, budget)
+        }
+        StageAttack::Bruteforce {
+            charset,
+            min_length,
+            max_length,
+        } => bruteforce_stage(
+            ctx,
+            state,
+            tracker,
+            charset,
+            (*min_length, *max_length),
+            None,
+            budget,
+        ),
     }
+}
 
-    // 如果找到密码，保存到默认字典并返回成功
-    found_password.map_or(
-        Err(CrackError::NotFound(CrackFailure {
-            total_tested,
-            elapsed_secs: total_elapsed,
-        })),
-        |password| {
-            Ok(CrackSuccess {
-                password,
-                total_tested,
-                elapsed_secs: total_elapsed,
-            })
+/// 本次运行使用的字典：内置字典总是第一个；按键空间分片时去掉内置字典，
+/// 因为它随机器不同且会自动追加密码，各机器算出的分片会对不上
+pub(crate) fn run_dictionaries(builtin_dictionary: &Path, config: &CrackConfig) -> Vec<PathBuf> {
+    let mut dict_paths = resolve_dictionaries(builtin_dictionary, &config.dictionaries);
+    if config.keyspace.is_some() {
+        dict_paths.retain(|path| path != builtin_dictionary);
+    }
+    dict_paths
+}
+
+/// 计划中字典阶段使用的字典：`paths` 为空时使用命令行指定的字典，`builtin` 为假时去掉内置字典
+pub(crate) fn stage_dictionaries(
+    builtin_dictionary: &Path,
+    dict_paths: &[PathBuf],
+    paths: &[String],
+    builtin: bool,
+) -> Vec<PathBuf> {
+    let mut dict_paths = if paths.is_empty() {
+        dict_paths.to_vec()
+    } else {
+        resolve_dictionaries(builtin_dictionary, paths)
+    };
+    if !builtin {
+        dict_paths.retain(|path| path != builtin_dictionary);
+    }
+    dict_paths
+}
+
+/// 作为协调进程把字典和暴力破解的键空间分发给 worker，返回是否因预算用尽提前结束
+fn distributed_stage(
+    ctx: &StageContext,
+    state: &mut RunState,
+    serve: &ServeConfig,
+) -> Result<bool, CrackError> {
+    let bruteforce = &ctx.config.bruteforce;
+    let dict_paths = if ctx.config.stages.dictionary {
+        ctx.dict_paths
+    } else {
+        &[]
+    };
+    // 字典条目数未知，只有跳过字典时才能计算完成比例
+    if dict_paths.is_empty() {
+        ctx.monitor.progress().set_keyspace(ctx.bruteforce_total, 0);
+    }
+
+    let token = serve.token.as_deref().filter(|token| !token.is_empty());
+    if serve.require_token && token.is_none() {
+        return Err(CrackError::InvalidToken(
+            "协调进程要求令牌，但未提供令牌".to_string(),
+        ));
+    }
+
+    let listener =
+        TcpListener::bind(&serve.addr).map_err(|e| CrackError::Network(e.to_string()))?;
+    let result = distributed::coordinate(
+        &listener,
+        &distributed::CoordinatorParams {
+            token,
+            archive_path: ctx.archive_path,
+            dict_paths,
+            charsets: &bruteforce.charsets,
+            min_len: bruteforce.min_length,
+            max_len: bruteforce.max_length,
+            bruteforce_total: ctx.bruteforce_total,
+            targets: ctx.targets,
+            handler: ctx.handler,
+            chunk_size: serve.chunk_size,
+            lease_timeout: serve.lease_timeout,
+            budget: ctx.budget.after(state.total_tested),
+            monitor: ctx.monitor,
         },
     )
+    .map_err(|e| CrackError::Network(e.to_string()))?;
+
+    state.interrupted = result.interrupted;
+    let (password, worker) = result.found.unzip();
+    state.absorb(
+        password,
+        result.total_tested,
+        result.elapsed_secs,
+        format!("worker {}", worker.unwrap_or_default()),
+    );
+    Ok(result.budget_exhausted)
+}
+
+/// 键空间分片及其在各字典中的条目范围
+type Partition = (KeyspaceSlice, Vec<Range<u64>>);
+
+/// 按 `--skip`/`--limit`/`--part` 计算本次运行的键空间分片及其在各字典中的范围
+fn partition_keyspace(
+    config: &CrackConfig,
+    dict_paths: &[PathBuf],
+    bruteforce_total: u128,
+    monitor: &Monitor,
+) -> Option<Partition> {
+    let selection = config.keyspace?;
+    monitor.message("键空间分片运行不使用内置字典（各机器的内置字典内容不同，分片会重叠或遗漏）");
+
+    // 与字典攻击一致：跳过字典时字典部分为空
+    let counts = if config.stages.dictionary {
+        monitor.message("统计字典条目数...");
+        count_dictionary_entries(dict_paths)
+    } else {
+        vec![0; dict_paths.len()]
+    };
+    let dictionary_total = counts.iter().sum();
+    let slice = selection.slice(dictionary_total, bruteforce_total);
+    let part = match selection {
+        KeyspaceSelection::Part(part) => format!(" ({part})"),
+        KeyspaceSelection::Range { .. } => String::new(),
+    };
+    monitor.message(&format!(
+        "键空间: 字典 {dictionary_total} + 暴力破解 {bruteforce_total} = {}\n本分片{part}: [{}, {})，共 {} 个候选\n",
+        slice.total,
+        slice.start,
+        slice.end,
+        slice.len()
+    ));
+
+    let ranges = split_dictionary_range(&counts, &slice.dictionary_range());
+    Some((slice, ranges))
+}
+
+/// 加载攻击计划并检查各暴力破解阶段的参数
+pub(crate) fn load_plan(name: &str) -> Result<Plan, CrackError> {
+    let plan =
+        Plan::load(name).map_err(|e| CrackError::PlanLoad(name.to_string(), e.to_string()))?;
+    for stage in &plan.stages {
+        if let StageAttack::Bruteforce {
+            charset,
+            min_length,
+            max_length,
+        } = &stage.attack
+        {
+            if charset.is_empty() {
+                return Err(CrackError::PlanLoad(
+                    name.to_string(),
+                    "暴力破解阶段的字符集为空".to_string(),
+                ));
+            }
+            if *min_length == 0 {
+                return Err(CrackError::ZeroLength);
+            }
+            if min_length > max_length {
+                return Err(CrackError::InvalidLengthRange(*min_length, *max_length));
+            }
+            let (_, chars) = charset::get_combined_charset(charset);
+            if bruteforce_keyspace(chars.len(), *min_length, *max_length).is_none() {
+                return Err(CrackError::KeyspaceTooLarge(chars.len(), *max_length));
+            }
+        }
+    }
+    Ok(plan)
+}
+
+/// 创建新会话，或按 `--restore` 加载已保存的会话
+fn open_session(config: &CrackConfig) -> Result<Session, CrackError> {
+    if let Some(name) = &config.restore {
+        return Session::load(name)
+            .map_err(|e| CrackError::SessionLoad(name.clone(), e.to_string()));
+    }
+    let name = config
+        .session
+        .clone()
+        .unwrap_or_else(|| Session::default_name(&config.archive));
+    Ok(Session::new(&name, config))
+}
+
+/// 字典之前的快速阶段：potfile、邻近文件、元数据候选词、已破解密码
+fn quick_stages(
+    ctx: &StageContext,
+    state: &mut RunState,
+    fingerprint: Option<&str>,
+    potfile_path: &Path,
+) {
+    let stages = &ctx.config.stages;
+
+    // 第零阶段：potfile 中已记录的本压缩包（或其副本）的密码
+    if fingerprint.is_some() {
+        state.run_stage(ctx, "potfile", |state| {
+            potfile_lookup(ctx, state, fingerprint, potfile_path);
+            false
+        });
+    }
+
+    // 第一、二阶段：邻近文件中的密码线索（需显式开启）和压缩包元数据中的候选词
+    let (neighbors, metadata) = (stages.neighbors, stages.metadata);
+    if !state.stopped() && (neighbors.is_some() || metadata) {
+        let name = harvest_stage_name(neighbors.is_some(), metadata);
+        state.run_stage(ctx, name, |state| {
+            let budget = ctx.budget.after(state.total_tested);
+            harvest_stage(ctx, state, neighbors, metadata, budget)
+        });
+    }
+
+    // 第三阶段：字典攻击，先尝试 potfile 中所有已破解过的密码
+    if !state.stopped() && stages.dictionary && stages.potfile {
+        state.run_stage(ctx, "已破解密码", |state| {
+            let budget = ctx.budget.after(state.total_tested);
+            potfile_cracked(ctx, state, potfile_path, budget)
+        });
+    }
+}
+
+/// 默认顺序中邻近文件和元数据候选词阶段的名称
+pub(crate) const fn harvest_stage_name(neighbors: bool, metadata: bool) -> &'static str {
+    match (neighbors, metadata) {
+        (true, true) => "邻近文件 + 元数据候选词",
+        (true, false) => "邻近文件",
+        _ => "元数据候选词",
+    }
+}
+
+/// 尝试 potfile 中已记录的本压缩包（或其副本）的密码
+fn potfile_lookup(
+    ctx: &StageContext,
+    state: &mut RunState,
+    fingerprint: Option<&str>,
+    potfile_path: &Path,
+) {
+    if let Some(fp) = fingerprint
+        && let Some(pwd) = potfile::lookup(potfile_path, fp)
+    {
+        let start = Instant::now();
+        let outcome = ctx.handler.try_targets(ctx.archive_path, &pwd, ctx.targets);
+        let hit = ctx.monitor.record(&pwd, &outcome);
+        if hit {
+            ctx.monitor.message("在 potfile 中找到该压缩包的密码");
+        }
+        let elapsed = start.elapsed().as_secs_f64();
+        let source = format!("potfile {}", potfile_path.display());
+        state.absorb(hit.then_some(pwd), 1, elapsed, source);
+    }
+}
+
+/// 尝试 potfile 中所有已破解过的密码，返回是否因预算用尽提前结束
+fn potfile_cracked(
+    ctx: &StageContext,
+    state: &mut RunState,
+    potfile_path: &Path,
+    budget: Budget,
+) -> bool {
+    let cracked = potfile::passwords(potfile_path);
+    let result = wordlist_stage(ctx, "potfile 中已破解的密码", &cracked, budget);
+    let exhausted = result.budget_exhausted;
+    state.absorb_wordlist(result, format!("potfile {}", potfile_path.display()));
+    exhausted
+}
+
+/// 邻近文件（`neighbors` 为递归层数）和压缩包元数据中的候选词，返回是否因预算用尽提前结束
+fn harvest_stage(
+    ctx: &StageContext,
+    state: &mut RunState,
+    neighbors: Option<usize>,
+    metadata: bool,
+    budget: Budget,
+) -> bool {
+    let tested = state.total_tested;
+    let mut exhausted = false;
+
+    if let Some(depth) = neighbors {
+        let (result, source) = neighbors_attack(ctx, depth, budget);
+        exhausted = result.budget_exhausted;
+        state.absorb_wordlist(result, source);
+    }
+
+    if !state.stopped() && !exhausted && metadata {
+        let words = harvest_candidates(ctx.archive_path, ctx.handler);
+        let budget = budget.after(state.total_tested - tested);
+        let result = wordlist_stage(ctx, "压缩包元数据", &words, budget);
+        exhausted = result.budget_exhausted;
+        state.absorb_wordlist(result, "压缩包元数据".to_string());
+    }
+    exhausted
+}
+
+/// 各攻击阶段共享的上下文
+struct StageContext<'a> {
+    config: &'a CrackConfig,
+    /// 压缩包路径
+    archive_path: &'a Path,
+    targets: &'a [TargetFile],
+    file_count: usize,
+    handler: &'a dyn ArchiveHandler,
+    dict_paths: &'a [PathBuf],
+    /// 内置字典路径
+    builtin_dictionary: &'a Path,
+    /// 暴力破解部分的键空间大小
+    bruteforce_total: u128,
+    /// 整个运行的预算
+    budget: Budget,
+    monitor: &'a Monitor<'a>,
+}
+
+/// 各阶段累计的破解状态
+#[derive(Default)]
+struct RunState {
+    /// 找到的密码及其来源
+    found: Option<(String, String)>,
+    total_tested: u64,
+    total_elapsed: f64,
+    dictionary_sources: Vec<SourceStats>,
+    /// 是否被 Ctrl-C 中断
+    interrupted: bool,
+    /// 整个运行的预算是否用尽
+    budget_exhausted: bool,
+    /// 中止运行的错误
+    fault: Option<ArchiveError>,
+    near_misses: u64,
+    keyspace: Option<KeyspaceSlice>,
+    stages: Vec<StageReport>,
+}
+
+impl RunState {
+    /// 累加一个阶段的统计，找到密码时记录来源
+    fn absorb(&mut self, password: Option<String>, tested: u64, elapsed_secs: f64, source: String) {
+        self.total_tested += tested;
+        self.total_elapsed += elapsed_secs;
+        if let Some(pwd) = password {
+            self.found = Some((pwd, source));
+        }
+    }
+
+    /// 累加字典类阶段的结果
+    fn absorb_wordlist(&mut self, result: DictionaryResult, source: String) {
+        self.interrupted |= result.interrupted;
+        self.absorb(
+            result.password,
+            result.total_tested,
+            result.elapsed_secs,
+            source,
+        );
+    }
+
+    /// 是否已找到密码
+    const fn done(&self) -> bool {
+        self.found.is_some()
+    }
+
+    /// 是否应停止后续阶段（已找到密码、被中断、出错或预算用尽）
+    const fn stopped(&self) -> bool {
+        self.done() || self.interrupted || self.fault.is_some() || self.budget_exhausted
+    }
+
+    /// 执行一个阶段并记录其统计，`run` 返回该阶段是否因预算用尽提前结束
+    fn run_stage(
+        &mut self,
+        ctx: &StageContext,
+        name: impl Into<String>,
+        run: impl FnOnce(&mut Self) -> bool,
+    ) {
+        let name = name.into();
+        let monitor = ctx.monitor;
+        monitor.progress().begin_stage(&name);
+        monitor.observer().stage_started(&name);
+        let (tested, elapsed) = (self.total_tested, self.total_elapsed);
+        let near_misses = monitor.near_misses();
+        let cut_short = run(self);
+        self.fault = monitor.fault();
+        self.budget_exhausted = !self.done()
+            && !self.interrupted
+            && self.fault.is_none()
+            && ctx.budget.exhausted(self.total_tested);
+        self.near_misses = monitor.near_misses();
+
+        let status = if self.done() {
+            StageStatus::Found
+        } else if self.fault.is_some() {
+            StageStatus::Failed
+        } else if self.interrupted {
+            StageStatus::Interrupted
+        } else if cut_short {
+            StageStatus::CutShort
+        } else {
+            StageStatus::Completed
+        };
+        if self.budget_exhausted {
+            monitor.message("⏱️ 运行预算用尽，停止");
+        } else if cut_short {
+            monitor.message("⏱️ 阶段预算用尽，进入下一阶段");
+        }
+        let report = StageReport {
+            name,
+            status,
+            tested: self.total_tested - tested,
+            elapsed_secs: self.total_elapsed - elapsed,
+            near_misses: self.near_misses - near_misses,
+        };
+        monitor.observer().stage_finished(&report);
+        if status == StageStatus::Found
+            && let Some((password, source)) = &self.found
+        {
+            monitor.observer().password_found(password, source);
+        }
+        self.stages.push(report);
+    }
+
+    /// 未找到密码且出错（如网络存储断开）、被中断或预算用尽时保存会话并返回对应的错误
+    fn suspend(
+        mut self,
+        tracker: &SessionTracker,
+        session: String,
+        monitor: &Monitor,
+    ) -> Result<Self, CrackError> {
+        if self.done() || !(self.interrupted || self.budget_exhausted || self.fault.is_some()) {
+            return Ok(self);
+        }
+        if let Err(e) = tracker.save() {
+            monitor.warning(&format!("无法保存会话: {e}"));
+        }
+        let fault = self.fault.take();
+        let interrupted = self.interrupted;
+        let failure = Box::new(self.into_failure());
+        Err(match fault {
+            Some(e) => CrackError::Aborted(session, e, failure),
+            None if interrupted => CrackError::Interrupted(session, failure),
+            None => CrackError::BudgetExhausted(session, failure),
+        })
+    }
+
+    /// 未找到密码时的统计
+    fn into_failure(self) -> CrackFailure {
+        CrackFailure {
+            total_tested: self.total_tested,
+            elapsed_secs: self.total_elapsed,
+            dictionary_sources: self.dictionary_sources,
+            keyspace: self.keyspace,
+            stages: self.stages,
+            near_misses: self.near_misses,
+        }
+    }
+
+    /// 生成最终结果
+    fn finish(self) -> CrackResult {
+        match self.found {
+            Some((password, source)) => Ok(CrackSuccess {
+                password,
+                source,
+                total_tested: self.total_tested,
+                elapsed_secs: self.total_elapsed,
+                dictionary_sources: self.dictionary_sources,
+                keyspace: self.keyspace,
+                stages: self.stages,
+                near_misses: self.near_misses,
+                extracted: None,
+                rewrapped: None,
+            }),
+            None => Err(CrackError::NotFound(Box::new(self.into_failure()))),
+        }
+    }
+}
+
+/// 对内存候选列表执行攻击，列表为空时跳过
+fn wordlist_stage(
+    ctx: &StageContext,
+    label: &str,
+    words: &[String],
+    budget: Budget,
+) -> DictionaryResult {
+    if words.is_empty() {
+        return DictionaryResult {
+            password: None,
+            total_tested: 0,
+            elapsed_secs: 0.0,
+            sources: Vec::new(),
+            interrupted: false,
+            budget_exhausted: false,
+        };
+    }
+    wordlist_attack(&WordlistParams {
+        source: label,
+        passwords: words,
+        archive_path: ctx.archive_path,
+        targets: ctx.targets,
+        file_count: ctx.file_count,
+        handler: ctx.handler,
+        budget,
+        monitor: ctx.monitor,
+    })
+}
+
+/// 把找到的密码写入 potfile（按指纹）并追加到默认字典
+fn remember_password(
+    potfile_path: &Path,
+    fingerprint: Option<&str>,
+    dict_path: &Path,
+    password: &str,
+    monitor: &Monitor,
+) {
+    if let Some(fp) = fingerprint
+        && let Err(e) = potfile::record(potfile_path, fp, password)
+    {
+        monitor.warning(&format!("无法写入 potfile: {e}"));
+    }
+    if let Err(e) = append_to_dictionary(dict_path, password) {
+        monitor.warning(&format!("无法写入内置字典: {e}"));
+    }
+}
+
+/// 邻近文件阶段，同时返回提供密码的文件（未找到时为空）
+fn neighbors_attack(
+    ctx: &StageContext,
+    depth: usize,
+    budget: Budget,
+) -> (DictionaryResult, String) {
+    let harvested = harvest_neighbors(ctx.archive_path, depth);
+    let words: Vec<String> = harvested.iter().map(|h| h.word.clone()).collect();
+    let result = wordlist_stage(ctx, "邻近文件", &words, budget);
+
+    let source = result
+        .password
+        .as_ref()
+        .and_then(|pwd| harvested.iter().find(|h| &h.word == pwd))
+        .map(|h| h.source.clone())
+        .unwrap_or_default();
+    (result, source)
 }
@@ -1,15 +1,74 @@
-use archive_cracker::{Args, CrackError, crack_archive};
+use archive_cracker::attack::dictionary::SourceStats;
+use archive_cracker::bench::run_bench;
+use archive_cracker::cli::{Secrets, read_new_password, read_token, restore_session_name};
+use archive_cracker::distributed::run_worker;
+use archive_cracker::estimate::estimate_run;
+use archive_cracker::extract::ExtractReport;
+use archive_cracker::keyspace::KeyspaceSlice;
+use archive_cracker::observer::{ConsoleObserver, CrackControl, Monitor};
+use archive_cracker::per_entry::{EntryReport, crack_entries};
+use archive_cracker::progress;
+use archive_cracker::rewrap::RewrapReport;
+use archive_cracker::session::install_interrupt_handler;
+use archive_cracker::{
+    Args, CrackConfig, CrackError, CrackFailure, StageReport, crack_archive_with,
+};
 use clap::Parser;
+use std::time::Duration;
 
 fn main() {
-    let args = Args::parse();
+    let control = CrackControl::new();
+    // 第二次 Ctrl-C 直接退出
+    if let Err(e) = install_interrupt_handler(&control, ConsoleObserver, || std::process::exit(130))
+    {
+        println!("⚠️ 无法安装 Ctrl-C 处理器，中断后将无法继续: {e}");
+    }
+
+    // 恢复会话时配置从会话文件读取
+    let mut dry_run = false;
+    let config = if let Some(name) = restore_session_name(std::env::args().skip(1)) {
+        CrackConfig::from_session(&name).and_then(with_secrets)
+    } else {
+        let args = Args::parse();
+        // worker 模式：压缩包和任务由协调进程下发
+        if let Some(addr) = &args.worker {
+            let token = args.token.then(read_token).transpose().unwrap_or_else(|e| {
+                println!("❌ 错误: {e}");
+                std::process::exit(1);
+            });
+            run_worker_mode(addr, token.as_deref(), control);
+            return;
+        }
+        if args.bench {
+            run_bench_mode(args.bench_time, control);
+            return;
+        }
+        dry_run = args.dry_run;
+        read_secrets(&args).and_then(|secrets| args.to_config(secrets))
+    };
+    let config = config.unwrap_or_else(|e| {
+        println!("❌ 错误: {e}");
+        std::process::exit(1);
+    });
 
     println!("=== Archive Cracker ===");
-    println!("目标文件: {}", args.archive_path);
+    println!("目标文件: {}", config.archive.display());
     println!();
+    if dry_run {
+        run_dry_run(&config, control);
+        return;
+    }
+    if progress::install_status_key() {
+        println!("提示: 运行中按回车键查看当前进度");
+    }
+
+    if config.per_entry {
+        run_per_entry_mode(&config, &control);
+        return;
+    }
 
     // 调用库函数执行破解
-    let result = crack_archive(&args);
+    let result = crack_archive_with(&config, &ConsoleObserver, &control);
 
     // 输出结果
     println!();
@@ -18,19 +77,36 @@ fn main() {
     match result {
         Ok(success) => {
             println!("✅ 密码找到: {}", success.password);
+            println!("密码来源: {}", success.source);
             println!("密码长度: {}", success.password.len());
             println!("总耗时: {:.2} 秒", success.elapsed_secs);
             println!("已测试: {} 个密码", success.total_tested);
             println!("平均速度: {:.0} 次/秒", success.speed());
+            print_near_misses(success.near_misses);
+            print_dictionary_sources(&success.dictionary_sources);
+            print_keyspace(success.keyspace.as_ref());
+            print_extracted(&config, success.extracted.as_ref());
+            print_rewrapped(success.rewrapped.as_ref());
         }
         Err(e) => match e {
             CrackError::NotFound(failure) => {
                 println!("❌ 未找到密码");
-                println!("总耗时: {:.2} 秒", failure.elapsed_secs);
-                println!("已测试: {} 个密码", failure.total_tested);
-                if failure.elapsed_secs > 0.0 {
-                    println!("平均速度: {:.0} 次/秒", failure.speed());
-                }
+                print_failure(&failure);
+            }
+            CrackError::Interrupted(name, failure) => {
+                println!("⏸️ 已中断");
+                print_failure(&failure);
+                println!("会话已保存，使用 --restore {name} 继续");
+            }
+            CrackError::Aborted(name, error, failure) => {
+                println!("❌ 已中止: {error}");
+                print_failure(&failure);
+                println!("会话已保存，问题解决后使用 --restore {name} 继续");
+            }
+            CrackError::BudgetExhausted(name, failure) => {
+                println!("⏱️ 预算用尽，未找到密码");
+                print_failure(&failure);
+                println!("会话已保存，使用 --restore {name} 继续");
             }
             _ => {
                 println!("❌ 错误: {e}");
@@ -38,3 +114,291 @@ fn main() {
         },
     }
 }
+
+/// 读取 `--token` 和 `--new-password` 需要的机密（环境变量或终端输入）
+fn read_secrets(args: &Args) -> Result<Secrets, CrackError> {
+    Ok(Secrets {
+        token: (args.serve.is_some() && args.token)
+            .then(read_token)
+            .transpose()?,
+        new_password: (args.rewrap.is_some() && args.new_password)
+            .then(read_new_password)
+            .transpose()?,
+    })
+}
+
+/// 恢复的会话不保存重新打包的新密码和协调进程的令牌，需要时重新读取
+fn with_secrets(mut config: CrackConfig) -> Result<CrackConfig, CrackError> {
+    if let Some(rewrap) = &mut config.rewrap
+        && rewrap.encryption.is_some()
+    {
+        println!("会话需要用新密码重新打包: {}", rewrap.output.display());
+        rewrap.password = Some(read_new_password()?);
+    }
+    if let Some(serve) = &mut config.serve
+        && serve.require_token
+    {
+        println!("会话的协调进程要求 worker 提供令牌");
+        serve.token = Some(read_token()?);
+    }
+    Ok(config)
+}
+
+/// 运行 worker 并输出统计
+fn run_worker_mode(addr: &str, token: Option<&str>, control: CrackControl) {
+    println!("=== Archive Cracker (worker) ===");
+    match run_worker(addr, token, &Monitor::new(&ConsoleObserver, control)) {
+        Ok(summary) => {
+            println!();
+            println!("=== worker 统计 ===");
+            if let Some(password) = &summary.password {
+                println!("✅ 本机找到密码: {password}");
+            }
+            println!("完成任务块: {} 个", summary.chunks_done);
+            println!("已测试: {} 个密码", summary.total_tested);
+            println!("总耗时: {:.2} 秒", summary.elapsed_secs);
+        }
+        Err(e) => {
+            println!("❌ worker 出错: {e}");
+            std::process::exit(1);
+        }
+    }
+}
+
+/// 运行基准测试并保存结果
+fn run_bench_mode(duration: Duration, control: CrackControl) {
+    println!("=== Archive Cracker (基准测试) ===");
+    println!(
+        "线程数: {}，每项测量 {:.0} 秒",
+        rayon::current_num_threads(),
+        duration.as_secs_f64()
+    );
+    println!();
+    let monitor = Monitor::new(&ConsoleObserver, control);
+    match run_bench(duration, &monitor) {
+        Ok(results) if monitor.cancelled() => {
+            println!(
+                "⏸️ 已中断，完成 {} 项测量，结果未保存",
+                results.measurements.len()
+            );
+        }
+        Ok(results) => match results.save() {
+            Ok(path) => println!("\n✅ 结果已保存到 {}，运行前将据此估算耗时", path.display()),
+            Err(e) => println!("\n⚠️ 无法保存结果: {e}"),
+        },
+        Err(e) => {
+            println!("❌ 基准测试出错: {e}");
+            std::process::exit(1);
+        }
+    }
+}
+
+/// 只估算各阶段的键空间和耗时，不破解
+fn run_dry_run(config: &CrackConfig, control: CrackControl) {
+    let monitor = Monitor::new(&ConsoleObserver, control);
+    let estimate = estimate_run(config, &monitor).unwrap_or_else(|e| {
+        println!("❌ 错误: {e}");
+        std::process::exit(1);
+    });
+    println!();
+    println!("=== 运行前估算 ===");
+    println!(
+        "验证条目: {} ({})",
+        estimate.target.name,
+        estimate.target.verification.label()
+    );
+    if let Some(cipher) = estimate.cipher {
+        println!("加密方式: {}", cipher.name());
+    }
+    match estimate.speed {
+        Some((speed, source)) => println!("速度: {speed:.0} 次/秒（{}）", source.label()),
+        None => println!("速度: 未知"),
+    }
+    println!("攻击阶段:");
+    for (index, stage) in estimate.stages.iter().enumerate() {
+        let limited = if stage.limited() {
+            format!("，预算内测试 {} 个", stage.candidates)
+        } else {
+            String::new()
+        };
+        let over = if estimate.exceeds_horizon(stage) {
+            "  ⚠️ 超过时限"
+        } else {
+            ""
+        };
+        println!(
+            "  {}. {} ({}): 键空间 {}{limited}，预计 {}{over}",
+            index + 1,
+            stage.name,
+            stage.detail,
+            stage.keyspace,
+            progress::format_eta(stage.eta_secs)
+        );
+    }
+    println!(
+        "合计: {} 个候选，预计 {}",
+        estimate.total_candidates(),
+        progress::format_eta(estimate.total_eta_secs())
+    );
+    if let Some(horizon) = estimate.horizon {
+        let horizon = progress::format_eta(Some(horizon.as_secs_f64()));
+        match estimate.over_horizon().count() {
+            0 => println!("✅ 各阶段预计耗时都在时限 {horizon} 以内"),
+            count => println!(
+                "⚠️ {count} 个阶段超过时限 {horizon}，正式运行时将拒绝开始；\
+                 可缩小字符集或长度，或用 --max-time/--max-candidates、计划中的阶段预算限制"
+            ),
+        }
+    }
+}
+
+/// 按条目破解并输出每个条目的密码
+fn run_per_entry_mode(config: &CrackConfig, control: &CrackControl) {
+    let result = crack_entries(config, &ConsoleObserver, control);
+    println!();
+    println!("=== 最终结果 ===");
+    match result {
+        Ok(report) => {
+            print_entry_report(&report);
+            print_extracted(config, report.extracted.as_ref());
+            print_rewrapped(report.rewrapped.as_ref());
+        }
+        Err(e) => println!("❌ 错误: {e}"),
+    }
+}
+
+/// 输出按条目破解的密码表
+fn print_entry_report(report: &EntryReport) {
+    let passwords = report.passwords();
+    let unsolved = report.unsolved().count();
+    if passwords.is_empty() {
+        println!("❌ 未找到密码");
+    } else {
+        println!(
+            "✅ 找到 {} 个密码: {}",
+            passwords.len(),
+            passwords.join(", ")
+        );
+    }
+    println!("条目密码:");
+    for entry in &report.entries {
+        let password = entry.password.as_deref().map_or_else(
+            || "❌ 未找到".to_string(),
+            |password| format!("{password:?}"),
+        );
+        println!("  {} ({}): {password}", entry.name, entry.encryption);
+    }
+    if unsolved > 0 {
+        println!("未找到密码的条目: {unsolved} 个");
+    }
+    println!("破解组数: {}", report.groups_cracked);
+    println!("总耗时: {:.2} 秒", report.elapsed_secs);
+    println!("已测试: {} 个密码", report.total_tested);
+    if let Some(stopped) = &report.stopped {
+        println!("⏸️ 提前结束: {stopped}");
+        println!("重新运行相同的命令即从中断的组继续（已找到的密码会先尝试）");
+    }
+}
+
+/// 输出解压结果和未解压的条目
+fn print_extracted(config: &CrackConfig, report: Option<&ExtractReport>) {
+    let (Some(options), Some(report)) = (&config.extract, report) else {
+        return;
+    };
+    println!(
+        "已解压: {} 个文件 ({} 字节) 到 {}",
+        report.files,
+        report.bytes,
+        options.dir.display()
+    );
+    if !report.skipped.is_empty() {
+        println!("未解压: {} 个条目", report.skipped.len());
+        for (name, reason) in &report.skipped {
+            println!("  {name}: {reason}");
+        }
+    }
+}
+
+/// 输出重新打包的结果
+fn print_rewrapped(report: Option<&RewrapReport>) {
+    if let Some(report) = report {
+        println!(
+            "已重新打包: {} 个条目 ({} 字节) 到 {}",
+            report.entries,
+            report.bytes,
+            report.output.display()
+        );
+        if !report.recompressed.is_empty() {
+            println!(
+                "改用 Deflate 压缩: {} 个条目（原压缩方法无法写入）",
+                report.recompressed.len()
+            );
+            for (name, method) in &report.recompressed {
+                println!("  {name}: {method}");
+            }
+        }
+    }
+}
+
+/// 输出未找到密码时的统计
+fn print_failure(failure: &CrackFailure) {
+    println!("总耗时: {:.2} 秒", failure.elapsed_secs);
+    println!("已测试: {} 个密码", failure.total_tested);
+    if failure.elapsed_secs > 0.0 {
+        println!("平均速度: {:.0} 次/秒", failure.speed());
+    }
+    print_near_misses(failure.near_misses);
+    print_dictionary_sources(&failure.dictionary_sources);
+    print_keyspace(failure.keyspace.as_ref());
+    print_stages(&failure.stages);
+}
+
+/// 输出近似匹配数量（通过快速检查但内容检查未通过的候选）
+fn print_near_misses(count: u64) {
+    if count > 0 {
+        println!("近似匹配: {count} 个（通过快速检查但内容不符）");
+    }
+}
+
+/// 输出各攻击阶段的状态和统计
+fn print_stages(stages: &[StageReport]) {
+    if stages.is_empty() {
+        return;
+    }
+    println!("攻击阶段:");
+    for stage in stages {
+        let near_misses = if stage.near_misses > 0 {
+            format!(", 近似匹配 {} 个", stage.near_misses)
+        } else {
+            String::new()
+        };
+        println!(
+            "  {}: {}, 测试 {} 个, 耗时 {:.2} 秒{near_misses}",
+            stage.name, stage.status, stage.tested, stage.elapsed_secs
+        );
+    }
+}
+
+/// 输出本次运行负责的键空间分片，便于事后核对覆盖范围
+fn print_keyspace(slice: Option<&KeyspaceSlice>) {
+    if let Some(slice) = slice {
+        println!(
+            "键空间分片: [{}, {}) / {}",
+            slice.start, slice.end, slice.total
+        );
+    }
+}
+
+/// 输出各字典来源的候选数量
+fn print_dictionary_sources(sources: &[SourceStats]) {
+    if sources.is_empty() {
+        return;
+    }
+    println!("字典来源:");
+    for stats in sources {
+        println!(
+            "  {}: 测试 {} 个, 重复跳过 {} 个",
+            stats.source, stats.tested, stats.duplicates
+        );
+    }
+}
@@ -0,0 +1,261 @@
+//! 破解过程的观察者和运行控制
+//!
+//! 库本身不输出任何内容：阶段、进度和提示信息都通过 [`CrackObserver`] 报告，命令行使用
+//! [`ConsoleObserver`] 输出到终端。[`CrackControl`] 可以在其他线程中取消、暂停或继续破解
+
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::StageReport;
+use crate::archive::{ArchiveError, TryOutcome};
+use crate::progress::{self, Progress, Status};
+
+/// 后台线程检查按键请求的间隔
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// 破解过程的观察者，所有方法默认不做任何事
+///
+/// 方法可能在后台线程中调用，实现应尽快返回
+pub trait CrackObserver: Sync {
+    /// 攻击阶段开始
+    fn stage_started(&self, _name: &str) {}
+
+    /// 攻击阶段结束
+    fn stage_finished(&self, _report: &StageReport) {}
+
+    /// 定期（或按回车键时）报告的进度
+    fn progress(&self, _status: &Status) {}
+
+    /// 找到密码
+    fn password_found(&self, _password: &str, _source: &str) {}
+
+    /// 警告（如字典无法打开、会话无法保存）
+    fn warning(&self, _message: &str) {}
+
+    /// 一般提示（如攻击配置、断点位置），可能包含多行
+    fn message(&self, _message: &str) {}
+}
+
+/// 不报告任何内容的观察者
+pub struct SilentObserver;
+
+impl CrackObserver for SilentObserver {}
+
+/// 把提示、警告和进度输出到终端的观察者（命令行使用）
+pub struct ConsoleObserver;
+
+impl CrackObserver for ConsoleObserver {
+    fn progress(&self, status: &Status) {
+        println!("{status}");
+    }
+
+    fn warning(&self, message: &str) {
+        println!("⚠️ {message}");
+    }
+
+    fn message(&self, message: &str) {
+        println!("{message}");
+    }
+}
+
+/// 运行控制：可以克隆后在其他线程中取消、暂停或继续破解
+#[derive(Clone, Default)]
+pub struct CrackControl {
+    state: Arc<ControlState>,
+}
+
+#[derive(Default)]
+struct ControlState {
+    cancelled: AtomicBool,
+    paused: Mutex<bool>,
+    changed: Condvar,
+}
+
+impl CrackControl {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 取消破解：攻击循环在下一个安全点停止，保存会话并返回 `CrackError::Interrupted`
+    pub fn cancel(&self) {
+        self.state.cancelled.store(true, Ordering::Relaxed);
+        // 唤醒暂停中的攻击循环
+        let _paused = self.state.paused.lock().expect("控制锁已损坏");
+        self.state.changed.notify_all();
+    }
+
+    /// 是否已取消
+    #[must_use]
+    pub fn is_cancelled(&self) -> bool {
+        self.state.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// 暂停破解：攻击循环在当前块（约 2 秒）结束后等待，直到继续或取消
+    pub fn pause(&self) {
+        *self.state.paused.lock().expect("控制锁已损坏") = true;
+    }
+
+    /// 继续已暂停的破解
+    pub fn resume(&self) {
+        *self.state.paused.lock().expect("控制锁已损坏") = false;
+        self.state.changed.notify_all();
+    }
+
+    /// 是否已暂停
+    #[must_use]
+    pub fn is_paused(&self) -> bool {
+        *self.state.paused.lock().expect("控制锁已损坏")
+    }
+
+    /// 暂停时阻塞，直到继续或取消
+    fn wait_while_paused(&self) {
+        let paused = self.state.paused.lock().expect("控制锁已损坏");
+        let _paused = self
+            .state
+            .changed
+            .wait_while(paused, |paused| *paused && !self.is_cancelled())
+            .expect("控制锁已损坏");
+    }
+}
+
+/// 一次破解运行的观察者、运行控制、进度计数和验证结果汇总，传给各攻击函数
+pub struct Monitor<'a> {
+    observer: &'a dyn CrackObserver,
+    control: CrackControl,
+    progress: Progress,
+    /// 通过快速检查但内容检查未通过的候选数量
+    near_misses: AtomicU64,
+    /// 中止本次运行的错误（如压缩包无法读取）
+    fault: Mutex<Option<ArchiveError>>,
+    /// 是否已记录中止错误（在 rayon 闭包中检查，避免加锁）
+    faulted: AtomicBool,
+}
+
+impl<'a> Monitor<'a> {
+    #[must_use]
+    pub fn new(observer: &'a dyn CrackObserver, control: CrackControl) -> Self {
+        Self {
+            observer,
+            control,
+            progress: Progress::default(),
+            near_misses: AtomicU64::new(0),
+            fault: Mutex::new(None),
+            faulted: AtomicBool::new(false),
+        }
+    }
+
+    /// 不报告任何内容、不会被取消的运行（单独调用攻击函数时使用）
+    #[must_use]
+    pub fn silent() -> Monitor<'static> {
+        Monitor::new(&SilentObserver, CrackControl::new())
+    }
+
+    #[must_use]
+    pub fn observer(&self) -> &'a dyn CrackObserver {
+        self.observer
+    }
+
+    #[must_use]
+    pub const fn progress(&self) -> &Progress {
+        &self.progress
+    }
+
+    /// 是否已取消或因错误中止（在攻击循环的 rayon 闭包中调用）
+    #[must_use]
+    pub fn cancelled(&self) -> bool {
+        self.control.is_cancelled() || self.faulted.load(Ordering::Relaxed)
+    }
+
+    /// 记录候选 `password` 的验证结果，返回是否为正确密码
+    ///
+    /// 遇到读取失败等致命错误时记录第一个错误并中止运行：攻击循环像取消时一样在当前块结束后停止。
+    /// 未通过完整校验的候选报告警告后继续搜索
+    pub fn record(&self, password: &str, outcome: &TryOutcome) -> bool {
+        match outcome {
+            TryOutcome::Verified => return true,
+            TryOutcome::Candidate { .. } => {
+                self.near_misses.fetch_add(1, Ordering::Relaxed);
+            }
+            TryOutcome::Unconfirmed(reason) => {
+                self.near_misses.fetch_add(1, Ordering::Relaxed);
+                self.warning(&format!(
+                    "候选密码 {password:?} 通过了内容检查但完整校验失败 ({reason})，继续搜索"
+                ));
+            }
+            TryOutcome::Rejected => {}
+            TryOutcome::IoError(_) | TryOutcome::Unsupported(_) => {
+                if let Some(error) = outcome.fatal_error() {
+                    self.fault
+                        .lock()
+                        .expect("错误记录锁已损坏")
+                        .get_or_insert(error);
+                }
+                self.faulted.store(true, Ordering::Relaxed);
+            }
+        }
+        false
+    }
+
+    /// 通过快速检查但内容检查未通过的候选数量（近似匹配）
+    #[must_use]
+    pub fn near_misses(&self) -> u64 {
+        self.near_misses.load(Ordering::Relaxed)
+    }
+
+    /// 中止本次运行的错误
+    #[must_use]
+    pub fn fault(&self) -> Option<ArchiveError> {
+        self.fault.lock().expect("错误记录锁已损坏").clone()
+    }
+
+    /// 是否已暂停
+    #[must_use]
+    pub fn paused(&self) -> bool {
+        self.control.is_paused()
+    }
+
+    /// 块之间的安全点：暂停时等待继续，返回是否已取消
+    #[must_use]
+    pub fn checkpoint(&self) -> bool {
+        if !self.cancelled() {
+            self.control.wait_while_paused();
+        }
+        self.cancelled()
+    }
+
+    /// 报告一般提示
+    pub fn message(&self, message: &str) {
+        self.observer.message(message);
+    }
+
+    /// 报告警告
+    pub fn warning(&self, message: &str) {
+        self.observer.warning(message);
+    }
+
+    /// 执行 `run` 的同时在后台线程报告进度：每隔 `interval` 一次（`None` 表示不定期报告），按回车键时立即报告
+    pub fn with_reporter<T>(&self, interval: Option<Duration>, run: impl FnOnce() -> T) -> T {
+        let stop = AtomicBool::new(false);
+        thread::scope(|scope| {
+            scope.spawn(|| {
+                let mut last_report = Instant::now();
+                while !stop.load(Ordering::Relaxed) {
+                    thread::sleep(POLL_INTERVAL);
+                    let due = interval.is_some_and(|interval| last_report.elapsed() >= interval);
+                    if progress::take_status_request() || due {
+                        if let Some(status) = self.progress.status() {
+                            self.observer.progress(&status);
+                        }
+                        last_report = Instant::now();
+                    }
+                }
+            });
+            let result = run();
+            stop.store(true, Ordering::Relaxed);
+            result
+        })
+    }
+}
@@ -0,0 +1,11 @@
+//! 数据目录 (~/.`archive_cracker`)：内置字典、potfile、会话和攻击计划都保存在这里
+
+use std::io;
+use std::path::PathBuf;
+
+/// 数据目录路径，无法获取用户主目录时返回错误
+pub fn data_dir() -> io::Result<PathBuf> {
+    dirs::home_dir()
+        .map(|home| home.join(".archive_cracker"))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "无法获取用户主目录"))
+}
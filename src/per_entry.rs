@@ -0,0 +1,346 @@
+//! 按条目破解：同一压缩包中不同条目使用不同密码时逐组破解
+//!
+//! 加密的条目先按加密方式（ZIP 为 `ZipCrypto`/AES 密钥长度，7z 为数据块）分组，每组选一个验证条目，
+//! 先尝试其他组已找到的密码，都不对时对该组执行完整的破解流程。找到密码后逐个验证组内其余条目，
+//! 未通过的条目（同一加密方式下的另一个密码）重新组成一组继续破解
+
+use std::collections::VecDeque;
+use std::path::Path;
+use std::time::Instant;
+
+use crate::archive::{
+    ArchiveFormat, ArchiveHandler, EntryInfo, TargetFile, get_handler, pick_target,
+};
+use crate::extract::{ExtractReport, Extractor};
+use crate::observer::{CrackControl, CrackObserver, Monitor};
+use crate::rewrap::{RewrapReport, Rewrapper};
+use crate::session::Session;
+use crate::{CrackConfig, CrackError, crack_archive_with, extract_with, rewrap_with};
+
+/// 单个条目的破解结果
+#[derive(Debug, Clone)]
+pub struct EntryPassword {
+    pub index: usize,
+    pub name: String,
+    /// 加密方式
+    pub encryption: String,
+    /// 该条目的密码，未找到时为 `None`
+    pub password: Option<String>,
+}
+
+/// 按条目破解的结果
+#[derive(Debug, Clone)]
+pub struct EntryReport {
+    /// 所有加密条目的密码，按条目顺序（空条目没有可验证的内容，不在其中）
+    pub entries: Vec<EntryPassword>,
+    /// 执行了完整破解流程的组数
+    pub groups_cracked: usize,
+    /// 总共测试的密码数量
+    pub total_tested: u64,
+    /// 总耗时（秒）
+    pub elapsed_secs: f64,
+    /// 解压结果（使用 `--extract` 时），每个密码解压各自的条目，未加密的条目随第一个密码解压
+    pub extracted: Option<ExtractReport>,
+    /// 重新打包结果（使用 `--rewrap` 且所有条目都找到密码时）
+    pub rewrapped: Option<RewrapReport>,
+    /// 提前结束的原因（`CrackError::Interrupted` 或 `CrackError::Aborted`），该组的会话已保存；
+    /// `None` 表示所有组都已尝试
+    pub stopped: Option<CrackError>,
+}
+
+impl EntryReport {
+    /// 所有条目都还没有密码的结果
+    fn new(entries: &[EntryInfo]) -> Self {
+        Self {
+            entries: entries
+                .iter()
+                .map(|entry| EntryPassword {
+                    index: entry.index,
+                    name: entry.name.clone(),
+                    encryption: entry.encryption.clone().unwrap_or_default(),
+                    password: None,
+                })
+                .collect(),
+            groups_cracked: 0,
+            total_tested: 0,
+            elapsed_secs: 0.0,
+            extracted: None,
+            rewrapped: None,
+            stopped: None,
+        }
+    }
+
+    /// 未找到密码的条目
+    pub fn unsolved(&self) -> impl Iterator<Item = &EntryPassword> {
+        self.entries.iter().filter(|entry| entry.password.is_none())
+    }
+
+    /// 找到的不同密码，按首次出现的条目顺序
+    #[must_use]
+    pub fn passwords(&self) -> Vec<&str> {
+        let mut passwords: Vec<&str> = Vec::new();
+        for password in self.entries.iter().filter_map(|e| e.password.as_deref()) {
+            if !passwords.contains(&password) {
+                passwords.push(password);
+            }
+        }
+        passwords
+    }
+}
+
+/// 一组加密方式相同的条目
+struct Group {
+    encryption: String,
+    entries: Vec<EntryInfo>,
+}
+
+/// 按条目破解，通过 `observer` 报告各组的破解过程
+///
+/// 每组使用 `config` 的全部阶段和预算，会话名为 `<会话名>.<验证条目索引>`：
+/// 中断后重新运行相同的配置，已找到的密码先被尝试，中断的组从保存的会话继续
+///
+/// # 示例
+/// ```no_run
+/// use archive_cracker::CrackConfig;
+/// use archive_cracker::observer::{CrackControl, SilentObserver};
+/// use archive_cracker::per_entry::crack_entries;
+///
+/// let config = CrackConfig::builder("file.zip").per_entry(true).build().unwrap();
+/// let report = crack_entries(&config, &SilentObserver, &CrackControl::new()).unwrap();
+/// for entry in &report.entries {
+///     println!("{}: {:?}", entry.name, entry.password);
+/// }
+/// ```
+pub fn crack_entries(
+    config: &CrackConfig,
+    observer: &dyn CrackObserver,
+    control: &CrackControl,
+) -> Result<EntryReport, CrackError> {
+    let archive_path = config.archive.as_path();
+    let format = ArchiveFormat::detect(archive_path).ok_or(CrackError::UnsupportedFormat)?;
+    let handler = get_handler(format);
+    config.validate()?;
+    let monitor = Monitor::new(observer, control.clone());
+
+    let start = Instant::now();
+    let entries: Vec<EntryInfo> = handler
+        .entries(archive_path)?
+        .into_iter()
+        .filter(|entry| entry.is_encrypted() && entry.size > 0)
+        .collect();
+    let mut report = EntryReport::new(&entries);
+
+    let mut pending = group_entries(entries);
+    if pending.is_empty() {
+        return Err(CrackError::NoRecognizableFile);
+    }
+    monitor.message(&format!(
+        "按条目破解: {} 个加密条目，{} 组\n",
+        report.entries.len(),
+        pending.len()
+    ));
+
+    let base_session = config
+        .session
+        .clone()
+        .unwrap_or_else(|| Session::default_name(&config.archive));
+    let mut found: Vec<String> = Vec::new();
+
+    while let Some(group) = pending.pop_front() {
+        if monitor.cancelled() {
+            break;
+        }
+        let Some(target) = pick_target(group.entries.iter().cloned()) else {
+            continue;
+        };
+        monitor.message(&format!(
+            "=== 条目组: {}，{} 个条目，验证条目 {} ===",
+            group.encryption,
+            group.entries.len(),
+            target.name
+        ));
+
+        let known = found.iter().find(|password| {
+            handler
+                .try_password(archive_path, password, &target)
+                .is_verified()
+        });
+        let password = if let Some(password) = known {
+            monitor.message("使用其他组已找到的密码\n");
+            password.clone()
+        } else {
+            report.groups_cracked += 1;
+            let group_config = group_config(config, &base_session, &target);
+            match crack_archive_with(&group_config, observer, control) {
+                Ok(success) => {
+                    report.total_tested += success.total_tested;
+                    found.push(success.password.clone());
+                    success.password
+                }
+                Err(e) => {
+                    report.total_tested += e.failure().map_or(0, |failure| failure.total_tested);
+                    match e {
+                        CrackError::NotFound(_) | CrackError::BudgetExhausted(..) => {
+                            monitor.warning(&format!("条目组 {} 未找到密码\n", group.encryption));
+                            continue;
+                        }
+                        CrackError::Interrupted(..) | CrackError::Aborted(..) => {
+                            report.stopped = Some(e);
+                            break;
+                        }
+                        e => return Err(e),
+                    }
+                }
+            }
+        };
+
+        // 组内其余条目可能使用同一加密方式下的其他密码
+        let rest = assign_password(
+            &mut report,
+            handler.as_ref(),
+            archive_path,
+            &password,
+            group.entries,
+            &target,
+        );
+        if !rest.is_empty() {
+            monitor.message(&format!(
+                "{} 个条目未通过该密码的验证，作为新的一组继续破解\n",
+                rest.len()
+            ));
+            pending.push_back(Group {
+                encryption: group.encryption,
+                entries: rest,
+            });
+        }
+    }
+
+    report.elapsed_secs = start.elapsed().as_secs_f64();
+    report.extracted = extract_entries(config, &report, handler.as_ref(), &monitor)?;
+    report.rewrapped = rewrap_entries(config, &report, handler.as_ref(), &monitor);
+    Ok(report)
+}
+
+/// 使用 `--rewrap` 时用各条目的密码重新打包，有条目未找到密码时无法重新打包
+fn rewrap_entries(
+    config: &CrackConfig,
+    report: &EntryReport,
+    handler: &dyn ArchiveHandler,
+    monitor: &Monitor,
+) -> Option<RewrapReport> {
+    let options = config.rewrap.as_ref()?;
+    if report.unsolved().next().is_some() {
+        monitor.warning("有条目未找到密码，无法重新打包\n");
+        return None;
+    }
+    let passwords = report.passwords();
+    let mut rewrapper = Rewrapper::new(options, passwords.first()?);
+    for entry in &report.entries {
+        if let Some(password) = &entry.password {
+            rewrapper.set_password(entry.index, password);
+        }
+    }
+    rewrap_with(handler, &config.archive, rewrapper, monitor)
+}
+
+/// 使用 `--extract` 且找到了密码时解压：每个密码解压各自的条目，未加密和空条目随第一个密码解压，
+/// 未找到密码的条目记为跳过
+fn extract_entries(
+    config: &CrackConfig,
+    report: &EntryReport,
+    handler: &dyn ArchiveHandler,
+    monitor: &Monitor,
+) -> Result<Option<ExtractReport>, CrackError> {
+    let Some(options) = &config.extract else {
+        return Ok(None);
+    };
+    if report.passwords().is_empty() {
+        return Ok(None);
+    }
+    let archive_path = config.archive.as_path();
+    let mut extractor = Extractor::new(options);
+    let mut others: Vec<usize> = handler
+        .entries(archive_path)?
+        .into_iter()
+        .map(|entry| entry.index)
+        .filter(|index| report.entries.iter().all(|entry| entry.index != *index))
+        .collect();
+    for password in report.passwords() {
+        let mut indexes: Vec<usize> = report
+            .entries
+            .iter()
+            .filter(|entry| entry.password.as_deref() == Some(password))
+            .map(|entry| entry.index)
+            .collect();
+        indexes.append(&mut others);
+        extractor.restrict(indexes);
+        extract_with(handler, archive_path, password, &mut extractor, monitor);
+    }
+    for entry in report.unsolved() {
+        if extractor.wants(entry.index, &entry.name) {
+            extractor.skip(&entry.name, "未找到密码");
+        }
+    }
+    Ok(Some(extractor.finish()))
+}
+
+/// 把 `password` 记录为组内能通过验证的条目的密码，返回未通过的条目
+fn assign_password(
+    report: &mut EntryReport,
+    handler: &dyn ArchiveHandler,
+    archive_path: &Path,
+    password: &str,
+    entries: Vec<EntryInfo>,
+    target: &TargetFile,
+) -> Vec<EntryInfo> {
+    let (solved, rest): (Vec<_>, Vec<_>) = entries.into_iter().partition(|entry| {
+        entry.index == target.index
+            || handler
+                .try_password(
+                    archive_path,
+                    password,
+                    &TargetFile::new(entry.index, entry.name.clone()),
+                )
+                .is_verified()
+    });
+    for entry in &solved {
+        if let Some(result) = report.entries.iter_mut().find(|e| e.index == entry.index) {
+            result.password = Some(password.to_string());
+        }
+    }
+    rest
+}
+
+/// 按加密方式分组，保持条目顺序
+fn group_entries(entries: impl IntoIterator<Item = EntryInfo>) -> VecDeque<Group> {
+    let mut groups: VecDeque<Group> = VecDeque::new();
+    for entry in entries {
+        let encryption = entry.encryption.clone().unwrap_or_default();
+        match groups
+            .iter_mut()
+            .find(|group| group.encryption == encryption)
+        {
+            Some(group) => group.entries.push(entry),
+            None => groups.push_back(Group {
+                encryption,
+                entries: vec![entry],
+            }),
+        }
+    }
+    groups
+}
+
+/// 破解一组条目的配置：只验证 `target`，使用独立的会话，已保存的会话自动继续
+fn group_config(config: &CrackConfig, base_session: &str, target: &TargetFile) -> CrackConfig {
+    let session = format!("{base_session}.{}", target.index);
+    let saved = Session::path_for(&session).is_ok_and(|path| path.exists());
+    CrackConfig {
+        entries: vec![target.name.clone()],
+        per_entry: false,
+        extract: None,
+        rewrap: None,
+        session: Some(session.clone()),
+        restore: saved.then_some(session),
+        ..config.clone()
+    }
+}
@@ -0,0 +1,224 @@
+//! 声明式攻击计划
+//!
+//! 计划文件为 TOML 格式，每个 `[[stage]]` 描述一个攻击阶段，按顺序执行：
+//!
+//! ```toml
+//! description = "常用密码 → 字典 → 拼音 → 短密码"
+//! max_seconds = 7200
+//!
+//! [[stage]]
+//! type = "potfile"
+//!
+//! [[stage]]
+//! type = "harvest"
+//! neighbors = true
+//!
+//! [[stage]]
+//! type = "dictionary"
+//! paths = ["/data/wordlists/rockyou.txt.gz"]
+//! max_seconds = 600
+//!
+//! [[stage]]
+//! type = "bruteforce"
+//! charset = ["pinyin"]
+//! max_length = 4
+//!
+//! [[stage]]
+//! type = "bruteforce"
+//! charset = ["lower", "digit"]
+//! max_length = 6
+//! max_candidates = 1000000000
+//! deadline = "2026-10-19T08:00:00+08:00"
+//! ```
+
+use clap::ValueEnum;
+use serde::{Deserialize, Deserializer, de};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use crate::attack::Budget;
+use crate::attack::budget::parse_deadline;
+use crate::cli::Charset;
+use crate::paths::data_dir;
+
+/// 攻击计划：按顺序执行的攻击阶段
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Plan {
+    /// 计划说明
+    #[serde(default)]
+    pub description: String,
+    /// 整个计划最多运行的秒数
+    pub max_seconds: Option<u64>,
+    /// 整个计划最多测试的候选数量
+    pub max_candidates: Option<u64>,
+    /// 整个计划的截止时间（RFC 3339）
+    #[serde(default, deserialize_with = "deserialize_deadline")]
+    pub deadline: Option<SystemTime>,
+    /// 攻击阶段
+    #[serde(rename = "stage", default)]
+    pub stages: Vec<PlanStage>,
+}
+
+/// 计划中的一个阶段
+#[derive(Debug, Clone, Deserialize)]
+pub struct PlanStage {
+    #[serde(flatten)]
+    pub attack: StageAttack,
+    /// 本阶段最多运行的秒数，用尽后进入下一阶段
+    pub max_seconds: Option<u64>,
+    /// 本阶段最多测试的候选数量，用尽后进入下一阶段
+    pub max_candidates: Option<u64>,
+    /// 本阶段的截止时间（RFC 3339），到达后进入下一阶段
+    #[serde(default, deserialize_with = "deserialize_deadline")]
+    pub deadline: Option<SystemTime>,
+}
+
+impl PlanStage {
+    /// 从现在开始计算的本阶段预算
+    #[must_use]
+    pub fn budget(&self) -> Budget {
+        Budget::new(
+            self.max_seconds.map(Duration::from_secs),
+            self.max_candidates,
+        )
+        .with_deadline(self.deadline)
+    }
+}
+
+/// 阶段的攻击方式
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum StageAttack {
+    /// potfile 中本压缩包的密码和所有已破解过的密码
+    Potfile,
+    /// 压缩包元数据中的候选词，可选扫描邻近文件
+    Harvest {
+        /// 是否扫描压缩包所在目录的说明文件和同源压缩包
+        #[serde(default)]
+        neighbors: bool,
+        /// 扫描邻近文件时递归的子目录层数
+        #[serde(default)]
+        depth: usize,
+    },
+    /// 字典攻击
+    Dictionary {
+        /// 字典文件或目录，为空时使用命令行 `-D` 指定的字典
+        #[serde(default)]
+        paths: Vec<String>,
+        /// 是否先尝试内置字典
+        #[serde(default = "default_builtin")]
+        builtin: bool,
+    },
+    /// 暴力破解
+    Bruteforce {
+        /// 字符集，默认为 lower,upper,digit
+        #[serde(default = "default_charset")]
+        charset: Vec<Charset>,
+        /// 最小长度
+        #[serde(default = "default_min_length")]
+        min_length: u32,
+        /// 最大长度
+        max_length: u32,
+    },
+}
+
+impl fmt::Display for StageAttack {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Potfile => write!(f, "potfile"),
+            Self::Harvest { neighbors, .. } => {
+                if *neighbors {
+                    write!(f, "元数据候选词 + 邻近文件")
+                } else {
+                    write!(f, "元数据候选词")
+                }
+            }
+            Self::Dictionary { paths, .. } => {
+                if paths.is_empty() {
+                    write!(f, "字典")
+                } else {
+                    write!(f, "字典 {}", paths.join(", "))
+                }
+            }
+            Self::Bruteforce {
+                charset,
+                min_length,
+                max_length,
+            } => {
+                let names: Vec<_> = charset
+                    .iter()
+                    .filter_map(|c| c.to_possible_value().map(|v| v.get_name().to_string()))
+                    .collect();
+                write!(
+                    f,
+                    "暴力破解 {} (长度 {min_length}~{max_length})",
+                    names.join(",")
+                )
+            }
+        }
+    }
+}
+
+fn deserialize_deadline<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<SystemTime>, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    parse_deadline(&value).map(Some).map_err(de::Error::custom)
+}
+
+const fn default_builtin() -> bool {
+    true
+}
+
+fn default_charset() -> Vec<Charset> {
+    vec![Charset::Lower, Charset::Upper, Charset::Digit]
+}
+
+const fn default_min_length() -> u32 {
+    1
+}
+
+impl Plan {
+    /// 计划目录 (~/.`archive_cracker/plans`)
+    pub fn dir() -> io::Result<PathBuf> {
+        Ok(data_dir()?.join("plans"))
+    }
+
+    /// 按名称或路径加载计划：存在的文件直接读取，否则读取计划目录中的 `<name>.toml`
+    pub fn load(name: &str) -> io::Result<Self> {
+        let path = Path::new(name);
+        let path = if path.is_file() {
+            path.to_path_buf()
+        } else {
+            Self::dir()?.join(format!("{name}.toml"))
+        };
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    /// 从现在开始计算的整个计划的预算
+    #[must_use]
+    pub fn budget(&self) -> Budget {
+        Budget::new(
+            self.max_seconds.map(Duration::from_secs),
+            self.max_candidates,
+        )
+        .with_deadline(self.deadline)
+    }
+
+    /// 解析 TOML 格式的计划
+    pub fn parse(content: &str) -> io::Result<Self> {
+        let plan: Self = toml::from_str(content)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+        if plan.stages.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "计划中没有任何阶段",
+            ));
+        }
+        Ok(plan)
+    }
+}
@@ -0,0 +1,101 @@
+use std::collections::HashSet;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+
+use crate::attack::wordlist::{decode_word, encode_word};
+use crate::paths::data_dir;
+
+/// 获取默认 potfile 路径 (~/.`archive_cracker/potfile.txt`)
+///
+/// 每行格式为 `指纹:密码`，指纹见 [`crate::archive::ArchiveHandler::fingerprint`]；
+/// 含换行符等特殊字符的密码写成 `$HEX[...]`（见 [`encode_word`]）
+pub fn get_default_potfile_path() -> io::Result<PathBuf> {
+    Ok(data_dir()?.join("potfile.txt"))
+}
+
+/// 查找指纹对应的已破解密码（同一指纹有多条记录时取最新的一条）
+#[must_use]
+pub fn lookup(path: &Path, fingerprint: &str) -> Option<String> {
+    read_entries(path)
+        .into_iter()
+        .rev()
+        .find(|(fp, _)| fp == fingerprint)
+        .map(|(_, password)| password)
+}
+
+/// 读取 potfile 中所有已破解的密码（去重，保持记录顺序）
+#[must_use]
+pub fn passwords(path: &Path) -> Vec<String> {
+    let mut seen = HashSet::new();
+    read_entries(path)
+        .into_iter()
+        .map(|(_, password)| password)
+        .filter(|password| seen.insert(password.clone()))
+        .collect()
+}
+
+/// 记录破解结果，返回是否新写入
+///
+/// 写入期间持有排他文件锁，多个进程同时运行也不会损坏文件
+pub fn record(path: &Path, fingerprint: &str, password: &str) -> std::io::Result<bool> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let mut file = OpenOptions::new()
+        .read(true)
+        .append(true)
+        .create(true)
+        .open(path)?;
+    file.lock()?;
+
+    // 持锁后再检查，避免并发运行重复写入；文件可能被手动编辑过，含有非 UTF-8 内容
+    let mut bytes = Vec::new();
+    file.read_to_end(&mut bytes)?;
+    let content = String::from_utf8_lossy(&bytes);
+    let exists = content
+        .lines()
+        .filter_map(parse_line)
+        .any(|(fp, pwd)| fp == fingerprint && decode_word(pwd) == password);
+    if exists {
+        return Ok(false);
+    }
+
+    // 上一条记录可能未以换行结尾（例如被手动编辑过）
+    if !content.is_empty() && !content.ends_with('\n') {
+        writeln!(file)?;
+    }
+    writeln!(file, "{fingerprint}:{}", encode_word(password))?;
+    Ok(true)
+}
+
+/// 读取所有记录（持共享锁）
+fn read_entries(path: &Path) -> Vec<(String, String)> {
+    let Ok(file) = File::open(path) else {
+        return Vec::new();
+    };
+    if file.lock_shared().is_err() {
+        return Vec::new();
+    }
+
+    // 含非 UTF-8 内容的行按有损方式解码，不影响其余记录
+    BufReader::new(&file)
+        .split(b'\n')
+        .map_while(Result::ok)
+        .map(|line| String::from_utf8_lossy(&line).into_owned())
+        .filter_map(|line| {
+            parse_line(&line).map(|(fp, pwd)| (fp.to_string(), decode_word(pwd).into_owned()))
+        })
+        .collect()
+}
+
+/// 解析一行记录：密码中可以包含 `:`，只按第一个 `:` 分割
+fn parse_line(line: &str) -> Option<(&str, &str)> {
+    let line = line.strip_suffix('\r').unwrap_or(line);
+    if line.starts_with('#') {
+        return None;
+    }
+    line.split_once(':')
+        .filter(|(fp, pwd)| !fp.is_empty() && !pwd.is_empty())
+}
@@ -0,0 +1,335 @@
+//! 实时进度：攻击循环在 rayon 闭包中累加计数，后台线程定期（或按回车键时）报告状态
+//!
+//! 计数器按 rayon 线程序号分片，每个分片独占一个缓存行，多核同时计数时互不争用
+
+use std::fmt;
+use std::io::{self, BufRead, IsTerminal};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::thread;
+use std::time::Instant;
+
+/// 计数器分片数
+const SHARDS: usize = 64;
+
+/// 按回车键请求输出状态
+static STATUS_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// 是否已监听回车键
+static STATUS_KEY: AtomicBool = AtomicBool::new(false);
+
+/// 监听回车键：每按一次报告一次状态，返回是否开始监听（标准输入不是终端时不监听）
+pub fn install_status_key() -> bool {
+    let stdin = io::stdin();
+    if !stdin.is_terminal() || STATUS_KEY.swap(true, Ordering::Relaxed) {
+        return false;
+    }
+    thread::spawn(move || {
+        for line in stdin.lock().lines() {
+            if line.is_err() {
+                break;
+            }
+            STATUS_REQUESTED.store(true, Ordering::Relaxed);
+        }
+    });
+    true
+}
+
+/// 是否按过回车键（读取后清除）
+pub fn take_status_request() -> bool {
+    STATUS_REQUESTED.swap(false, Ordering::Relaxed)
+}
+
+/// 独占一个缓存行的计数器分片
+#[repr(align(64))]
+struct Shard(AtomicU64);
+
+/// 一次运行的进度：累计计数、当前阶段和之后的工作量
+pub struct Progress {
+    /// 已测试的候选数量（各分片之和）
+    tested: [Shard; SHARDS],
+    /// 当前阶段的进度，还没有开始任何阶段时为 `None`
+    stage: Mutex<Option<StageProgress>>,
+    /// 当前阶段之后还要执行的工作量
+    pending: Mutex<Pending>,
+}
+
+impl Default for Progress {
+    fn default() -> Self {
+        Self {
+            tested: std::array::from_fn(|_| Shard(AtomicU64::new(0))),
+            stage: Mutex::new(None),
+            pending: Mutex::new(Pending::NONE),
+        }
+    }
+}
+
+impl Progress {
+    /// 记录测试了一个候选（在攻击循环的 rayon 闭包中调用）
+    #[inline]
+    pub fn record(&self) {
+        self.add(1);
+    }
+
+    /// 记录测试了 `count` 个候选
+    #[inline]
+    pub fn add(&self, count: u64) {
+        // 非 rayon 线程使用第 0 个分片
+        let shard = rayon::current_thread_index().map_or(0, |i| (i + 1) % SHARDS);
+        self.tested[shard].0.fetch_add(count, Ordering::Relaxed);
+    }
+
+    /// 累计测试的候选数量
+    #[must_use]
+    pub fn tested(&self) -> u64 {
+        self.tested
+            .iter()
+            .map(|shard| shard.0.load(Ordering::Relaxed))
+            .sum()
+    }
+
+    /// 开始一个新阶段
+    pub fn begin_stage(&self, name: &str) {
+        let now = Instant::now();
+        let base = self.tested();
+        *self.stage.lock().expect("进度锁已损坏") = Some(StageProgress {
+            name: name.to_string(),
+            detail: String::new(),
+            started: now,
+            base,
+            keyspace: None,
+            fraction: None,
+            last: (now, base),
+        });
+    }
+
+    /// 修改当前阶段的进度（还没有开始任何阶段时忽略）
+    fn update(&self, apply: impl FnOnce(&mut StageProgress)) {
+        if let Some(stage) = self.stage.lock().expect("进度锁已损坏").as_mut() {
+            apply(stage);
+        }
+    }
+
+    /// 设置当前阶段的键空间大小，`done` 为断点续传时已完成的数量
+    pub fn set_keyspace(&self, total: u128, done: u128) {
+        let base = self.tested();
+        self.update(|stage| stage.keyspace = Some((total, done.min(total), base)));
+    }
+
+    /// 设置当前阶段按读取量估算的完成比例（0.0 ~ 1.0）
+    pub fn set_fraction(&self, fraction: f64) {
+        self.update(|stage| {
+            let start = stage.fraction.map_or(fraction, |(start, _)| start);
+            stage.fraction = Some((start, fraction));
+        });
+    }
+
+    /// 设置当前阶段的细节（如当前长度、当前字典）
+    pub fn set_detail(&self, detail: String) {
+        self.update(|stage| stage.detail = detail);
+    }
+
+    /// 设置当前阶段之后还要执行的工作量
+    pub fn set_pending(&self, pending: Pending) {
+        *self.pending.lock().expect("进度锁已损坏") = pending;
+    }
+
+    /// 当前阶段的状态（还没有开始任何阶段时为 `None`），瞬时速度从上次调用开始计算
+    #[must_use]
+    pub fn status(&self) -> Option<Status> {
+        let pending = *self.pending.lock().expect("进度锁已损坏");
+        let count = self.tested();
+        self.stage
+            .lock()
+            .expect("进度锁已损坏")
+            .as_mut()
+            .map(|stage| Status {
+                pending,
+                ..stage.sample(Instant::now(), count)
+            })
+    }
+}
+
+/// 当前阶段之后还要执行的工作量，用于估算整体剩余时间
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Pending {
+    /// 键空间已知的候选数量
+    pub candidates: u128,
+    /// 是否还有键空间未知的阶段（如字典）
+    pub unknown: bool,
+}
+
+impl Pending {
+    /// 当前阶段是最后一个阶段
+    pub const NONE: Self = Self {
+        candidates: 0,
+        unknown: false,
+    };
+
+    /// 是否没有后续工作
+    #[must_use]
+    pub const fn is_none(self) -> bool {
+        self.candidates == 0 && !self.unknown
+    }
+}
+
+/// 一个阶段的进度
+struct StageProgress {
+    name: String,
+    /// 阶段内的细节（如当前长度、当前字典）
+    detail: String,
+    started: Instant,
+    /// 阶段开始时的累计计数
+    base: u64,
+    /// 键空间大小、开始时已完成的数量、设置时的累计计数
+    keyspace: Option<(u128, u128, u64)>,
+    /// 按读取量估算的完成比例：首次设置时的值和当前值
+    fraction: Option<(f64, f64)>,
+    /// 上次报告状态的时间和累计计数，用于计算瞬时速度
+    last: (Instant, u64),
+}
+
+impl StageProgress {
+    /// 计算当前的速度、完成比例和阶段剩余时间
+    #[allow(clippy::cast_precision_loss)]
+    fn sample(&mut self, now: Instant, count: u64) -> Status {
+        let tested = count - self.base;
+        let elapsed = now.duration_since(self.started).as_secs_f64();
+        let average = if elapsed > 0.0 {
+            tested as f64 / elapsed
+        } else {
+            0.0
+        };
+        let since = now.duration_since(self.last.0).as_secs_f64();
+        let speed = if since > 0.0 {
+            (count - self.last.1) as f64 / since
+        } else {
+            average
+        };
+        self.last = (now, count);
+
+        // 优先按键空间计算，否则按读取量估算
+        let (percent, eta) = if let Some((total, done, base)) = self.keyspace {
+            let done = (done + u128::from(count - base)).min(total);
+            let percent = done as f64 / total.max(1) as f64 * 100.0;
+            let eta = (average > 0.0).then(|| (total - done) as f64 / average);
+            (Some(percent), eta)
+        } else if let Some((start, fraction)) = self.fraction {
+            let eta = (fraction > start).then(|| elapsed * (1.0 - fraction) / (fraction - start));
+            (Some(fraction * 100.0), eta)
+        } else {
+            (None, None)
+        };
+
+        Status {
+            stage: self.name.clone(),
+            detail: self.detail.clone(),
+            tested,
+            speed,
+            average_speed: average,
+            percent,
+            stage_eta_secs: eta,
+            pending: Pending::NONE,
+        }
+    }
+}
+
+/// 一次进度报告
+#[derive(Debug, Clone)]
+pub struct Status {
+    /// 阶段名
+    pub stage: String,
+    /// 阶段内的细节（如当前长度、当前字典）
+    pub detail: String,
+    /// 本阶段已测试的候选数量
+    pub tested: u64,
+    /// 自上次报告以来的速度（次/秒）
+    pub speed: f64,
+    /// 本阶段的平均速度（次/秒）
+    pub average_speed: f64,
+    /// 本阶段的完成百分比，无法估算时为 `None`
+    pub percent: Option<f64>,
+    /// 本阶段的预计剩余秒数，无法估算时为 `None`
+    pub stage_eta_secs: Option<f64>,
+    /// 本阶段之后还要执行的工作量
+    pub pending: Pending,
+}
+
+impl Status {
+    /// 按本阶段的平均速度估算的整体剩余秒数（之后还有字典阶段时为下限）
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn total_eta_secs(&self) -> Option<f64> {
+        self.stage_eta_secs
+            .filter(|_| self.average_speed > 0.0)
+            .map(|eta| eta + self.pending.candidates as f64 / self.average_speed)
+    }
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "进度 [{}", self.stage)?;
+        if !self.detail.is_empty() {
+            write!(f, " · {}", self.detail)?;
+        }
+        write!(f, "] 已测试 {}", self.tested)?;
+        if let Some(percent) = self.percent {
+            write!(f, " ({percent:.2}%)")?;
+        }
+        write!(
+            f,
+            "，速度 {:.0} 次/秒（平均 {:.0} 次/秒），阶段剩余 {}",
+            self.speed,
+            self.average_speed,
+            format_eta(self.stage_eta_secs)
+        )?;
+        if !self.pending.is_none() {
+            let total = self.total_eta_secs();
+            let prefix = if self.pending.unknown && total.is_some() {
+                "至少 "
+            } else {
+                ""
+            };
+            write!(f, "，整体剩余 {prefix}{}", format_eta(total))?;
+        }
+        Ok(())
+    }
+}
+
+/// 格式化剩余时间，如 `01:02:03`、`3 天 04:05:06`、`200 年 12 天`，`None` 为“未知”
+///
+/// ```
+/// use archive_cracker::progress::format_eta;
+///
+/// assert_eq!(format_eta(Some(3723.0)), "01:02:03");
+/// assert_eq!(format_eta(Some(200.0 * 365.0 * 86_400.0)), "200 年 0 天");
+/// assert_eq!(format_eta(None), "未知");
+/// ```
+#[must_use]
+#[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+pub fn format_eta(secs: Option<f64>) -> String {
+    const YEAR: f64 = 365.0 * 86_400.0;
+    let Some(secs) = secs.filter(|s| s.is_finite()) else {
+        return "未知".to_string();
+    };
+    if secs >= 1e4 * YEAR {
+        return format!("{:.2e} 年", secs / YEAR);
+    }
+    if secs >= YEAR {
+        let days = (secs / 86_400.0) as u64;
+        return format!("{} 年 {} 天", days / 365, days % 365);
+    }
+    let secs = secs.ceil() as u64;
+    let (days, secs) = (secs / 86_400, secs % 86_400);
+    let clock = format!(
+        "{:02}:{:02}:{:02}",
+        secs / 3600,
+        secs % 3600 / 60,
+        secs % 60
+    );
+    if days > 0 {
+        format!("{days} 天 {clock}")
+    } else {
+        clock
+    }
+}
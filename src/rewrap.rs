@@ -0,0 +1,214 @@
+//! 找到密码后重新打包：去掉密码，或换成新密码重新加密
+//!
+//! 各格式的处理器用找到的密码逐个解密条目，边读边写入新的同格式压缩包，保留条目名、目录结构、
+//! 修改时间和权限等元数据。[`Rewrapper`] 提供每个条目的原密码（按条目破解时各条目可以不同）
+//! 和新压缩包的选项
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::CrackError;
+use crate::archive::ArchiveFormat;
+use crate::extract::NameEncoding;
+
+/// 新压缩包的加密方式（7z 只支持 AES-256）
+#[derive(Clone, Copy, Debug, Default, ValueEnum, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NewEncryption {
+    Aes128,
+    Aes192,
+    #[default]
+    Aes256,
+}
+
+impl NewEncryption {
+    /// 显示名称
+    #[must_use]
+    pub const fn name(self) -> &'static str {
+        match self {
+            Self::Aes128 => "AES-128",
+            Self::Aes192 => "AES-192",
+            Self::Aes256 => "AES-256",
+        }
+    }
+}
+
+/// 重新打包选项
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RewrapOptions {
+    /// 新压缩包的路径（必须不存在），格式与原压缩包相同
+    pub output: PathBuf,
+    /// 新压缩包的加密方式，`None` 表示不加密
+    pub encryption: Option<NewEncryption>,
+    /// 加密使用的新密码，不写入会话文件（恢复会话时需要重新提供）
+    #[serde(skip)]
+    pub password: Option<String>,
+    /// ZIP 原始条目名的编码，新压缩包中的条目名总是 UTF-8
+    pub encoding: NameEncoding,
+}
+
+impl Default for RewrapOptions {
+    fn default() -> Self {
+        Self {
+            output: PathBuf::new(),
+            encryption: None,
+            password: None,
+            encoding: NameEncoding::default(),
+        }
+    }
+}
+
+impl RewrapOptions {
+    /// 写入 `output`，不加密
+    #[must_use]
+    pub fn new(output: impl Into<PathBuf>) -> Self {
+        Self {
+            output: output.into(),
+            ..Self::default()
+        }
+    }
+
+    /// 用新密码加密，如 `RewrapOptions::new("out.zip").encrypt("新密码", NewEncryption::Aes256)`
+    #[must_use]
+    pub fn encrypt(mut self, password: impl Into<String>, encryption: NewEncryption) -> Self {
+        self.encryption = Some(encryption);
+        self.password = Some(password.into());
+        self
+    }
+
+    /// 加密时的新密码和加密方式，不加密时为 `None`
+    #[must_use]
+    pub fn new_password(&self) -> Option<(&str, NewEncryption)> {
+        Some((self.password.as_deref()?, self.encryption?))
+    }
+
+    /// 破解前检查：输出文件不能已存在，扩展名与原压缩包格式相同，加密时已提供新密码，7z 只能使用 AES-256
+    pub fn check(&self, archive: &Path) -> Result<(), CrackError> {
+        let format = ArchiveFormat::detect(archive);
+        if ArchiveFormat::detect(&self.output) != format {
+            return Err(CrackError::InvalidRewrap(format!(
+                "输出文件 {} 的扩展名应与原压缩包格式相同",
+                self.output.display()
+            )));
+        }
+        if self.output.exists() {
+            return Err(CrackError::InvalidRewrap(format!(
+                "输出文件已存在: {}",
+                self.output.display()
+            )));
+        }
+        let Some(encryption) = self.encryption else {
+            return Ok(());
+        };
+        if self.password.as_deref().is_none_or(str::is_empty) {
+            return Err(CrackError::InvalidRewrap(
+                "加密重新打包需要提供新密码".to_string(),
+            ));
+        }
+        if encryption != NewEncryption::Aes256 && format == Some(ArchiveFormat::SevenZ) {
+            return Err(CrackError::InvalidRewrap(format!(
+                "7z 不支持 {} 加密，只能使用 AES-256",
+                encryption.name()
+            )));
+        }
+        Ok(())
+    }
+
+    /// 新压缩包的加密方式说明
+    #[must_use]
+    pub const fn describe(&self) -> &'static str {
+        match self.encryption {
+            Some(encryption) => encryption.name(),
+            None => "不加密",
+        }
+    }
+}
+
+/// 重新打包结果
+#[derive(Debug, Clone, Default)]
+pub struct RewrapReport {
+    /// 新压缩包的路径
+    pub output: PathBuf,
+    /// 写入的条目数量（含目录）
+    pub entries: usize,
+    /// 条目解压后的总字节数
+    pub bytes: u64,
+    /// 原压缩方法无法写入、改用 Deflate 的条目及原方法（ZIP 的 LZMA、Deflate64 等）
+    pub recompressed: Vec<(String, String)>,
+}
+
+/// 向处理器提供原密码和新压缩包的选项，并记录写入的条目
+pub struct Rewrapper<'a> {
+    options: &'a RewrapOptions,
+    password: String,
+    /// 与 `password` 不同的条目密码（按条目破解时）
+    entry_passwords: Vec<(usize, String)>,
+    report: RewrapReport,
+}
+
+impl<'a> Rewrapper<'a> {
+    /// 所有条目使用同一个原密码
+    #[must_use]
+    pub fn new(options: &'a RewrapOptions, password: &str) -> Self {
+        Self {
+            options,
+            password: password.to_string(),
+            entry_passwords: Vec::new(),
+            report: RewrapReport {
+                output: options.output.clone(),
+                ..RewrapReport::default()
+            },
+        }
+    }
+
+    /// 条目 `index` 使用另一个原密码
+    pub fn set_password(&mut self, index: usize, password: &str) {
+        self.entry_passwords.push((index, password.to_string()));
+    }
+
+    /// 条目 `index` 的原密码
+    #[must_use]
+    pub fn password(&self, index: usize) -> &str {
+        self.entry_passwords
+            .iter()
+            .find(|(i, _)| *i == index)
+            .map_or(&self.password, |(_, password)| password)
+    }
+
+    #[must_use]
+    pub const fn options(&self) -> &'a RewrapOptions {
+        self.options
+    }
+
+    /// 新压缩包的路径
+    #[must_use]
+    pub fn output(&self) -> &Path {
+        &self.options.output
+    }
+
+    /// 按选项中的编码解码 ZIP 原始条目名
+    #[must_use]
+    pub fn decode_name(&self, raw: &[u8]) -> String {
+        self.options.encoding.decode(raw)
+    }
+
+    /// 记录条目 `name` 的原压缩方法 `method` 无法写入，改用了其他方法
+    pub fn record_recompressed(&mut self, name: &str, method: impl Into<String>) {
+        self.report
+            .recompressed
+            .push((name.to_string(), method.into()));
+    }
+
+    /// 记录写入了一个解压后为 `bytes` 字节的条目
+    pub const fn record(&mut self, bytes: u64) {
+        self.report.entries += 1;
+        self.report.bytes += bytes;
+    }
+
+    #[must_use]
+    pub fn finish(self) -> RewrapReport {
+        self.report
+    }
+}
@@ -0,0 +1,456 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::archive::Verifier;
+use crate::archive::common::to_hex;
+use crate::config::{CrackConfig, decimal};
+use crate::observer::{CrackControl, CrackObserver};
+use crate::paths::data_dir;
+
+/// 会话文件自动保存间隔
+const SAVE_INTERVAL: Duration = Duration::from_secs(30);
+
+/// 断点分块的目标耗时
+const CHUNK_TARGET: Duration = Duration::from_secs(2);
+
+/// 断点分块的大小上限
+const MAX_CHUNK_SIZE: usize = 1 << 22;
+
+/// 会话文件头
+const SESSION_HEADER: &str = "# archive_cracker session v2";
+
+/// 安装 Ctrl-C 处理器：收到信号后通过 `observer` 报告并取消 `control`，攻击循环在安全点停止并保存会话
+///
+/// 再次收到信号时调用 `on_repeat`（命令行在这里直接退出进程）
+pub fn install_interrupt_handler(
+    control: &CrackControl,
+    observer: impl CrackObserver + Send + 'static,
+    on_repeat: impl Fn() + Send + 'static,
+) -> Result<(), ctrlc::Error> {
+    let control = control.clone();
+    let interrupted = AtomicBool::new(false);
+    ctrlc::set_handler(move || {
+        if interrupted.swap(true, Ordering::Relaxed) {
+            on_repeat();
+            return;
+        }
+        observer.message("\n收到中断信号，正在保存会话...");
+        control.cancel();
+    })
+}
+
+/// 会话所处的攻击阶段
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SessionStage {
+    /// 尚未进入需要断点续传的阶段
+    Start,
+    /// 字典攻击
+    Dictionary,
+    /// 暴力破解
+    Bruteforce,
+}
+
+/// 断点续传会话：保存运行参数和各阶段已完成的进度
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Session {
+    /// 会话名（即文件名，不写入文件）
+    #[serde(skip)]
+    pub name: String,
+    /// 攻击计划模式：正在执行的计划阶段序号（之前的阶段已完成）
+    pub plan_stage: usize,
+    /// 当前阶段
+    pub stage: SessionStage,
+    /// 字典阶段：正在处理的字典序号
+    pub dict_source: usize,
+    /// 字典阶段：当前字典中已测试完的条目数
+    pub dict_line: u64,
+    /// 暴力破解阶段：当前长度
+    pub bf_length: u32,
+    /// 暴力破解阶段：当前长度下 `[0, bf_done)` 的索引已全部测试
+    #[serde(with = "decimal")]
+    pub bf_done: u128,
+    /// 运行配置（放在最后，TOML 中作为子表）
+    pub config: CrackConfig,
+}
+
+impl Session {
+    /// 以给定配置创建新会话
+    #[must_use]
+    pub fn new(name: &str, config: &CrackConfig) -> Self {
+        Self {
+            name: name.to_string(),
+            config: config.clone(),
+            plan_stage: 0,
+            stage: SessionStage::Start,
+            dict_source: 0,
+            dict_line: 0,
+            bf_length: 0,
+            bf_done: 0,
+        }
+    }
+
+    /// 字典阶段的断点：(字典序号, 已完成条目数)
+    #[must_use]
+    pub fn dictionary_resume(&self) -> (usize, u64) {
+        if self.stage == SessionStage::Dictionary {
+            (self.dict_source, self.dict_line)
+        } else {
+            (0, 0)
+        }
+    }
+
+    /// 暴力破解阶段的断点：(长度, 已完成索引)
+    #[must_use]
+    pub fn bruteforce_resume(&self) -> (u32, u128) {
+        if self.stage == SessionStage::Bruteforce {
+            (self.bf_length, self.bf_done)
+        } else {
+            (0, 0)
+        }
+    }
+
+    /// 清空字典和暴力破解的断点
+    const fn reset_progress(&mut self) {
+        self.dict_source = 0;
+        self.dict_line = 0;
+        self.bf_length = 0;
+        self.bf_done = 0;
+    }
+
+    /// 会话文件路径 (~/.`archive_cracker/sessions/<name>.session`)
+    pub fn path_for(name: &str) -> io::Result<PathBuf> {
+        if !Self::is_valid_name(name) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("会话名不能为空，也不能包含路径分隔符或 ..: {name}"),
+            ));
+        }
+        Ok(data_dir()?.join("sessions").join(format!("{name}.session")))
+    }
+
+    /// 会话名是否可以用作文件名（不能借助分隔符或 `..` 指向会话目录之外）
+    #[must_use]
+    pub fn is_valid_name(name: &str) -> bool {
+        !name.is_empty() && !name.contains(['/', '\\', '\0']) && !name.contains("..")
+    }
+
+    /// 默认会话名：压缩包文件名（不含扩展名）加完整路径的哈希，如 `data-1a2b3c4d`
+    ///
+    /// 不同目录下的同名压缩包（`a/data.zip` 和 `b/data.7z`）不会共用会话
+    #[must_use]
+    pub fn default_name(archive_path: &Path) -> String {
+        let stem = archive_path.file_stem().map_or_else(
+            || "default".to_string(),
+            |s| s.to_string_lossy().to_string(),
+        );
+        let full_path = fs::canonicalize(archive_path)
+            .or_else(|_| std::path::absolute(archive_path))
+            .unwrap_or_else(|_| archive_path.to_path_buf());
+        let hash = Sha256::digest(full_path.as_os_str().as_encoded_bytes());
+        // `a..b.zip` 这样的文件名不能直接作为会话名
+        format!("{}-{}", stem.replace("..", "_"), to_hex(&hash[..4]))
+    }
+
+    /// 加载会话
+    pub fn load(name: &str) -> io::Result<Self> {
+        let content = fs::read_to_string(Self::path_for(name)?)?;
+        Self::parse(name, &content).map_err(|e| {
+            io::Error::new(io::ErrorKind::InvalidData, format!("会话文件格式错误: {e}"))
+        })
+    }
+
+    /// 保存会话（先写临时文件再重命名，避免中途退出留下损坏的文件）
+    pub fn save(&self) -> io::Result<()> {
+        let path = Self::path_for(&self.name)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let tmp = path.with_extension("session.tmp");
+        fs::write(&tmp, self.serialize()?)?;
+        fs::rename(tmp, path)
+    }
+
+    /// 删除会话文件（破解完成后调用）
+    pub fn remove(&self) {
+        if let Ok(path) = Self::path_for(&self.name) {
+            let _ = fs::remove_file(path);
+        }
+    }
+
+    /// 序列化为 TOML：文件头注释、断点和完整配置
+    ///
+    /// 库调用方提供的自定义验证函数无法保存，恢复时改为自动选择目标文件
+    fn serialize(&self) -> io::Result<String> {
+        let mut session = self.clone();
+        if session
+            .config
+            .expect
+            .as_ref()
+            .is_some_and(|expect| matches!(expect.verifier, Verifier::Custom { .. }))
+        {
+            session.config.expect = None;
+        }
+        let content = toml::to_string(&session).map_err(io::Error::other)?;
+        Ok(format!("{SESSION_HEADER}\n{content}"))
+    }
+
+    fn parse(name: &str, content: &str) -> Result<Self, toml::de::Error> {
+        let mut session: Self = toml::from_str(content)?;
+        session.name = name.to_string();
+        session.config.session = Some(name.to_string());
+        Ok(session)
+    }
+}
+
+/// 运行中的会话记录器：攻击循环在安全点更新进度，按间隔自动保存
+pub struct SessionTracker {
+    session: Mutex<Session>,
+    last_save: Mutex<Instant>,
+}
+
+impl SessionTracker {
+    #[must_use]
+    pub fn new(session: Session) -> Self {
+        Self {
+            session: Mutex::new(session),
+            last_save: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// 当前会话快照
+    #[must_use]
+    pub fn snapshot(&self) -> Session {
+        self.session.lock().expect("会话锁已损坏").clone()
+    }
+
+    /// 进入攻击计划的第 `index` 个阶段，重置阶段进度（恢复会话时同一阶段保留断点）
+    pub fn enter_plan_stage(&self, index: usize) {
+        let mut session = self.session.lock().expect("会话锁已损坏");
+        if session.plan_stage != index {
+            session.plan_stage = index;
+            session.stage = SessionStage::Start;
+            session.reset_progress();
+        }
+    }
+
+    /// 进入新阶段，重置该阶段的进度
+    pub fn enter_stage(&self, stage: SessionStage) {
+        let mut session = self.session.lock().expect("会话锁已损坏");
+        if session.stage != stage {
+            session.stage = stage;
+            session.reset_progress();
+        }
+    }
+
+    /// 记录字典进度，返回到期自动保存时的错误
+    pub fn update_dictionary(&self, source: usize, line: u64) -> io::Result<()> {
+        self.update(|session| {
+            session.dict_source = source;
+            session.dict_line = line;
+        })
+    }
+
+    /// 记录暴力破解进度，返回到期自动保存时的错误
+    pub fn update_bruteforce(&self, length: u32, done: u128) -> io::Result<()> {
+        self.update(|session| {
+            session.bf_length = length;
+            session.bf_done = done;
+        })
+    }
+
+    /// 立即保存
+    pub fn save(&self) -> io::Result<()> {
+        *self.last_save.lock().expect("会话锁已损坏") = Instant::now();
+        self.snapshot().save()
+    }
+
+    /// 破解结束（找到密码或穷尽搜索空间）后删除会话文件
+    pub fn finish(&self) {
+        self.snapshot().remove();
+    }
+
+    fn update(&self, apply: impl FnOnce(&mut Session)) -> io::Result<()> {
+        apply(&mut self.session.lock().expect("会话锁已损坏"));
+
+        let due = self.last_save.lock().expect("会话锁已损坏").elapsed() >= SAVE_INTERVAL;
+        if due { self.save() } else { Ok(()) }
+    }
+}
+
+/// 自适应的断点分块大小
+///
+/// 攻击循环按块并行测试，每块完成后记录一次断点。块大小按实际速度调整到约 2 秒一块，
+/// 慢速格式（如 7z）也能及时记录进度，快速格式不会因频繁同步而变慢
+pub struct ChunkSizer {
+    size: usize,
+    min: usize,
+}
+
+impl ChunkSizer {
+    #[must_use]
+    pub fn new() -> Self {
+        let min = rayon::current_num_threads();
+        Self { size: min, min }
+    }
+
+    /// 当前块大小
+    #[must_use]
+    pub const fn size(&self) -> usize {
+        self.size
+    }
+
+    /// 根据上一块的耗时调整块大小
+    pub fn adjust(&mut self, elapsed: Duration) {
+        self.size = if elapsed < CHUNK_TARGET {
+            (self.size * 2).min(MAX_CHUNK_SIZE)
+        } else {
+            (self.size / 2).max(self.min)
+        };
+    }
+}
+
+impl Default for ChunkSizer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{KeyspaceSelection, ServeConfig};
+    use crate::rewrap::{NewEncryption, RewrapOptions};
+    use std::time::SystemTime;
+
+    #[test]
+    fn session_round_trips_through_toml() {
+        let mut config = CrackConfig::builder("dir/data.zip")
+            .build()
+            .expect("默认配置有效");
+        config.keyspace = Some(KeyspaceSelection::Range {
+            skip: Some(u128::MAX - 1),
+            limit: None,
+        });
+        config.horizon = Some(Duration::from_hours(1));
+        config.limits.deadline = Some(SystemTime::UNIX_EPOCH + Duration::from_secs(1_800_000_123));
+        config.status_interval = None;
+        config.per_entry = true;
+        config.serve = Some(ServeConfig {
+            addr: "127.0.0.1:7700".to_string(),
+            ..ServeConfig::default()
+        });
+
+        let mut session = Session::new("data", &config);
+        session.stage = SessionStage::Bruteforce;
+        session.bf_length = 30;
+        session.bf_done = u128::MAX;
+
+        let content = session.serialize().expect("可以序列化");
+        assert!(content.starts_with(SESSION_HEADER));
+        let parsed = Session::parse("data", &content).expect("可以解析");
+        assert_eq!(parsed.stage, SessionStage::Bruteforce);
+        assert_eq!(parsed.bruteforce_resume(), (30, u128::MAX));
+        assert_eq!(
+            parsed.config,
+            CrackConfig {
+                session: Some("data".to_string()),
+                ..config
+            }
+        );
+    }
+
+    #[test]
+    fn session_names_cannot_leave_session_dir() {
+        assert!(Session::is_valid_name("data-1a2b3c4d"));
+        for name in ["", "..", "../data", "a/b", "a\\b", "a..b"] {
+            assert!(!Session::is_valid_name(name), "{name}");
+            assert!(Session::path_for(name).is_err(), "{name}");
+        }
+        assert!(Session::is_valid_name(&Session::default_name(Path::new(
+            "dir/v1..2.zip"
+        ))));
+    }
+
+    #[test]
+    fn session_never_contains_rewrap_password() {
+        let mut config = CrackConfig::builder("data.zip")
+            .build()
+            .expect("默认配置有效");
+        config.rewrap =
+            Some(RewrapOptions::new("out.zip").encrypt("s3cret!", NewEncryption::Aes128));
+
+        let content = Session::new("data", &config)
+            .serialize()
+            .expect("可以序列化");
+        assert!(!content.contains("s3cret!"));
+        let rewrap = Session::parse("data", &content)
+            .expect("可以解析")
+            .config
+            .rewrap
+            .expect("保留重新打包选项");
+        assert_eq!(rewrap.encryption, Some(NewEncryption::Aes128));
+        assert_eq!(rewrap.password, None);
+    }
+
+    #[test]
+    fn session_never_contains_serve_token() {
+        let config = CrackConfig::builder("data.zip")
+            .serve("0.0.0.0:7700")
+            .token("t0ken!")
+            .build()
+            .expect("配置有效");
+
+        let content = Session::new("data", &config)
+            .serialize()
+            .expect("可以序列化");
+        assert!(!content.contains("t0ken!"));
+        let serve = Session::parse("data", &content)
+            .expect("可以解析")
+            .config
+            .serve
+            .expect("保留协调进程选项");
+        assert!(serve.require_token);
+        assert_eq!(serve.token, None);
+    }
+
+    #[test]
+    fn session_drops_custom_verifier() {
+        let mut config = CrackConfig::builder("data.zip")
+            .build()
+            .expect("默认配置有效");
+        config.expect = Some(crate::config::ExpectedContent {
+            entry: "a.bin".to_string(),
+            verifier: Verifier::custom(1, |data: &[u8]| data.starts_with(b"x")),
+        });
+
+        let content = Session::new("data", &config)
+            .serialize()
+            .expect("自定义验证函数不影响保存");
+        let parsed = Session::parse("data", &content).expect("可以解析");
+        assert!(parsed.config.expect.is_none());
+    }
+
+    #[test]
+    fn default_name_distinguishes_directories() {
+        let a = Session::default_name(Path::new("a/data.zip"));
+        let b = Session::default_name(Path::new("b/data.7z"));
+        assert!(a.starts_with("data-"));
+        assert!(b.starts_with("data-"));
+        assert_ne!(a, b);
+        assert_eq!(a, Session::default_name(Path::new("a/data.zip")));
+    }
+
+    #[test]
+    fn session_rejects_legacy_format() {
+        let legacy = "# archive_cracker session v1\narchive_path=a.zip\nlength=4\n";
+        assert!(Session::parse("a", legacy).is_err());
+    }
+}
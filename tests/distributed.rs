@@ -0,0 +1,165 @@
+//! 协调进程与 worker 的端到端测试：在本机回环地址上运行协调进程和两个 worker
+
+use std::io::Write;
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use archive_cracker::archive::{ArchiveFormat, get_handler};
+use archive_cracker::attack::Budget;
+use archive_cracker::charset::get_combined_charset;
+use archive_cracker::cli::Charset;
+use archive_cracker::distributed::{
+    CoordinatorParams, CoordinatorResult, WorkerSummary, coordinate, run_worker,
+};
+use archive_cracker::keyspace::bruteforce_keyspace;
+use archive_cracker::observer::{CrackControl, CrackObserver, Monitor};
+use zip::ZipWriter;
+use zip::unstable::write::FileOptionsExt;
+use zip::write::SimpleFileOptions;
+
+const WORKERS: usize = 2;
+const CHUNK_SIZE: u64 = 50;
+
+/// 生成用 `password` 加密（ZipCrypto，调试构建下也足够快）的测试压缩包
+fn encrypted_zip(name: &str, password: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!(
+        "archive_cracker_test_{}_{name}",
+        std::process::id()
+    ));
+    std::fs::create_dir_all(&dir).expect("可以创建临时目录");
+    let path = dir.join("secret.zip");
+
+    let file = std::fs::File::create(&path).expect("可以创建压缩包");
+    let mut zip = ZipWriter::new(file);
+    let options = SimpleFileOptions::default()
+        .with_deprecated_encryption(password.as_bytes())
+        .expect("密码不为空");
+    zip.start_file("notes.txt", options).expect("可以添加条目");
+    zip.write_all("分布式测试的明文内容\n".repeat(20).as_bytes())
+        .expect("可以写入条目");
+    zip.finish().expect("可以写完压缩包");
+    path
+}
+
+/// 所有 worker 连接后才继续（协调进程暂停时只让 worker 等待），避免一个 worker 在另一个连接前就做完全部任务
+struct ResumeWhenConnected {
+    control: CrackControl,
+    connected: AtomicUsize,
+}
+
+impl CrackObserver for ResumeWhenConnected {
+    fn message(&self, message: &str) {
+        if message.starts_with("worker 已连接")
+            && self.connected.fetch_add(1, Ordering::Relaxed) + 1 == WORKERS
+        {
+            self.control.resume();
+        }
+    }
+}
+
+/// 在 127.0.0.1 的随机端口上运行协调进程，同时运行 `WORKERS` 个 worker 线程
+fn run_cluster(archive: &Path) -> (CoordinatorResult, Vec<WorkerSummary>) {
+    let charsets = [Charset::Lower];
+    let (min_len, max_len) = (1, 2);
+    let (_, chars) = get_combined_charset(&charsets);
+    let bruteforce_total =
+        bruteforce_keyspace(chars.len(), min_len, max_len).expect("键空间不会溢出");
+
+    let format = ArchiveFormat::detect(archive).expect("ZIP 格式");
+    let handler = get_handler(format);
+    let target = handler
+        .detect_target(archive)
+        .expect("可以读取压缩包")
+        .expect("有加密条目");
+
+    let listener = TcpListener::bind("127.0.0.1:0").expect("可以监听本机端口");
+    let addr = listener.local_addr().expect("已绑定地址").to_string();
+
+    thread::scope(|scope| {
+        // 必须在运行协调进程之前启动 worker
+        #[allow(clippy::needless_collect)]
+        let workers: Vec<_> = (0..WORKERS)
+            .map(|_| scope.spawn(|| run_worker(&addr, None, &Monitor::silent())))
+            .collect();
+
+        let observer = ResumeWhenConnected {
+            control: CrackControl::new(),
+            connected: AtomicUsize::new(0),
+        };
+        observer.control.pause();
+        let monitor = Monitor::new(&observer, observer.control.clone());
+        let result = coordinate(
+            &listener,
+            &CoordinatorParams {
+                token: None,
+                archive_path: archive,
+                dict_paths: &[],
+                charsets: &charsets,
+                min_len,
+                max_len,
+                bruteforce_total,
+                targets: std::slice::from_ref(&target),
+                handler: handler.as_ref(),
+                chunk_size: CHUNK_SIZE,
+                lease_timeout: Duration::from_mins(2),
+                budget: Budget::unlimited(),
+                monitor: &monitor,
+            },
+        )
+        .expect("协调进程正常结束");
+
+        let summaries = workers
+            .into_iter()
+            .map(|worker| {
+                worker
+                    .join()
+                    .expect("worker 线程未崩溃")
+                    .expect("worker 正常结束")
+            })
+            .collect();
+        (result, summaries)
+    })
+}
+
+#[test]
+fn workers_find_password_and_each_chunk_is_leased_once() {
+    let archive = encrypted_zip("found", "ab");
+    let (result, summaries) = run_cluster(&archive);
+
+    let (password, _) = result.found.expect("找到密码");
+    assert_eq!(password, "ab");
+    assert!(!result.interrupted);
+    assert_eq!(result.leases, result.chunks);
+    assert_eq!(
+        summaries
+            .iter()
+            .filter_map(|summary| summary.password.as_deref())
+            .collect::<Vec<_>>(),
+        ["ab"]
+    );
+    let _ = std::fs::remove_dir_all(archive.parent().expect("在临时目录中"));
+}
+
+#[test]
+fn workers_cover_whole_keyspace_without_repeating_chunks() {
+    // 密码长度超出键空间：所有任务块都会完成
+    let archive = encrypted_zip("exhausted", "abc");
+    let (result, summaries) = run_cluster(&archive);
+
+    let total = 26 + 26 * 26;
+    assert!(result.found.is_none());
+    assert_eq!(result.chunks, u64::div_ceil(total, CHUNK_SIZE));
+    assert_eq!(result.leases, result.chunks);
+    assert_eq!(result.total_tested, total);
+    assert_eq!(
+        summaries
+            .iter()
+            .map(|summary| summary.chunks_done)
+            .sum::<u64>(),
+        result.chunks
+    );
+    let _ = std::fs::remove_dir_all(archive.parent().expect("在临时目录中"));
+}